@@ -0,0 +1,187 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{component, entity_id::EntityId, raws::Raw};
+
+#[derive(Deserialize, Clone, Debug)]
+pub enum Condition {
+    HasTarget,
+    //distances are in blocks
+    TargetWithin(u32),
+    PlayerWithin(u32),
+    //percent of max health
+    HealthBelow(u32),
+    Not(Box<Condition>),
+}
+
+#[derive(Deserialize, Clone, Debug, PartialEq)]
+pub enum Action {
+    Idle,
+    Wander,
+    Chase,
+    Flee,
+    Attack,
+    AcquireTarget { range: u32 },
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub enum BehaviorNode {
+    //succeeds if every child succeeds, stopping at the first failure and undoing what the children chose
+    Sequence(Vec<BehaviorNode>),
+    //succeeds at the first child that succeeds
+    Selector(Vec<BehaviorNode>),
+    Condition(Condition),
+    Action(Action),
+}
+
+#[derive(Deserialize, Debug)]
+pub struct BehaviorTree {
+    canonical_name: String,
+    root: BehaviorNode,
+}
+
+impl BehaviorTree {
+    pub fn new(raw: &Raw) -> Result<BehaviorTree, serde_json::Error> {
+        let res: BehaviorTree = serde_json::from_value(raw.dat().clone())?;
+        Ok(res)
+    }
+    pub fn get_canonical_name(&self) -> &str {
+        &self.canonical_name
+    }
+    pub fn get_root(&self) -> &BehaviorNode {
+        &self.root
+    }
+}
+
+/**
+ * Lets an entity think for itself using a behavior tree from the raws
+ */
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Ai {
+    pub tree: String,
+    pub target: Option<EntityId>,
+}
+
+impl Ai {
+    pub fn new(tree: &str) -> Self {
+        Ai {
+            tree: tree.to_owned(),
+            target: None,
+        }
+    }
+}
+
+impl component::ComponentType for Ai {}
+
+/**
+ * What kind of creature or character a non player entity is, used by quests
+ */
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Npc {
+    pub kind: String,
+}
+
+impl component::ComponentType for Npc {}
+
+/**
+ * A kind of creature and what it starts out with when it spawns
+ */
+#[derive(Deserialize, Debug)]
+pub struct Creature {
+    //also the Npc kind quests count kills by
+    canonical_name: String,
+    //canonical name of the behavior tree it thinks with
+    ai: String,
+    health: i32,
+    #[serde(default)]
+    loot: Option<String>,
+    #[serde(default)]
+    xp: u64,
+}
+
+impl Creature {
+    pub fn new(raw: &Raw) -> Result<Creature, serde_json::Error> {
+        let res: Creature = serde_json::from_value(raw.dat().clone())?;
+        Ok(res)
+    }
+    pub fn get_canonical_name(&self) -> &str {
+        &self.canonical_name
+    }
+    pub fn get_ai(&self) -> &str {
+        &self.ai
+    }
+    pub fn get_health(&self) -> i32 {
+        self.health
+    }
+    pub fn get_loot(&self) -> Option<&str> {
+        self.loot.as_deref()
+    }
+    pub fn get_xp(&self) -> u64 {
+        self.xp
+    }
+}
+
+#[derive(Deserialize, Debug)]
+pub struct SpawnEntry {
+    //canonical creature name
+    creature: String,
+    weight: u32,
+}
+
+/**
+ * Which creatures turn up in a region, picked by weight
+ */
+#[derive(Deserialize, Debug)]
+pub struct SpawnTable {
+    canonical_name: String,
+    //nothing more spawns while this many npcs are already around the spot
+    max_nearby: usize,
+    entries: Vec<SpawnEntry>,
+}
+
+impl SpawnTable {
+    pub fn new(raw: &Raw) -> Result<SpawnTable, serde_json::Error> {
+        let res: SpawnTable = serde_json::from_value(raw.dat().clone())?;
+        Ok(res)
+    }
+    pub fn get_canonical_name(&self) -> &str {
+        &self.canonical_name
+    }
+    pub fn get_max_nearby(&self) -> usize {
+        self.max_nearby
+    }
+    /**
+     * Pick a creature with a roll between 0 and 1
+     */
+    pub fn choose(&self, roll: f32) -> Option<&str> {
+        let total: u32 = self.entries.iter().map(|e| e.weight).sum();
+        let mut pick = (roll * total as f32) as u32;
+        self.entries
+            .iter()
+            .filter(|e| e.weight > 0)
+            .find(|e| {
+                if pick < e.weight {
+                    true
+                } else {
+                    pick -= e.weight;
+                    false
+                }
+            })
+            .map(|e| e.creature.as_str())
+    }
+}
+
+#[test]
+fn test_spawn_table_choose() {
+    let table: SpawnTable = serde_json::from_str(
+        r#"{ "canonical_name" : "test", "max_nearby" : 2, "entries" : [
+            { "creature" : "goblin", "weight" : 3 },
+            { "creature" : "nothing", "weight" : 0 },
+            { "creature" : "wolf", "weight" : 1 }
+        ] }"#,
+    )
+    .unwrap();
+    assert_eq!(table.choose(0.0), Some("goblin"));
+    assert_eq!(table.choose(0.7), Some("goblin"));
+    assert_eq!(table.choose(0.8), Some("wolf"));
+    assert_eq!(table.choose(0.99), Some("wolf"));
+}
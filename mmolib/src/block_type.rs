@@ -4,6 +4,10 @@ use crate::effect;
 use crate::hashing::string_hash;
 use crate::{raws::Raw, resource};
 pub type BlockTypeId = u64;
+
+pub fn block_type_id(canonical_name: &str) -> BlockTypeId {
+    string_hash(canonical_name)
+}
 #[derive(Deserialize, Clone, Debug)]
 #[repr(u8)]
 pub enum BlockLayer {
@@ -20,6 +24,46 @@ pub struct BlockType {
     descriptive_name: String,
     layer: BlockLayer,
     resource: resource::ResourceId,
+    //canonical name of the block left behind when this block is broken
+    #[serde(default)]
+    breaks_into: Option<String>,
+    #[serde(default)]
+    placeable: bool,
+    #[serde(default)]
+    indestructible: bool,
+    //strength and length of the effect given to anything standing on an effect layer block
+    #[serde(default = "default_effect_magnitude")]
+    effect_magnitude: i32,
+    #[serde(default = "default_effect_duration")]
+    effect_duration_ticks: u64,
+    //players can bind their respawn point next to this block
+    #[serde(default)]
+    respawn_anchor: bool,
+    //depth of water layer blocks, sources never run dry
+    #[serde(default)]
+    fluid_level: u8,
+    #[serde(default)]
+    fluid_source: bool,
+    //percent chance each fire tick to catch from a burning neighbour
+    #[serde(default)]
+    flammability: u32,
+    //canonical name of what is left once it has burnt down, the fire block's own if unset
+    #[serde(default)]
+    burns_into: Option<String>,
+    #[serde(default = "default_burn_ticks")]
+    burn_ticks: u64,
+}
+
+fn default_effect_magnitude() -> i32 {
+    1
+}
+
+fn default_effect_duration() -> u64 {
+    20
+}
+
+fn default_burn_ticks() -> u64 {
+    100
 }
 
 impl BlockType {
@@ -39,4 +83,37 @@ impl BlockType {
     pub fn get_layer(&self) -> BlockLayer {
         self.layer.clone()
     }
+    pub fn get_breaks_into(&self) -> Option<BlockTypeId> {
+        self.breaks_into.as_deref().map(string_hash)
+    }
+    pub fn is_placeable(&self) -> bool {
+        self.placeable
+    }
+    pub fn is_indestructible(&self) -> bool {
+        self.indestructible
+    }
+    pub fn get_effect_magnitude(&self) -> i32 {
+        self.effect_magnitude
+    }
+    pub fn get_effect_duration_ticks(&self) -> u64 {
+        self.effect_duration_ticks
+    }
+    pub fn is_respawn_anchor(&self) -> bool {
+        self.respawn_anchor
+    }
+    pub fn get_fluid_level(&self) -> u8 {
+        self.fluid_level
+    }
+    pub fn is_fluid_source(&self) -> bool {
+        self.fluid_source
+    }
+    pub fn get_flammability(&self) -> u32 {
+        self.flammability
+    }
+    pub fn get_burns_into(&self) -> Option<BlockTypeId> {
+        self.burns_into.as_deref().map(string_hash)
+    }
+    pub fn get_burn_ticks(&self) -> u64 {
+        self.burn_ticks
+    }
 }
@@ -0,0 +1,133 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{component, raws::Raw};
+
+pub const MIN_NAME_LENGTH: usize = 3;
+pub const MAX_NAME_LENGTH: usize = 16;
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Appearance {
+    pub skin: String,
+    pub hair: String,
+}
+
+/**
+ * What a player picks when creating a character
+ */
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct CharacterCreation {
+    pub name: String,
+    //canonical names of raws
+    pub class: String,
+    pub race: String,
+    pub appearance: Appearance,
+}
+
+/**
+ * Who a character is, replicated so others can see it
+ */
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Character {
+    pub name: String,
+    pub class: String,
+    pub race: String,
+    pub appearance: Appearance,
+}
+
+impl component::ComponentType for Character {}
+
+#[derive(Deserialize, Debug)]
+pub struct CharacterClass {
+    canonical_name: String,
+    descriptive_name: String,
+    base_health: i32,
+    base_damage: i32,
+    #[serde(default)]
+    base_armor: i32,
+    //canonical item names and counts every new character of this class gets
+    #[serde(default)]
+    starting_items: Vec<(String, u32)>,
+}
+
+impl CharacterClass {
+    pub fn new(raw: &Raw) -> Result<CharacterClass, serde_json::Error> {
+        let res: CharacterClass = serde_json::from_value(raw.dat().clone())?;
+        Ok(res)
+    }
+    pub fn get_canonical_name(&self) -> &str {
+        &self.canonical_name
+    }
+    pub fn get_descriptive_name(&self) -> &str {
+        &self.descriptive_name
+    }
+    pub fn get_base_health(&self) -> i32 {
+        self.base_health
+    }
+    pub fn get_base_damage(&self) -> i32 {
+        self.base_damage
+    }
+    pub fn get_base_armor(&self) -> i32 {
+        self.base_armor
+    }
+    pub fn get_starting_items(&self) -> &[(String, u32)] {
+        &self.starting_items
+    }
+}
+
+#[derive(Deserialize, Debug)]
+pub struct Race {
+    canonical_name: String,
+    descriptive_name: String,
+    #[serde(default)]
+    health_bonus: i32,
+    #[serde(default = "crate::movement::default_ticks_per_step")]
+    ticks_per_step: u64,
+    skins: Vec<String>,
+    hairs: Vec<String>,
+}
+
+impl Race {
+    pub fn new(raw: &Raw) -> Result<Race, serde_json::Error> {
+        let res: Race = serde_json::from_value(raw.dat().clone())?;
+        Ok(res)
+    }
+    pub fn get_canonical_name(&self) -> &str {
+        &self.canonical_name
+    }
+    pub fn get_descriptive_name(&self) -> &str {
+        &self.descriptive_name
+    }
+    pub fn get_health_bonus(&self) -> i32 {
+        self.health_bonus
+    }
+    pub fn get_ticks_per_step(&self) -> u64 {
+        self.ticks_per_step
+    }
+    pub fn allows_appearance(&self, appearance: &Appearance) -> bool {
+        self.skins.contains(&appearance.skin) && self.hairs.contains(&appearance.hair)
+    }
+}
+
+pub fn is_valid_name(name: &str) -> bool {
+    (MIN_NAME_LENGTH..=MAX_NAME_LENGTH).contains(&name.chars().count())
+        && name.chars().all(|c| c.is_ascii_alphanumeric())
+}
+
+#[test]
+fn test_character_validation() {
+    assert!(is_valid_name("Bob123"));
+    assert!(!is_valid_name("Bo"));
+    assert!(!is_valid_name("Bob the great"));
+    let race: Race = serde_json::from_str(
+        r#"{ "canonical_name" : "human", "descriptive_name" : "Human", "skins" : ["pale"], "hairs" : ["brown"] }"#,
+    )
+    .unwrap();
+    assert!(race.allows_appearance(&Appearance {
+        skin: "pale".to_owned(),
+        hair: "brown".to_owned()
+    }));
+    assert!(!race.allows_appearance(&Appearance {
+        skin: "green".to_owned(),
+        hair: "brown".to_owned()
+    }));
+}
@@ -0,0 +1,127 @@
+use serde::{Deserialize, Serialize};
+
+//lines kept per channel for players who join later
+pub const HISTORY_CAP: usize = 100;
+
+/**
+ * Who gets to read a chat message
+ */
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Hash, Default)]
+pub enum ChatChannel {
+    #[default]
+    World,
+    //players within earshot of the sender
+    Local,
+    Party,
+    Guild,
+    //one other player, named in the message
+    Whisper,
+}
+
+/**
+ * A message as it was said, kept in channel history
+ */
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct ChatLine {
+    pub channel: ChatChannel,
+    pub username: String,
+    pub message: String,
+    //an action like /me waves rather than something said
+    #[serde(default)]
+    pub emote: bool,
+    //who a whisper went to
+    #[serde(default)]
+    pub to: Option<String>,
+    pub tick: u64,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum ChatCommand {
+    Say {
+        channel: ChatChannel,
+        message: String,
+    },
+    Whisper {
+        to: String,
+        message: String,
+    },
+    Me {
+        channel: ChatChannel,
+        action: String,
+    },
+    Who,
+}
+
+/**
+ * Work out what a line typed into a channel asks for. Lines not starting with a slash are said on that channel
+ */
+pub fn parse_command(line: &str, channel: ChatChannel) -> Result<ChatCommand, &'static str> {
+    let line = line.trim();
+    let rest = match line.strip_prefix('/') {
+        Some(rest) => rest,
+        None => {
+            return Ok(ChatCommand::Say {
+                channel,
+                message: line.to_owned(),
+            })
+        }
+    };
+    let (command, argument) = match rest.split_once(char::is_whitespace) {
+        Some((command, argument)) => (command, argument.trim()),
+        None => (rest, ""),
+    };
+    let say = |channel| ChatCommand::Say {
+        channel,
+        message: argument.to_owned(),
+    };
+    match command.to_lowercase().as_str() {
+        "w" | "whisper" | "tell" => match argument.split_once(char::is_whitespace) {
+            Some((to, message)) => Ok(ChatCommand::Whisper {
+                to: to.to_owned(),
+                message: message.trim().to_owned(),
+            }),
+            None => Err("usage: /w <user> <message>"),
+        },
+        "me" => Ok(ChatCommand::Me {
+            channel,
+            action: argument.to_owned(),
+        }),
+        "who" => Ok(ChatCommand::Who),
+        "s" | "say" => Ok(say(ChatChannel::World)),
+        "l" | "local" => Ok(say(ChatChannel::Local)),
+        "p" | "party" => Ok(say(ChatChannel::Party)),
+        "g" | "guild" => Ok(say(ChatChannel::Guild)),
+        _ => Err("unknown command"),
+    }
+}
+
+#[test]
+fn test_parse_command() {
+    assert_eq!(
+        parse_command("hello", ChatChannel::Party),
+        Ok(ChatCommand::Say {
+            channel: ChatChannel::Party,
+            message: "hello".to_owned()
+        })
+    );
+    assert_eq!(
+        parse_command("/w bob  see you at the shrine", ChatChannel::World),
+        Ok(ChatCommand::Whisper {
+            to: "bob".to_owned(),
+            message: "see you at the shrine".to_owned()
+        })
+    );
+    assert_eq!(
+        parse_command("/ME waves", ChatChannel::Guild),
+        Ok(ChatCommand::Me {
+            channel: ChatChannel::Guild,
+            action: "waves".to_owned()
+        })
+    );
+    assert_eq!(
+        parse_command("/who", ChatChannel::World),
+        Ok(ChatCommand::Who)
+    );
+    assert!(parse_command("/w bob", ChatChannel::World).is_err());
+    assert!(parse_command("/dance", ChatChannel::World).is_err());
+}
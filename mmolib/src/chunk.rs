@@ -14,12 +14,42 @@ pub struct Chunk {
     blocks: [[block_type::BlockTypeId; CHUNK_SIZE]; CHUNK_SIZE],
 }
 
+/**
+ * Climate of a part of the world. Temperature is in celsius, humidity from 0 to 1
+ */
+#[derive(Deserialize, Serialize, Debug, Clone)]
 pub struct LocationAttributes {
     temperature: f32,
     altitude: f32,
     humidity: f32,
 }
 
+impl LocationAttributes {
+    pub fn new(temperature: f32, altitude: f32, humidity: f32) -> Self {
+        LocationAttributes {
+            temperature,
+            altitude,
+            humidity,
+        }
+    }
+    pub fn get_temperature(&self) -> f32 {
+        self.temperature
+    }
+    pub fn get_altitude(&self) -> f32 {
+        self.altitude
+    }
+    pub fn get_humidity(&self) -> f32 {
+        self.humidity
+    }
+}
+
+impl Default for LocationAttributes {
+    //mild lowlands
+    fn default() -> Self {
+        LocationAttributes::new(15.0, 0.0, 0.5)
+    }
+}
+
 impl Chunk {
     pub fn new(dat: &[u8]) -> Result<Chunk, serde_cbor::Error> {
         let res = serde_cbor::from_slice(dat)?;
@@ -28,6 +58,21 @@ impl Chunk {
     pub fn new_from_array(blocks: [[block_type::BlockTypeId; CHUNK_SIZE]; CHUNK_SIZE]) -> Self {
         Self { blocks: blocks }
     }
+    pub fn to_bytes(&self) -> Result<Vec<u8>, serde_cbor::Error> {
+        serde_cbor::to_vec(self)
+    }
+    /**
+     * Get the block at a chunk relative position
+     */
+    pub fn get_block(&self, relative_position: Position) -> block_type::BlockTypeId {
+        self.blocks[relative_position.0 as usize][relative_position.1 as usize]
+    }
+    /**
+     * Set the block at a chunk relative position
+     */
+    pub fn set_block(&mut self, relative_position: Position, block_type_id: block_type::BlockTypeId) {
+        self.blocks[relative_position.0 as usize][relative_position.1 as usize] = block_type_id;
+    }
 }
 #[derive(Eq, Hash, PartialEq, Copy, Clone, Deserialize, Serialize, Debug)]
 pub struct ChunkId(u64);
@@ -61,6 +106,39 @@ pub fn position_of_chunk(chunk_id: ChunkId) -> Position {
     )
 }
 
+/**
+ * Number of king moves between two block positions
+ */
+pub fn chebyshev_distance(a: Position, b: Position) -> u32 {
+    a.0.abs_diff(b.0).max(a.1.abs_diff(b.1))
+}
+
+/**
+ * Whether a position lies in an area given by its inclusive (min, max) corners
+ */
+pub fn area_contains(area: (Position, Position), position: Position) -> bool {
+    position.0 >= area.0 .0
+        && position.0 <= area.1 .0
+        && position.1 >= area.0 .1
+        && position.1 <= area.1 .1
+}
+
+/**
+ * The area spanning two positions, grown by a margin on every side
+ */
+pub fn area_around(a: Position, b: Position, margin: u32) -> (Position, Position) {
+    (
+        (
+            a.0.min(b.0).saturating_sub(margin),
+            a.1.min(b.1).saturating_sub(margin),
+        ),
+        (
+            a.0.max(b.0).saturating_add(margin),
+            a.1.max(b.1).saturating_add(margin),
+        ),
+    )
+}
+
 pub fn distance_between_position(a: Position, b: Position) -> f32 {
     let (x1, y1) = a;
     let (x2, y2) = b;
@@ -74,6 +152,17 @@ fn test_chunks() {
     assert_eq!(convert_to_chunk_relative_position(p), (0, 0))
 }
 
+#[test]
+fn test_chunk_blocks() {
+    let mut c = Chunk::new_from_array([[0; CHUNK_SIZE]; CHUNK_SIZE]);
+    c.set_block((3, 4), 7);
+    assert_eq!(c.get_block((3, 4)), 7);
+    assert_eq!(c.get_block((4, 3)), 0);
+    let c = Chunk::new(&c.to_bytes().unwrap()).unwrap();
+    assert_eq!(c.get_block((3, 4)), 7);
+    assert_eq!(chebyshev_distance((3, 4), (5, 5)), 2);
+}
+
 impl Display for ChunkId {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let (x, y) = position_of_chunk(ChunkId(self.0));
@@ -0,0 +1,176 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{chunk, component, effect::Effect, entity_id::EntityId, position::Position};
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Health {
+    pub current: i32,
+    pub max: i32,
+}
+
+impl Health {
+    pub fn new(max: i32) -> Self {
+        Health { current: max, max }
+    }
+    pub fn is_dead(&self) -> bool {
+        self.current <= 0
+    }
+}
+
+impl component::ComponentType for Health {}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct AttackStats {
+    pub damage: i32,
+    //reach in blocks, 1 is adjacent
+    pub range: u32,
+    pub cooldown_ticks: u64,
+    pub next_attack_tick: u64,
+}
+
+impl AttackStats {
+    pub fn new(damage: i32, range: u32, cooldown_ticks: u64) -> Self {
+        AttackStats {
+            damage,
+            range,
+            cooldown_ticks,
+            next_attack_tick: 0,
+        }
+    }
+}
+
+impl Default for AttackStats {
+    //bare handed
+    fn default() -> Self {
+        AttackStats::new(1, 1, 10)
+    }
+}
+
+impl component::ComponentType for AttackStats {}
+
+#[derive(Clone, Debug, Serialize, Deserialize, Default)]
+pub struct Defense {
+    pub armor: i32,
+}
+
+impl component::ComponentType for Defense {}
+
+/**
+ * A player that has died and is waiting to come back
+ */
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Dead {
+    pub since_tick: u64,
+}
+
+impl component::ComponentType for Dead {}
+
+/**
+ * Where a player comes back after dying, the world spawn point if they have none
+ */
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RespawnPoint {
+    pub position: Position,
+}
+
+impl component::ComponentType for RespawnPoint {}
+
+/**
+ * What a player leaves behind when they die, holding their items until it rots away
+ */
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Corpse {
+    pub owner: EntityId,
+    //nobody else can loot it until then
+    pub owner_until_tick: u64,
+    pub despawn_tick: u64,
+}
+
+impl Corpse {
+    pub fn allows(&self, entity: EntityId, tick: u64) -> bool {
+        self.owner == entity || tick >= self.owner_until_tick
+    }
+}
+
+impl component::ComponentType for Corpse {}
+
+/**
+ * Something fired along a straight line. Clients can work out where it is from the
+ * origin, aim and step without waiting for every position update
+ */
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Projectile {
+    pub shooter: EntityId,
+    pub origin: chunk::Position,
+    //the line runs through this block and on past it until the range runs out
+    pub aim: chunk::Position,
+    //blocks travelled so far
+    pub step: u32,
+    pub range: u32,
+    //blocks per tick
+    pub speed: u32,
+    pub damage: i32,
+    //effect, magnitude and duration in ticks applied to whatever it hits
+    pub effect: Option<(Effect, i32, u64)>,
+}
+
+impl Projectile {
+    /**
+     * The block a projectile is over after travelling some number of blocks, None once
+     * it has left the world
+     */
+    pub fn position_at(&self, step: u32) -> Option<chunk::Position> {
+        let dx = i64::from(self.aim.0) - i64::from(self.origin.0);
+        let dy = i64::from(self.aim.1) - i64::from(self.origin.1);
+        let length = dx.abs().max(dy.abs());
+        if length == 0 {
+            return Some(self.origin);
+        }
+        //rounded to the nearest block, moving one block along the longer axis per step
+        let offset = |d: i64| (2 * d * i64::from(step) + length).div_euclid(2 * length);
+        Some((
+            u32::try_from(i64::from(self.origin.0) + offset(dx)).ok()?,
+            u32::try_from(i64::from(self.origin.1) + offset(dy)).ok()?,
+        ))
+    }
+}
+
+impl component::ComponentType for Projectile {}
+
+/**
+ * Damage dealt after armor, every hit does at least one point
+ */
+pub fn compute_damage(attack: &AttackStats, defense: &Defense) -> i32 {
+    (attack.damage - defense.armor).max(1)
+}
+
+#[test]
+fn test_compute_damage() {
+    let attack = AttackStats::new(10, 1, 10);
+    assert_eq!(compute_damage(&attack, &Defense { armor: 3 }), 7);
+    assert_eq!(compute_damage(&attack, &Defense { armor: 30 }), 1);
+}
+
+#[test]
+fn test_projectile_path() {
+    let projectile = Projectile {
+        shooter: EntityId::new_with_number(1),
+        origin: (10, 10),
+        aim: (14, 12),
+        step: 0,
+        range: 8,
+        speed: 2,
+        damage: 3,
+        effect: None,
+    };
+    assert_eq!(projectile.position_at(0), Some((10, 10)));
+    assert_eq!(projectile.position_at(2), Some((12, 11)));
+    assert_eq!(projectile.position_at(4), Some((14, 12)));
+    assert_eq!(projectile.position_at(8), Some((18, 14)));
+    let west = Projectile {
+        aim: (8, 10),
+        ..projectile
+    };
+    assert_eq!(west.position_at(3), Some((7, 10)));
+    assert_eq!(west.position_at(11), None);
+}
@@ -50,10 +50,12 @@ impl ComponentTypeId {
 /**
  * A generic (untyped) owned component
  */
+#[derive(Clone)]
 pub struct Component {
     type_id: ComponentTypeId,
     data: Arc<dyn Any>,
     serialization_fn: fn(Component) -> String,
+    value_fn: fn(&Component) -> serde_json::Value,
 }
 
 impl Component {
@@ -66,6 +68,7 @@ impl Component {
             type_id: get_type_id::<T>(),
             data: Arc::new(data),
             serialization_fn: |x| serde_json::to_string(&*(x.get_ref::<T>().unwrap())).unwrap(),
+            value_fn: |x| serde_json::to_value(&*(x.get_ref::<T>().unwrap())).unwrap(),
         }
     }
     pub fn get_type_id(&self) -> ComponentTypeId {
@@ -87,6 +90,12 @@ impl Component {
     pub fn serialize(self) -> String {
         (self.serialization_fn)(self)
     }
+    /**
+     * Serialize this component without consuming it
+     */
+    pub fn to_value(&self) -> serde_json::Value {
+        (self.value_fn)(self)
+    }
 }
 
 pub trait ComponentType: serde::de::DeserializeOwned + Serialize  + Any + Send + Sync + Clone {}
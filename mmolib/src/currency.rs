@@ -0,0 +1,31 @@
+use serde::{Deserialize, Serialize};
+
+use crate::component;
+
+/**
+ * Coins carried by an entity
+ */
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct Wallet {
+    pub coins: u64,
+}
+
+impl Wallet {
+    pub fn new(coins: u64) -> Self {
+        Wallet { coins }
+    }
+    /**
+     * Take coins out, false and nothing taken if there aren't enough
+     */
+    pub fn spend(&mut self, amount: u64) -> bool {
+        match self.coins.checked_sub(amount) {
+            Some(left) => {
+                self.coins = left;
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+impl component::ComponentType for Wallet {}
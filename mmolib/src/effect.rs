@@ -1,6 +1,8 @@
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 
-#[derive(Deserialize, Clone, Debug)]
+use crate::{component, entity_id::EntityId, raws::Raw};
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub enum Effect {
     Poison = 0,
     Fire = 1,
@@ -8,3 +10,172 @@ pub enum Effect {
     Stink = 3,
     Strength = 4,
 }
+
+impl Effect {
+    /**
+     * Whether putting this effect on someone counts as attacking them
+     */
+    pub fn is_harmful(&self) -> bool {
+        matches!(self, Effect::Poison | Effect::Fire)
+    }
+}
+
+/**
+ * What happens when an effect is applied to an entity that already has it
+ */
+#[derive(Deserialize, Clone, Debug)]
+pub enum StackingRule {
+    //reset the remaining time, keeping the stronger magnitude
+    Refresh,
+    //add the new duration onto the remaining time
+    Extend,
+    //keep separate instances, up to a limit
+    Stack { max_stacks: usize },
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct EffectRule {
+    effect: Effect,
+    stacking: StackingRule,
+    //how often the effect does something, e.g. poison damage
+    #[serde(default = "default_interval")]
+    interval_ticks: u64,
+}
+
+fn default_interval() -> u64 {
+    1
+}
+
+impl EffectRule {
+    pub fn new(raw: &Raw) -> Result<EffectRule, serde_json::Error> {
+        let res: EffectRule = serde_json::from_value(raw.dat().clone())?;
+        Ok(res)
+    }
+    pub fn new_default(effect: Effect) -> Self {
+        EffectRule {
+            effect,
+            stacking: StackingRule::Refresh,
+            interval_ticks: default_interval(),
+        }
+    }
+    pub fn get_effect(&self) -> Effect {
+        self.effect
+    }
+    pub fn get_stacking(&self) -> &StackingRule {
+        &self.stacking
+    }
+    pub fn get_interval_ticks(&self) -> u64 {
+        self.interval_ticks.max(1)
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ActiveEffect {
+    pub effect: Effect,
+    pub magnitude: i32,
+    pub remaining_ticks: u64,
+    pub source: Option<EntityId>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct ActiveEffects {
+    pub effects: Vec<ActiveEffect>,
+}
+
+impl ActiveEffects {
+    /**
+     * Add an effect following the stacking rule for it
+     */
+    pub fn apply(&mut self, new: ActiveEffect, rule: &StackingRule) {
+        let existing = self.effects.iter_mut().filter(|e| e.effect == new.effect);
+        match rule {
+            StackingRule::Refresh => {
+                if let Some(e) = existing.max_by_key(|e| e.magnitude) {
+                    e.remaining_ticks = e.remaining_ticks.max(new.remaining_ticks);
+                    if new.magnitude > e.magnitude {
+                        e.magnitude = new.magnitude;
+                        e.source = new.source;
+                    }
+                    return;
+                }
+            }
+            StackingRule::Extend => {
+                if let Some(e) = existing.max_by_key(|e| e.magnitude) {
+                    e.remaining_ticks += new.remaining_ticks;
+                    e.magnitude = e.magnitude.max(new.magnitude);
+                    return;
+                }
+            }
+            StackingRule::Stack { max_stacks } => {
+                if existing.count() >= *max_stacks {
+                    //replace the instance closest to running out
+                    if let Some(e) = self
+                        .effects
+                        .iter_mut()
+                        .filter(|e| e.effect == new.effect)
+                        .min_by_key(|e| e.remaining_ticks)
+                    {
+                        *e = new;
+                    }
+                    return;
+                }
+            }
+        }
+        self.effects.push(new);
+    }
+    /**
+     * Sum of the magnitudes of every instance of an effect
+     */
+    pub fn total_magnitude(&self, effect: Effect) -> i32 {
+        self.effects
+            .iter()
+            .filter(|e| e.effect == effect)
+            .map(|e| e.magnitude)
+            .sum()
+    }
+    pub fn has(&self, effect: Effect) -> bool {
+        self.effects.iter().any(|e| e.effect == effect)
+    }
+}
+
+impl component::ComponentType for ActiveEffects {}
+
+/**
+ * Effects that can never be applied to this entity
+ */
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct EffectImmunity {
+    pub effects: Vec<Effect>,
+}
+
+impl EffectImmunity {
+    pub fn is_immune(&self, effect: Effect) -> bool {
+        self.effects.contains(&effect)
+    }
+}
+
+impl component::ComponentType for EffectImmunity {}
+
+#[test]
+fn test_effect_stacking() {
+    let poison = |magnitude, remaining_ticks| ActiveEffect {
+        effect: Effect::Poison,
+        magnitude,
+        remaining_ticks,
+        source: None,
+    };
+    let mut effects = ActiveEffects::default();
+    effects.apply(poison(2, 10), &StackingRule::Refresh);
+    effects.apply(poison(1, 20), &StackingRule::Refresh);
+    assert_eq!(effects.effects.len(), 1);
+    assert_eq!(effects.effects[0].remaining_ticks, 20);
+    assert_eq!(effects.total_magnitude(Effect::Poison), 2);
+    effects.apply(poison(1, 5), &StackingRule::Extend);
+    assert_eq!(effects.effects[0].remaining_ticks, 25);
+    let mut effects = ActiveEffects::default();
+    for i in 0..4 {
+        effects.apply(poison(1, i), &StackingRule::Stack { max_stacks: 3 });
+    }
+    assert_eq!(effects.effects.len(), 3);
+    assert_eq!(effects.total_magnitude(Effect::Poison), 3);
+}
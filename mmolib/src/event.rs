@@ -0,0 +1,163 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    effect::Effect,
+    entity_id::EntityId,
+    group::{Guild, Party},
+    trade::Trade,
+};
+
+/**
+ * Something that happened in the world during a tick
+ */
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(tag = "type")]
+pub enum GameEvent {
+    Hit {
+        attacker: EntityId,
+        target: EntityId,
+        damage: i32,
+    },
+    Death {
+        entity: EntityId,
+        killer: Option<EntityId>,
+        //npc kind of whatever died, it may be gone by the time anyone looks
+        #[serde(default)]
+        kind: Option<String>,
+    },
+    EffectApplied {
+        entity: EntityId,
+        effect: Effect,
+    },
+    EffectExpired {
+        entity: EntityId,
+        effect: Effect,
+    },
+    CraftCompleted {
+        entity: EntityId,
+        recipe: String,
+    },
+    CraftFailed {
+        entity: EntityId,
+        recipe: String,
+        reason: String,
+    },
+    LevelUp {
+        entity: EntityId,
+        level: u32,
+    },
+    Respawned {
+        entity: EntityId,
+    },
+    Talked {
+        entity: EntityId,
+        npc: EntityId,
+        kind: String,
+    },
+    QuestAccepted {
+        entity: EntityId,
+        quest: String,
+    },
+    QuestUpdated {
+        entity: EntityId,
+        quest: String,
+    },
+    QuestCompleted {
+        entity: EntityId,
+        quest: String,
+    },
+    TradeUpdated {
+        trade: Trade,
+    },
+    TradeCompleted {
+        initiator: EntityId,
+        partner: EntityId,
+    },
+    TradeCancelled {
+        initiator: EntityId,
+        partner: EntityId,
+    },
+    ProjectileFired {
+        projectile: EntityId,
+        shooter: EntityId,
+    },
+    //target is None when it hit a wall or ran out of range
+    ProjectileImpact {
+        projectile: EntityId,
+        shooter: EntityId,
+        target: Option<EntityId>,
+    },
+    PartyInvite {
+        from: EntityId,
+        to: EntityId,
+    },
+    PartyUpdated {
+        party: Party,
+    },
+    //sent to whoever is no longer in the party, the rest get an update
+    PartyLeft {
+        entity: EntityId,
+    },
+    GuildInvite {
+        guild: String,
+        from: EntityId,
+        to: EntityId,
+    },
+    GuildUpdated {
+        guild: Guild,
+    },
+    GuildLeft {
+        entity: EntityId,
+        guild: String,
+    },
+}
+
+impl GameEvent {
+    /**
+     * Entities the event is about
+     */
+    pub fn get_entities(&self) -> Vec<EntityId> {
+        match self {
+            GameEvent::Hit {
+                attacker, target, ..
+            } => vec![*attacker, *target],
+            GameEvent::Death { entity, killer, .. } => {
+                let mut res = vec![*entity];
+                res.extend(killer);
+                res
+            }
+            GameEvent::EffectApplied { entity, .. }
+            | GameEvent::EffectExpired { entity, .. }
+            | GameEvent::CraftCompleted { entity, .. }
+            | GameEvent::CraftFailed { entity, .. }
+            | GameEvent::LevelUp { entity, .. }
+            | GameEvent::Respawned { entity }
+            | GameEvent::QuestAccepted { entity, .. }
+            | GameEvent::QuestUpdated { entity, .. }
+            | GameEvent::QuestCompleted { entity, .. } => vec![*entity],
+            GameEvent::Talked { entity, npc, .. } => vec![*entity, *npc],
+            GameEvent::TradeUpdated { trade } => vec![trade.initiator.entity, trade.partner.entity],
+            GameEvent::TradeCompleted { initiator, partner }
+            | GameEvent::TradeCancelled { initiator, partner } => vec![*initiator, *partner],
+            GameEvent::ProjectileFired {
+                projectile,
+                shooter,
+            } => vec![*projectile, *shooter],
+            GameEvent::ProjectileImpact {
+                projectile,
+                shooter,
+                target,
+            } => {
+                let mut res = vec![*projectile, *shooter];
+                res.extend(target);
+                res
+            }
+            GameEvent::PartyInvite { from, to } | GameEvent::GuildInvite { from, to, .. } => {
+                vec![*from, *to]
+            }
+            GameEvent::PartyUpdated { party } => party.members.clone(),
+            GameEvent::GuildUpdated { guild } => guild.get_members(),
+            GameEvent::PartyLeft { entity } | GameEvent::GuildLeft { entity, .. } => vec![*entity],
+        }
+    }
+}
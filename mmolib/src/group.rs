@@ -0,0 +1,186 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{component, entity_id::EntityId};
+
+//most members a party can have
+pub const MAX_PARTY_SIZE: usize = 6;
+//most members a guild can have
+pub const MAX_GUILD_SIZE: usize = 100;
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LootRule {
+    //anyone can pick up anything
+    FreeForAll,
+    //drops take turns belonging to each member for a while
+    RoundRobin,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct PartyRules {
+    //split kill xp between members nearby instead of giving it all to the killer
+    pub share_xp: bool,
+    pub loot: LootRule,
+}
+
+impl Default for PartyRules {
+    fn default() -> Self {
+        PartyRules {
+            share_xp: true,
+            loot: LootRule::FreeForAll,
+        }
+    }
+}
+
+/**
+ * A temporary group, gone once everyone leaves or the server restarts
+ */
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Party {
+    pub id: u64,
+    pub leader: EntityId,
+    pub members: Vec<EntityId>,
+    pub rules: PartyRules,
+    //index of the member next in line for round robin loot
+    pub next_looter: usize,
+}
+
+impl Party {
+    pub fn new(id: u64, leader: EntityId) -> Self {
+        Party {
+            id,
+            leader,
+            members: vec![leader],
+            rules: PartyRules::default(),
+            next_looter: 0,
+        }
+    }
+    pub fn is_member(&self, entity: EntityId) -> bool {
+        self.members.contains(&entity)
+    }
+    pub fn is_full(&self) -> bool {
+        self.members.len() >= MAX_PARTY_SIZE
+    }
+    /**
+     * Take someone out of the party, handing leadership on if it was the leader
+     */
+    pub fn remove(&mut self, entity: EntityId) {
+        self.members.retain(|m| *m != entity);
+        if self.leader == entity {
+            if let Some(next) = self.members.first() {
+                self.leader = *next;
+            }
+        }
+    }
+    /**
+     * Who gets the next drop under round robin
+     */
+    pub fn take_next_looter(&mut self) -> Option<EntityId> {
+        if self.members.is_empty() {
+            return None;
+        }
+        let looter = self.members[self.next_looter % self.members.len()];
+        self.next_looter = (self.next_looter + 1) % self.members.len();
+        Some(looter)
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum GuildRank {
+    Member,
+    Officer,
+    Leader,
+}
+
+impl GuildRank {
+    pub fn promoted(&self) -> GuildRank {
+        match self {
+            GuildRank::Member => GuildRank::Officer,
+            GuildRank::Officer | GuildRank::Leader => GuildRank::Leader,
+        }
+    }
+    pub fn demoted(&self) -> GuildRank {
+        match self {
+            GuildRank::Leader => GuildRank::Officer,
+            GuildRank::Officer | GuildRank::Member => GuildRank::Member,
+        }
+    }
+}
+
+/**
+ * A lasting group, kept in world storage
+ */
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Guild {
+    //names are freed when a guild disbands, so anything kept per guild goes by this
+    pub id: EntityId,
+    pub name: String,
+    pub roster: Vec<(EntityId, GuildRank)>,
+}
+
+impl Guild {
+    pub fn new(id: EntityId, name: &str, leader: EntityId) -> Self {
+        Guild {
+            id,
+            name: name.to_owned(),
+            roster: vec![(leader, GuildRank::Leader)],
+        }
+    }
+    pub fn rank_of(&self, entity: EntityId) -> Option<GuildRank> {
+        self.roster
+            .iter()
+            .find(|(member, _)| *member == entity)
+            .map(|(_, rank)| *rank)
+    }
+    pub fn set_rank(&mut self, entity: EntityId, rank: GuildRank) {
+        for (member, member_rank) in self.roster.iter_mut() {
+            if *member == entity {
+                *member_rank = rank;
+            }
+        }
+    }
+    pub fn remove(&mut self, entity: EntityId) {
+        self.roster.retain(|(member, _)| *member != entity);
+    }
+    pub fn get_members(&self) -> Vec<EntityId> {
+        self.roster.iter().map(|(member, _)| *member).collect()
+    }
+}
+
+/**
+ * The guild a player belongs to
+ */
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct GuildMembership {
+    pub guild: String,
+}
+
+impl component::ComponentType for GuildMembership {}
+
+/**
+ * Guild names are shown to everyone, so keep them short and plain
+ */
+pub fn is_valid_guild_name(name: &str) -> bool {
+    (3..=24).contains(&name.len())
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == ' ')
+        && !name.starts_with(' ')
+        && !name.ends_with(' ')
+}
+
+#[test]
+fn test_party_and_guild() {
+    let a = EntityId::new_with_number(1);
+    let b = EntityId::new_with_number(2);
+    let mut party = Party::new(1, a);
+    party.members.push(b);
+    assert_eq!(party.take_next_looter(), Some(a));
+    assert_eq!(party.take_next_looter(), Some(b));
+    assert_eq!(party.take_next_looter(), Some(a));
+    party.remove(a);
+    assert_eq!(party.leader, b);
+    let mut guild = Guild::new(EntityId::new_with_number(3), "Wolves", a);
+    guild.roster.push((b, GuildRank::Member));
+    guild.set_rank(b, GuildRank::Member.promoted());
+    assert_eq!(guild.rank_of(b), Some(GuildRank::Officer));
+    assert!(is_valid_guild_name("The Wolves"));
+    assert!(!is_valid_guild_name(" x"));
+}
@@ -0,0 +1,253 @@
+use serde::{Deserialize, Serialize};
+
+use crate::hashing::string_hash;
+use crate::{
+    component, effect::Effect, entity_id::EntityId, raws::Raw, resource, stats::StatModifier,
+};
+pub type ItemTypeId = u64;
+
+pub fn item_type_id(canonical_name: &str) -> ItemTypeId {
+    string_hash(canonical_name)
+}
+
+/**
+ * What using an item on a target does
+ */
+#[derive(Deserialize, Clone, Debug)]
+pub enum ItemEffect {
+    Heal(i32),
+    Damage(i32),
+    ApplyEffect {
+        effect: Effect,
+        magnitude: i32,
+        duration_ticks: u64,
+    },
+}
+
+impl ItemEffect {
+    pub fn is_harmful(&self) -> bool {
+        match self {
+            ItemEffect::Heal(_) => false,
+            ItemEffect::Damage(_) => true,
+            ItemEffect::ApplyEffect { effect, .. } => effect.is_harmful(),
+        }
+    }
+}
+
+#[derive(Deserialize, Debug)]
+pub struct ItemType {
+    canonical_name: String,
+    descriptive_name: String,
+    resource: resource::ResourceId,
+    #[serde(default = "default_max_stack")]
+    max_stack: u32,
+    //whether one is used up each time it is used
+    #[serde(default)]
+    consumable: bool,
+    #[serde(default)]
+    on_use: Vec<ItemEffect>,
+    //where the item is worn, items without a slot can't be equipped
+    #[serde(default)]
+    slot: Option<EquipSlot>,
+    //stat changes while equipped
+    #[serde(default)]
+    modifiers: Vec<StatModifier>,
+    //items with durability wear out with use and stop working once broken
+    #[serde(default)]
+    max_durability: Option<u32>,
+    #[serde(default)]
+    ranged: Option<RangedWeapon>,
+}
+
+/**
+ * What an item fires when shot with
+ */
+#[derive(Deserialize, Clone, Debug)]
+pub struct RangedWeapon {
+    //blocks a shot travels before dropping
+    pub range: u32,
+    //blocks a shot travels each tick
+    pub speed: u32,
+    pub damage: i32,
+    #[serde(default = "default_ranged_cooldown_ticks")]
+    pub cooldown_ticks: u64,
+    //item used up by each shot, none if the weapon needs no ammunition
+    #[serde(default)]
+    pub ammo: Option<String>,
+    #[serde(default)]
+    pub effect: Option<(Effect, i32, u64)>,
+}
+
+fn default_ranged_cooldown_ticks() -> u64 {
+    20
+}
+
+fn default_max_stack() -> u32 {
+    1
+}
+
+impl ItemType {
+    pub fn new(raw: &Raw) -> Result<ItemType, serde_json::Error> {
+        let res: ItemType = serde_json::from_value(raw.dat().clone())?;
+        Ok(res)
+    }
+    pub fn get_canonical_name(&self) -> &str {
+        &self.canonical_name
+    }
+    pub fn get_id(&self) -> ItemTypeId {
+        string_hash(&self.canonical_name)
+    }
+    pub fn get_descriptive_name(&self) -> &str {
+        &self.descriptive_name
+    }
+    pub fn get_max_stack(&self) -> u32 {
+        self.max_stack
+    }
+    pub fn is_consumable(&self) -> bool {
+        self.consumable
+    }
+    pub fn get_on_use(&self) -> &[ItemEffect] {
+        &self.on_use
+    }
+    pub fn get_modifiers(&self) -> &[StatModifier] {
+        &self.modifiers
+    }
+    pub fn get_slot(&self) -> Option<EquipSlot> {
+        self.slot
+    }
+    pub fn get_max_durability(&self) -> Option<u32> {
+        self.max_durability
+    }
+    pub fn get_ranged(&self) -> Option<&RangedWeapon> {
+        self.ranged.as_ref()
+    }
+}
+
+/**
+ * A stack of items, either lying in the world or held in an inventory
+ */
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Item {
+    pub item_type_id: ItemTypeId,
+    pub count: u32,
+}
+
+impl Item {
+    pub fn new(item_type_id: ItemTypeId, count: u32) -> Self {
+        Item {
+            item_type_id,
+            count,
+        }
+    }
+}
+
+impl component::ComponentType for Item {}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Inventory {
+    //number of stacks that fit
+    pub capacity: usize,
+    pub items: Vec<EntityId>,
+}
+
+impl Inventory {
+    pub fn new(capacity: usize) -> Self {
+        Inventory {
+            capacity,
+            items: Vec::new(),
+        }
+    }
+    pub fn is_full(&self) -> bool {
+        self.items.len() >= self.capacity
+    }
+    pub fn contains(&self, item: EntityId) -> bool {
+        self.items.contains(&item)
+    }
+    pub fn remove(&mut self, item: EntityId) -> bool {
+        let len = self.items.len();
+        self.items.retain(|i| *i != item);
+        len != self.items.len()
+    }
+}
+
+impl component::ComponentType for Inventory {}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EquipSlot {
+    MainHand,
+    OffHand,
+    Head,
+    Body,
+    Hands,
+    Feet,
+}
+
+/**
+ * Items from the inventory that are currently worn or wielded, at most one per slot
+ */
+#[derive(Clone, Debug, Serialize, Deserialize, Default)]
+pub struct Equipment {
+    pub slots: Vec<(EquipSlot, EntityId)>,
+}
+
+impl Equipment {
+    pub fn get(&self, slot: EquipSlot) -> Option<EntityId> {
+        self.slots
+            .iter()
+            .find(|(s, _)| *s == slot)
+            .map(|(_, item)| *item)
+    }
+    pub fn items(&self) -> impl Iterator<Item = EntityId> + '_ {
+        self.slots.iter().map(|(_, item)| *item)
+    }
+    pub fn is_equipped(&self, item: EntityId) -> bool {
+        self.items().any(|i| i == item)
+    }
+    /**
+     * Put an item in a slot, returning the item it replaced
+     */
+    pub fn equip(&mut self, slot: EquipSlot, item: EntityId) -> Option<EntityId> {
+        let replaced = self.get(slot);
+        self.slots.retain(|(s, i)| *s != slot && *i != item);
+        self.slots.push((slot, item));
+        replaced
+    }
+    pub fn unequip(&mut self, item: EntityId) -> bool {
+        let len = self.slots.len();
+        self.slots.retain(|(_, i)| *i != item);
+        len != self.slots.len()
+    }
+}
+
+impl component::ComponentType for Equipment {}
+
+/**
+ * How worn an item is, a broken item gives no stats until repaired
+ */
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Durability {
+    pub current: u32,
+    pub max: u32,
+}
+
+impl Durability {
+    pub fn new(max: u32) -> Self {
+        Durability { current: max, max }
+    }
+    pub fn is_broken(&self) -> bool {
+        self.current == 0
+    }
+    /**
+     * Wear the item down, true if this is what broke it
+     */
+    pub fn wear(&mut self, amount: u32) -> bool {
+        let was_broken = self.is_broken();
+        self.current = self.current.saturating_sub(amount);
+        !was_broken && self.is_broken()
+    }
+    pub fn missing(&self) -> u32 {
+        self.max - self.current
+    }
+}
+
+impl component::ComponentType for Durability {}
@@ -5,14 +5,33 @@
 #![feature(specialization)]
 #![allow(unused)]
 #![deny(warnings)]
+pub mod ai;
 pub mod block_type;
+pub mod character;
+pub mod chat;
 pub mod chunk;
+pub mod combat;
 pub mod component;
+pub mod currency;
 pub mod effect;
 pub mod entity_id;
+pub mod event;
+pub mod group;
 mod hashing;
+pub mod item;
+pub mod loot;
+pub mod moderation;
+pub mod movement;
+pub mod player;
 pub mod position;
+pub mod quest;
 pub mod raws;
+pub mod recipe;
 pub mod resource;
 pub mod server_request_type;
 pub mod server_response_type;
+pub mod shop;
+pub mod stats;
+pub mod trade;
+pub mod world_rules;
+pub mod world_time;
@@ -0,0 +1,272 @@
+use std::collections::HashMap;
+
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    component,
+    entity_id::EntityId,
+    item::{item_type_id, ItemTypeId},
+    position::Position,
+    raws::Raw,
+};
+
+#[derive(Deserialize, Clone, Debug)]
+pub enum LootKind {
+    Nothing,
+    //canonical item name
+    Item(String),
+    //canonical name of another loot table
+    Table(String),
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub enum LootCondition {
+    KilledByPlayer,
+    //percent chance the entry is allowed at all
+    Chance(u32),
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct LootEntry {
+    weight: u32,
+    kind: LootKind,
+    #[serde(default = "default_count")]
+    min_count: u32,
+    #[serde(default = "default_count")]
+    max_count: u32,
+    #[serde(default)]
+    conditions: Vec<LootCondition>,
+}
+
+fn default_count() -> u32 {
+    1
+}
+
+fn default_rolls() -> u32 {
+    1
+}
+
+#[derive(Deserialize, Debug)]
+pub struct LootTable {
+    canonical_name: String,
+    #[serde(default = "default_rolls")]
+    rolls: u32,
+    entries: Vec<LootEntry>,
+}
+
+/**
+ * Facts about how the loot came to be dropped, checked against entry conditions
+ */
+#[derive(Clone, Debug, Default)]
+pub struct LootContext {
+    pub killed_by_player: bool,
+}
+
+impl LootTable {
+    pub fn new(raw: &Raw) -> Result<LootTable, serde_json::Error> {
+        let res: LootTable = serde_json::from_value(raw.dat().clone())?;
+        Ok(res)
+    }
+    pub fn get_canonical_name(&self) -> &str {
+        &self.canonical_name
+    }
+}
+
+/**
+ * Loot dropped by an entity when it dies
+ */
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct LootDrop {
+    pub table: String,
+}
+
+impl component::ComponentType for LootDrop {}
+
+/**
+ * Loot set aside for one player for a while, nobody else can pick it up until then
+ */
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct LootOwner {
+    pub entity: EntityId,
+    pub until_tick: u64,
+}
+
+impl LootOwner {
+    pub fn allows(&self, entity: EntityId, tick: u64) -> bool {
+        self.entity == entity || tick >= self.until_tick
+    }
+}
+
+impl component::ComponentType for LootOwner {}
+
+/**
+ * A container that rolls its loot table the first time someone opens it
+ */
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Chest {
+    pub table: String,
+    pub opened: bool,
+}
+
+impl component::ComponentType for Chest {}
+
+/**
+ * Where a chest stands when a world is created
+ */
+#[derive(Deserialize, Debug)]
+pub struct ChestPlacement {
+    table: String,
+    position: Position,
+}
+
+impl ChestPlacement {
+    pub fn new(raw: &Raw) -> Result<ChestPlacement, serde_json::Error> {
+        let res: ChestPlacement = serde_json::from_value(raw.dat().clone())?;
+        Ok(res)
+    }
+    pub fn get_table(&self) -> &str {
+        &self.table
+    }
+    pub fn get_position(&self) -> &Position {
+        &self.position
+    }
+}
+
+fn allowed(entry: &LootEntry, context: &LootContext, rng: &mut impl Rng) -> bool {
+    entry.conditions.iter().all(|condition| match condition {
+        LootCondition::KilledByPlayer => context.killed_by_player,
+        LootCondition::Chance(percent) => rng.gen_range(0..100) < *percent,
+    })
+}
+
+/**
+ * Roll one table into res. rolling holds the tables being rolled above this one,
+ * a table that leads back to one of them rolls nothing so cycles in the raws end straight away
+ */
+fn roll_table<'a>(
+    tables: &'a HashMap<String, LootTable>,
+    name: &str,
+    context: &LootContext,
+    rng: &mut impl Rng,
+    rolling: &mut Vec<&'a str>,
+    res: &mut Vec<(ItemTypeId, u32)>,
+) {
+    let table = match tables.get(name) {
+        Some(table) if !rolling.contains(&table.canonical_name.as_str()) => table,
+        _ => return,
+    };
+    rolling.push(&table.canonical_name);
+    for _ in 0..table.rolls {
+        let entries: Vec<&LootEntry> = table
+            .entries
+            .iter()
+            .filter(|e| e.weight > 0 && allowed(e, context, rng))
+            .collect();
+        let total: u32 = entries.iter().map(|e| e.weight).sum();
+        if total == 0 {
+            continue;
+        }
+        let mut pick = rng.gen_range(0..total);
+        let entry = match entries.into_iter().find(|e| {
+            if pick < e.weight {
+                true
+            } else {
+                pick -= e.weight;
+                false
+            }
+        }) {
+            Some(entry) => entry,
+            None => continue,
+        };
+        let count = rng.gen_range(entry.min_count..=entry.max_count.max(entry.min_count));
+        match &entry.kind {
+            LootKind::Nothing => {}
+            LootKind::Item(item) => {
+                if count > 0 {
+                    res.push((item_type_id(item), count));
+                }
+            }
+            LootKind::Table(table) => {
+                for _ in 0..count {
+                    roll_table(tables, table, context, rng, rolling, res);
+                }
+            }
+        }
+    }
+    rolling.pop();
+}
+
+/**
+ * Roll a loot table by canonical name, returning item types and counts
+ */
+pub fn roll(
+    tables: &HashMap<String, LootTable>,
+    name: &str,
+    context: &LootContext,
+    rng: &mut impl Rng,
+) -> Vec<(ItemTypeId, u32)> {
+    let mut res = Vec::new();
+    roll_table(tables, name, context, rng, &mut Vec::new(), &mut res);
+    res
+}
+
+/**
+ * Split a count of items into stacks no bigger than max_stack
+ */
+pub fn split_into_stacks(count: u32, max_stack: u32) -> Vec<u32> {
+    let max_stack = max_stack.max(1);
+    let mut res = vec![max_stack; (count / max_stack) as usize];
+    if !count.is_multiple_of(max_stack) {
+        res.push(count % max_stack);
+    }
+    res
+}
+
+#[test]
+fn test_roll() {
+    use rand::SeedableRng;
+    let table = |s: &str| -> LootTable { serde_json::from_str(s).unwrap() };
+    let mut tables = HashMap::new();
+    tables.insert(
+        "goblin".to_owned(),
+        table(
+            r#"{ "canonical_name" : "goblin", "rolls" : 2, "entries" : [
+                { "weight" : 1, "kind" : { "Item" : "rock" }, "min_count" : 1, "max_count" : 3 },
+                { "weight" : 1, "kind" : { "Table" : "rare" }, "conditions" : ["KilledByPlayer"] }
+            ] }"#,
+        ),
+    );
+    tables.insert(
+        "rare".to_owned(),
+        table(r#"{ "canonical_name" : "rare", "entries" : [ { "weight" : 1, "kind" : { "Item" : "healthpotion" } } ] }"#),
+    );
+    //tables that lead back to themselves still terminate, without fanning out first
+    tables.insert(
+        "loop".to_owned(),
+        table(r#"{ "canonical_name" : "loop", "entries" : [ { "weight" : 1, "kind" : { "Table" : "loop" } } ] }"#),
+    );
+    tables.insert(
+        "ping".to_owned(),
+        table(r#"{ "canonical_name" : "ping", "rolls" : 10, "entries" : [ { "weight" : 1, "kind" : { "Table" : "pong" }, "min_count" : 10, "max_count" : 10 } ] }"#),
+    );
+    tables.insert(
+        "pong".to_owned(),
+        table(r#"{ "canonical_name" : "pong", "rolls" : 10, "entries" : [ { "weight" : 1, "kind" : { "Table" : "ping" }, "min_count" : 10, "max_count" : 10 } ] }"#),
+    );
+    let mut rng = rand::rngs::StdRng::seed_from_u64(1);
+    let drops = roll(&tables, "goblin", &LootContext::default(), &mut rng);
+    assert_eq!(drops.len(), 2);
+    assert!(drops.iter().all(|(id, c)| *id == item_type_id("rock") && (1..=3).contains(c)));
+    let seeded = |seed| {
+        let mut rng = rand::rngs::StdRng::seed_from_u64(seed);
+        roll(&tables, "goblin", &LootContext { killed_by_player: true }, &mut rng)
+    };
+    assert_eq!(seeded(5), seeded(5));
+    assert!(roll(&tables, "loop", &LootContext::default(), &mut rng).is_empty());
+    assert!(roll(&tables, "ping", &LootContext::default(), &mut rng).is_empty());
+    assert_eq!(split_into_stacks(7, 3), vec![3, 3, 1]);
+    assert_eq!(split_into_stacks(6, 3), vec![3, 3]);
+    assert_eq!(split_into_stacks(2, 0), vec![1, 1]);
+    assert!(split_into_stacks(0, 5).is_empty());
+}
@@ -0,0 +1,176 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{chat::ChatLine, component, raws::Raw};
+
+//messages a player can send in a burst
+pub const CHAT_BURST: u32 = 5;
+//ticks it takes to earn back one message
+pub const CHAT_REFILL_TICKS: u64 = 20;
+//reports kept for moderators, the oldest are dropped first
+pub const REPORT_CAP: usize = 500;
+//ticks a player has to wait between reports
+pub const REPORT_COOLDOWN_TICKS: u64 = 600;
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FilterAction {
+    //refuse the whole message
+    Block,
+    //star out the word and let the rest through
+    Mask,
+}
+
+/**
+ * Words that aren't allowed in chat. Matched against whole words, ignoring case
+ */
+#[derive(Deserialize, Debug)]
+pub struct WordFilter {
+    canonical_name: String,
+    action: FilterAction,
+    words: Vec<String>,
+}
+
+impl WordFilter {
+    pub fn new(raw: &Raw) -> Result<WordFilter, serde_json::Error> {
+        let mut res: WordFilter = serde_json::from_value(raw.dat().clone())?;
+        res.words = res.words.iter().map(|w| w.to_lowercase()).collect();
+        Ok(res)
+    }
+    pub fn get_canonical_name(&self) -> &str {
+        &self.canonical_name
+    }
+    pub fn get_action(&self) -> FilterAction {
+        self.action
+    }
+    pub fn matches(&self, word: &str) -> bool {
+        let word = word.to_lowercase();
+        self.words.contains(&word)
+    }
+}
+
+/**
+ * Run a message through every filter, None if one of them blocks it
+ */
+pub fn apply_filters(filters: &[WordFilter], message: &str) -> Option<String> {
+    let mut res = String::with_capacity(message.len());
+    let mut word = String::new();
+    //a trailing separator flushes the last word
+    for c in message.chars().chain(std::iter::once(' ')) {
+        if c.is_alphanumeric() {
+            word.push(c);
+            continue;
+        }
+        if !word.is_empty() {
+            let mut masked = false;
+            for filter in filters.iter().filter(|f| f.matches(&word)) {
+                match filter.get_action() {
+                    FilterAction::Block => return None,
+                    FilterAction::Mask => masked = true,
+                }
+            }
+            if masked {
+                res.extend(word.chars().map(|_| '*'));
+            } else {
+                res.push_str(&word);
+            }
+            word.clear();
+        }
+        res.push(c);
+    }
+    res.pop();
+    Some(res)
+}
+
+/**
+ * A player who can't chat until the mute runs out, or for good without an end
+ */
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Muted {
+    //unix seconds, so the mute keeps running out while the server is down
+    pub until: Option<u64>,
+    pub reason: String,
+    pub by: String,
+}
+
+impl Muted {
+    pub fn is_active(&self, now: u64) -> bool {
+        self.until.is_none_or(|until| now < until)
+    }
+}
+
+impl component::ComponentType for Muted {}
+
+/**
+ * How many messages someone can still send right now, refilling over time
+ */
+#[derive(Clone, Debug)]
+pub struct RateLimit {
+    tokens: u32,
+    last_refill_tick: u64,
+}
+
+impl RateLimit {
+    pub fn new(tick: u64) -> Self {
+        RateLimit {
+            tokens: CHAT_BURST,
+            last_refill_tick: tick,
+        }
+    }
+    /**
+     * Use up one message, false if there are none left
+     */
+    pub fn allow(&mut self, tick: u64) -> bool {
+        let earned = tick.saturating_sub(self.last_refill_tick) / CHAT_REFILL_TICKS;
+        if earned > 0 {
+            self.tokens = (u64::from(self.tokens) + earned).min(u64::from(CHAT_BURST)) as u32;
+            self.last_refill_tick += earned * CHAT_REFILL_TICKS;
+        }
+        if self.tokens == 0 {
+            return false;
+        }
+        self.tokens -= 1;
+        true
+    }
+}
+
+/**
+ * A message a player flagged for moderators, with what they had seen around it
+ */
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Report {
+    pub reporter: String,
+    pub line: ChatLine,
+    pub context: Vec<ChatLine>,
+    pub tick: u64,
+}
+
+#[test]
+fn test_moderation() {
+    let filter = |action: &str, words: &str| -> WordFilter {
+        serde_json::from_str(&format!(
+            r#"{{ "canonical_name" : "test", "action" : "{}", "words" : [{}] }}"#,
+            action, words
+        ))
+        .unwrap()
+    };
+    let filters = vec![filter("Mask", r#""darn""#), filter("Block", r#""scam""#)];
+    assert_eq!(
+        apply_filters(&filters, "Darn it, darnation!"),
+        Some("**** it, darnation!".to_owned())
+    );
+    assert_eq!(apply_filters(&filters, "free gold, no scam"), None);
+    assert_eq!(apply_filters(&filters, ""), Some("".to_owned()));
+    let mut limit = RateLimit::new(0);
+    for _ in 0..CHAT_BURST {
+        assert!(limit.allow(0));
+    }
+    assert!(!limit.allow(CHAT_REFILL_TICKS - 1));
+    assert!(limit.allow(CHAT_REFILL_TICKS));
+    assert!(!limit.allow(CHAT_REFILL_TICKS));
+    let muted = Muted {
+        until: Some(100),
+        reason: "spam".to_owned(),
+        by: "mod".to_owned(),
+    };
+    assert!(muted.is_active(99));
+    assert!(!muted.is_active(100));
+}
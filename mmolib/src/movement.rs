@@ -0,0 +1,55 @@
+use serde::{Deserialize, Serialize};
+
+use crate::component;
+
+//how many ticks a straight step takes when nothing else says otherwise
+pub const DEFAULT_TICKS_PER_STEP: u64 = 4;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Movement {
+    pub ticks_per_step: u64,
+    pub next_move_tick: u64,
+}
+
+impl Movement {
+    pub fn new(ticks_per_step: u64) -> Self {
+        Movement {
+            ticks_per_step,
+            next_move_tick: 0,
+        }
+    }
+    /**
+     * Ticks until the next move is allowed, diagonal steps cover ~1.4 blocks so take longer
+     */
+    pub fn step_cooldown(&self, diagonal: bool) -> u64 {
+        step_cooldown(self.ticks_per_step, diagonal)
+    }
+}
+
+pub fn default_ticks_per_step() -> u64 {
+    DEFAULT_TICKS_PER_STEP
+}
+
+pub fn step_cooldown(ticks_per_step: u64, diagonal: bool) -> u64 {
+    if diagonal {
+        (ticks_per_step * 14).div_ceil(10)
+    } else {
+        ticks_per_step
+    }
+}
+
+impl Default for Movement {
+    fn default() -> Self {
+        Movement::new(DEFAULT_TICKS_PER_STEP)
+    }
+}
+
+impl component::ComponentType for Movement {}
+
+/**
+ * Entities with a collider occupy their block so nothing else can move into it
+ */
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Collider {}
+
+impl component::ComponentType for Collider {}
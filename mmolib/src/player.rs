@@ -0,0 +1,13 @@
+use serde::{Deserialize, Serialize};
+
+use crate::component;
+
+/**
+ * Marks an entity as controlled by a player account
+ */
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Player {
+    pub username: String,
+}
+
+impl component::ComponentType for Player {}
@@ -1,6 +1,6 @@
 use serde::{Deserialize, Serialize};
 
-use crate::component;
+use crate::{chunk, component};
 
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -9,5 +9,23 @@ pub struct Position {
     pub y : i32,
 }
 
+impl Position {
+    pub fn new(x: i32, y: i32) -> Self {
+        Position { x, y }
+    }
+    /**
+     * The block this position lies on, if it is inside the world
+     */
+    pub fn block_position(&self) -> Option<chunk::Position> {
+        Some((self.x.try_into().ok()?, self.y.try_into().ok()?))
+    }
+    pub fn from_block_position(position: chunk::Position) -> Self {
+        Position {
+            x: position.0 as i32,
+            y: position.1 as i32,
+        }
+    }
+}
+
 impl component::ComponentType for Position {
 }
\ No newline at end of file
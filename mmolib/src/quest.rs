@@ -0,0 +1,202 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    chunk, component,
+    item::{item_type_id, ItemTypeId},
+    raws::Raw,
+};
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub enum Objective {
+    Kill { kind: String, count: u32 },
+    Collect { item: String, count: u32 },
+    Reach { position: chunk::Position, radius: u32 },
+    TalkTo { kind: String },
+}
+
+impl Objective {
+    /**
+     * Progress needed for the objective to be done
+     */
+    pub fn get_goal(&self) -> u32 {
+        match self {
+            Objective::Kill { count, .. } | Objective::Collect { count, .. } => *count,
+            Objective::Reach { .. } | Objective::TalkTo { .. } => 1,
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Default)]
+pub struct QuestReward {
+    #[serde(default)]
+    xp: u64,
+    #[serde(default)]
+    items: Vec<(String, u32)>,
+}
+
+impl QuestReward {
+    pub fn get_xp(&self) -> u64 {
+        self.xp
+    }
+    pub fn get_items(&self) -> Vec<(ItemTypeId, u32)> {
+        self.items
+            .iter()
+            .map(|(name, count)| (item_type_id(name), *count))
+            .collect()
+    }
+}
+
+#[derive(Deserialize, Debug)]
+pub struct Quest {
+    canonical_name: String,
+    descriptive_name: String,
+    objectives: Vec<Objective>,
+    #[serde(default)]
+    rewards: QuestReward,
+    //npc kind that hands out and takes back the quest, anyone anywhere if unset
+    #[serde(default)]
+    giver: Option<String>,
+    #[serde(default)]
+    repeatable: bool,
+}
+
+impl Quest {
+    pub fn new(raw: &Raw) -> Result<Quest, serde_json::Error> {
+        let res: Quest = serde_json::from_value(raw.dat().clone())?;
+        Ok(res)
+    }
+    pub fn get_canonical_name(&self) -> &str {
+        &self.canonical_name
+    }
+    pub fn get_descriptive_name(&self) -> &str {
+        &self.descriptive_name
+    }
+    pub fn get_objectives(&self) -> &[Objective] {
+        &self.objectives
+    }
+    pub fn get_rewards(&self) -> &QuestReward {
+        &self.rewards
+    }
+    pub fn get_giver(&self) -> Option<&str> {
+        self.giver.as_deref()
+    }
+    pub fn is_repeatable(&self) -> bool {
+        self.repeatable
+    }
+    pub fn is_complete(&self, progress: &QuestProgress) -> bool {
+        self.objectives
+            .iter()
+            .enumerate()
+            .all(|(i, o)| progress.progress.get(i).copied().unwrap_or(0) >= o.get_goal())
+    }
+}
+
+/**
+ * How far along a player is with one quest, one number per objective
+ */
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct QuestProgress {
+    pub quest: String,
+    pub progress: Vec<u32>,
+}
+
+impl QuestProgress {
+    pub fn new(quest: &Quest) -> Self {
+        QuestProgress {
+            quest: quest.get_canonical_name().to_owned(),
+            progress: vec![0; quest.get_objectives().len()],
+        }
+    }
+    fn update(&mut self, quest: &Quest, f: impl Fn(&Objective, u32) -> u32) -> bool {
+        let mut changed = false;
+        for (i, objective) in quest.get_objectives().iter().enumerate() {
+            if i >= self.progress.len() {
+                self.progress.push(0);
+            }
+            let next = f(objective, self.progress[i]).min(objective.get_goal());
+            if next != self.progress[i] {
+                self.progress[i] = next;
+                changed = true;
+            }
+        }
+        changed
+    }
+    pub fn record_kill(&mut self, quest: &Quest, killed: &str) -> bool {
+        self.update(quest, |o, p| match o {
+            Objective::Kill { kind, .. } if kind == killed => p + 1,
+            _ => p,
+        })
+    }
+    pub fn record_talk(&mut self, quest: &Quest, talked_to: &str) -> bool {
+        self.update(quest, |o, p| match o {
+            Objective::TalkTo { kind } if kind == talked_to => 1,
+            _ => p,
+        })
+    }
+    pub fn record_position(&mut self, quest: &Quest, position: chunk::Position) -> bool {
+        self.update(quest, |o, p| match o {
+            Objective::Reach {
+                position: goal,
+                radius,
+            } if chunk::chebyshev_distance(*goal, position) <= *radius => 1,
+            _ => p,
+        })
+    }
+    /**
+     * Collection progress follows what is in the inventory, so it can go back down
+     */
+    pub fn record_held(&mut self, quest: &Quest, held: impl Fn(ItemTypeId) -> u32) -> bool {
+        self.update(quest, |o, p| match o {
+            Objective::Collect { item, .. } => held(item_type_id(item)),
+            _ => p,
+        })
+    }
+}
+
+/**
+ * Every quest a player has going and every one they finished
+ */
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct QuestLog {
+    pub active: Vec<QuestProgress>,
+    pub completed: Vec<String>,
+}
+
+impl QuestLog {
+    pub fn get_active(&self, quest: &str) -> Option<&QuestProgress> {
+        self.active.iter().find(|p| p.quest == quest)
+    }
+    pub fn has_completed(&self, quest: &str) -> bool {
+        self.completed.iter().any(|q| q == quest)
+    }
+}
+
+impl component::ComponentType for QuestLog {}
+
+#[test]
+fn test_quest_progress() {
+    let quest: Quest = serde_json::from_str(
+        r#"{
+            "canonical_name" : "test",
+            "descriptive_name" : "A test",
+            "objectives" : [
+                { "Kill" : { "kind" : "goblin", "count" : 2 } },
+                { "Collect" : { "item" : "rock", "count" : 3 } },
+                { "Reach" : { "position" : [10, 10], "radius" : 1 } }
+            ]
+        }"#,
+    )
+    .unwrap();
+    let mut progress = QuestProgress::new(&quest);
+    assert!(progress.record_kill(&quest, "goblin"));
+    assert!(!progress.record_kill(&quest, "rat"));
+    progress.record_kill(&quest, "goblin");
+    progress.record_kill(&quest, "goblin");
+    assert_eq!(progress.progress[0], 2);
+    assert!(!progress.record_position(&quest, (20, 20)));
+    assert!(progress.record_position(&quest, (11, 9)));
+    progress.record_held(&quest, |_| 5);
+    assert!(quest.is_complete(&progress));
+    progress.record_held(&quest, |_| 1);
+    assert!(!quest.is_complete(&progress));
+}
@@ -0,0 +1,66 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    block_type::{block_type_id, BlockTypeId},
+    component,
+    item::{item_type_id, ItemTypeId},
+    raws::Raw,
+};
+
+#[derive(Deserialize, Debug)]
+pub struct Recipe {
+    canonical_name: String,
+    //canonical item names and counts
+    inputs: Vec<(String, u32)>,
+    outputs: Vec<(String, u32)>,
+    //canonical name of a block that has to be next to the crafter
+    #[serde(default)]
+    station: Option<String>,
+    #[serde(default)]
+    craft_ticks: u64,
+    #[serde(default)]
+    xp: u64,
+}
+
+impl Recipe {
+    pub fn new(raw: &Raw) -> Result<Recipe, serde_json::Error> {
+        let res: Recipe = serde_json::from_value(raw.dat().clone())?;
+        Ok(res)
+    }
+    pub fn get_canonical_name(&self) -> &str {
+        &self.canonical_name
+    }
+    pub fn get_inputs(&self) -> Vec<(ItemTypeId, u32)> {
+        self.inputs
+            .iter()
+            .map(|(name, count)| (item_type_id(name), *count))
+            .collect()
+    }
+    pub fn get_outputs(&self) -> Vec<(ItemTypeId, u32)> {
+        self.outputs
+            .iter()
+            .map(|(name, count)| (item_type_id(name), *count))
+            .collect()
+    }
+    pub fn get_station(&self) -> Option<BlockTypeId> {
+        self.station.as_deref().map(block_type_id)
+    }
+    pub fn get_craft_ticks(&self) -> u64 {
+        self.craft_ticks
+    }
+    pub fn get_xp(&self) -> u64 {
+        self.xp
+    }
+}
+
+/**
+ * A craft in progress, replicated so the client can show progress
+ */
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Crafting {
+    pub recipe: String,
+    pub started_tick: u64,
+    pub finish_tick: u64,
+}
+
+impl component::ComponentType for Crafting {}
@@ -8,6 +8,17 @@ pub enum ResourceId {
     Grass1,
     Dirt1,
     AcidAnimation,
+    StoneWall,
+    Pit,
+    HealthPotion,
+    HastePotion,
+    Rock,
+    Sword,
+    Shrine,
+    Water,
+    Fire,
+    Bow,
+    Arrow,
 }
 
 #[derive(Clone)]
@@ -32,6 +43,54 @@ pub fn spawn_resource_map() -> HashMap<ResourceId, ResourceType> {
             ResourceId::Grass1,
             ResourceType::StaticImage("images/sprite/Grass1.png"),
         ),
+        (
+            ResourceId::Dirt1,
+            ResourceType::StaticImage("images/sprite/Dirt1.png"),
+        ),
+        (
+            ResourceId::StoneWall,
+            ResourceType::StaticImage("images/sprite/StoneWall.png"),
+        ),
+        (
+            ResourceId::Pit,
+            ResourceType::StaticImage("images/sprite/Pit.png"),
+        ),
+        (
+            ResourceId::HealthPotion,
+            ResourceType::StaticImage("images/sprite/HealthPotion.png"),
+        ),
+        (
+            ResourceId::HastePotion,
+            ResourceType::StaticImage("images/sprite/HastePotion.png"),
+        ),
+        (
+            ResourceId::Rock,
+            ResourceType::StaticImage("images/sprite/Rock.png"),
+        ),
+        (
+            ResourceId::Sword,
+            ResourceType::StaticImage("images/sprite/Sword.png"),
+        ),
+        (
+            ResourceId::Shrine,
+            ResourceType::StaticImage("images/sprite/Shrine.png"),
+        ),
+        (
+            ResourceId::Water,
+            ResourceType::StaticImage("images/sprite/Water.png"),
+        ),
+        (
+            ResourceId::Fire,
+            ResourceType::StaticImage("images/sprite/Fire.png"),
+        ),
+        (
+            ResourceId::Bow,
+            ResourceType::StaticImage("images/sprite/Bow.png"),
+        ),
+        (
+            ResourceId::Arrow,
+            ResourceType::StaticImage("images/sprite/Arrow.png"),
+        ),
         (
             ResourceId::AcidAnimation,
             ResourceType::Animation(&["images/sprite/Acid1.png", "images/sprite/Acid2.png"]),
@@ -1,11 +1,19 @@
 use serde::{Deserialize, Serialize};
 
+use crate::block_type::BlockTypeId;
+use crate::character::CharacterCreation;
+use crate::chat::ChatChannel;
+use crate::chunk::Position;
 use crate::entity_id::EntityId;
+use crate::group::PartyRules;
+use crate::world_rules::WorldRules;
 #[derive(Serialize, Deserialize, Debug)]
 #[serde(tag = "type")]
 pub enum ServerRequestType {
     CreateGame {
         world_name: String,
+        #[serde(default)]
+        rules: WorldRules,
     },
     PlayerList {
         world_name: String,
@@ -27,10 +35,40 @@ pub enum ServerRequestType {
     SendChat {
         world_name: String,
         message: String,
+        #[serde(default)]
+        channel: ChatChannel,
+        //who a whisper is for when not given with /w
+        #[serde(default)]
+        to: Option<String>,
+    },
+    ChatHistory {
+        world_name: String,
+        channel: ChatChannel,
+    },
+    //flag a message the reporter was sent, found by who said it and what it said
+    ReportMessage {
+        world_name: String,
+        username: String,
+        message: String,
+    },
+    Mute {
+        world_name: String,
+        username: String,
+        //forever if missing
+        #[serde(default)]
+        duration_secs: Option<u64>,
+        reason: String,
+    },
+    Unmute {
+        world_name: String,
+        username: String,
+    },
+    GetReports {
+        world_name: String,
     },
     Spawn {
         world_name: String,
-        player_parameters: String,
+        player_parameters: CharacterCreation,
     },
     RegisterUser {
         user: String,
@@ -42,8 +80,64 @@ pub enum ServerRequestType {
         world_name: String,
         action: PlayerActionType,
     },
+    TradeRequest {
+        world_name: String,
+        target: EntityId,
+    },
+    TradeOffer {
+        world_name: String,
+        items: Vec<EntityId>,
+        coins: u64,
+    },
+    TradeAccept {
+        world_name: String,
+    },
+    TradeCancel {
+        world_name: String,
+    },
+    PartyAction {
+        world_name: String,
+        action: PartyActionType,
+    },
+    GuildAction {
+        world_name: String,
+        action: GuildActionType,
+    },
 }
-#[derive(Serialize, Deserialize, Debug)]
+
+impl ServerRequestType {
+    /**
+     * The world a request is aimed at, None for requests about the account or server
+     */
+    pub fn get_world_name(&self) -> Option<&str> {
+        match self {
+            ServerRequestType::CreateGame { world_name, .. }
+            | ServerRequestType::PlayerList { world_name }
+            | ServerRequestType::Join { world_name }
+            | ServerRequestType::Leave { world_name }
+            | ServerRequestType::LoadGame { world_name }
+            | ServerRequestType::SendChat { world_name, .. }
+            | ServerRequestType::ChatHistory { world_name, .. }
+            | ServerRequestType::ReportMessage { world_name, .. }
+            | ServerRequestType::Mute { world_name, .. }
+            | ServerRequestType::Unmute { world_name, .. }
+            | ServerRequestType::GetReports { world_name }
+            | ServerRequestType::Spawn { world_name, .. }
+            | ServerRequestType::PlayerAction { world_name, .. }
+            | ServerRequestType::TradeRequest { world_name, .. }
+            | ServerRequestType::TradeOffer { world_name, .. }
+            | ServerRequestType::TradeAccept { world_name }
+            | ServerRequestType::TradeCancel { world_name }
+            | ServerRequestType::PartyAction { world_name, .. }
+            | ServerRequestType::GuildAction { world_name, .. } => Some(world_name),
+            ServerRequestType::Login { .. }
+            | ServerRequestType::Logout {}
+            | ServerRequestType::RegisterUser { .. }
+            | ServerRequestType::GetUserInviteCode {} => None,
+        }
+    }
+}
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum Direction {
     North,
     East,
@@ -55,6 +149,57 @@ pub enum Direction {
     Northwest,
 }
 
+impl Direction {
+    pub const ALL: [Direction; 8] = [
+        Direction::North,
+        Direction::East,
+        Direction::South,
+        Direction::West,
+        Direction::Northeast,
+        Direction::Southeast,
+        Direction::Southwest,
+        Direction::Northwest,
+    ];
+    pub const CARDINAL: [Direction; 4] = [
+        Direction::North,
+        Direction::East,
+        Direction::South,
+        Direction::West,
+    ];
+    /**
+     * The (x, y) step this direction moves by, north is negative y
+     */
+    pub fn offset(&self) -> (i32, i32) {
+        match self {
+            Direction::North => (0, -1),
+            Direction::East => (1, 0),
+            Direction::South => (0, 1),
+            Direction::West => (-1, 0),
+            Direction::Northeast => (1, -1),
+            Direction::Southeast => (1, 1),
+            Direction::Southwest => (-1, 1),
+            Direction::Northwest => (-1, -1),
+        }
+    }
+    pub fn from_offset(offset: (i32, i32)) -> Option<Direction> {
+        Direction::ALL.into_iter().find(|d| d.offset() == offset)
+    }
+    pub fn is_diagonal(&self) -> bool {
+        let (x, y) = self.offset();
+        x != 0 && y != 0
+    }
+    /**
+     * The block one step away in this direction, if it is inside the world
+     */
+    pub fn step(&self, position: Position) -> Option<Position> {
+        let (x, y) = self.offset();
+        Some((
+            u32::try_from(i64::from(position.0) + i64::from(x)).ok()?,
+            u32::try_from(i64::from(position.1) + i64::from(y)).ok()?,
+        ))
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug)]
 #[serde(tag = "type")]
 pub enum PlayerActionType {
@@ -63,4 +208,84 @@ pub enum PlayerActionType {
     UseOn { item: EntityId, target: EntityId },
     Pickup(EntityId),
     Drop(EntityId),
+    PlaceBlock {
+        position: Position,
+        block_type_id: BlockTypeId,
+    },
+    BreakBlock {
+        position: Position,
+    },
+    Craft {
+        recipe: String,
+    },
+    Equip(EntityId),
+    Unequip(EntityId),
+    BindRespawn {
+        position: Position,
+    },
+    Respawn,
+    TakeFrom {
+        container: EntityId,
+        item: EntityId,
+    },
+    //a chest fills from its loot table when first opened, then items are taken with TakeFrom
+    Open(EntityId),
+    Talk(EntityId),
+    AcceptQuest {
+        quest: String,
+    },
+    TurnInQuest {
+        quest: String,
+    },
+    Buy {
+        vendor: EntityId,
+        item: String,
+        count: u32,
+    },
+    Sell {
+        vendor: EntityId,
+        item: EntityId,
+    },
+    Repair {
+        vendor: EntityId,
+        item: EntityId,
+    },
+    Shoot {
+        weapon: EntityId,
+        target: AimTarget,
+    },
+}
+
+/**
+ * What a ranged attack is aimed at, an entity is aimed at where it stands when fired
+ */
+#[derive(Serialize, Deserialize, Debug)]
+pub enum AimTarget {
+    Entity(EntityId),
+    Position(Position),
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub enum PartyActionType {
+    Invite(EntityId),
+    Accept,
+    Decline,
+    Leave,
+    Kick(EntityId),
+    Promote(EntityId),
+    Disband,
+    SetRules(PartyRules),
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub enum GuildActionType {
+    Create { name: String },
+    Invite(EntityId),
+    Accept,
+    Decline,
+    Leave,
+    Kick(EntityId),
+    Promote(EntityId),
+    Demote(EntityId),
+    Disband,
 }
@@ -2,9 +2,13 @@ use serde::{Deserialize, Serialize};
 
 use crate::{
     block_type::BlockTypeId,
+    chat::{ChatChannel, ChatLine},
     chunk::{Chunk, ChunkId, Position},
     component::ComponentTypeId,
     entity_id::EntityId,
+    event::GameEvent,
+    moderation::Report,
+    trade::Trade,
 };
 
 pub type EncodingType = serde_json::Value;
@@ -26,15 +30,35 @@ pub enum ServerResponseType {
         world_name: String,
         component_updates: Vec<ComponentUpdate>,
         block_updates: Vec<BlockUpdate>,
+        events: Vec<GameEvent>,
     },
 
     ChatMessage {
         message: String,
         username: String,
+        #[serde(default)]
+        channel: ChatChannel,
+        #[serde(default)]
+        emote: bool,
+        #[serde(default)]
+        to: Option<String>,
+    },
+    ChatHistory {
+        channel: ChatChannel,
+        lines: Vec<ChatLine>,
     },
     PlayerList {
         players: Vec<String>,
     },
+    Reports {
+        reports: Vec<Report>,
+    },
+    Spawned {
+        entity_id: EntityId,
+    },
+    TradeStatus {
+        trade: Trade,
+    },
 }
 
 #[derive(Serialize, Deserialize, Clone)]
@@ -76,9 +100,18 @@ impl ComponentUpdate {
     }
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Clone)]
 #[serde(tag = "type")]
 pub struct BlockUpdate {
     pub block_pos: Position,
     pub block_type_id: BlockTypeId,
 }
+
+impl BlockUpdate {
+    pub fn new(block_pos: Position, block_type_id: BlockTypeId) -> Self {
+        BlockUpdate {
+            block_pos,
+            block_type_id,
+        }
+    }
+}
@@ -0,0 +1,183 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    component,
+    item::{item_type_id, ItemTypeId},
+    position::Position,
+    raws::Raw,
+};
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct ShopEntry {
+    item: String,
+    //coins for one item
+    price: u64,
+    //how many the vendor holds when fully stocked, unlimited if missing
+    #[serde(default)]
+    stock: Option<u32>,
+}
+
+impl ShopEntry {
+    pub fn get_item(&self) -> &str {
+        &self.item
+    }
+    pub fn get_price(&self) -> u64 {
+        self.price
+    }
+    pub fn get_stock(&self) -> Option<u32> {
+        self.stock
+    }
+}
+
+/**
+ * What a vendor sells, what it buys back and for how much
+ */
+#[derive(Deserialize, Debug)]
+pub struct Shop {
+    canonical_name: String,
+    #[serde(default)]
+    sells: Vec<ShopEntry>,
+    //item and the coins paid for one
+    #[serde(default)]
+    buys: Vec<(String, u64)>,
+    //coins per point of durability restored, vendors without it don't repair
+    #[serde(default)]
+    repair_cost: Option<u64>,
+    #[serde(default = "default_restock_ticks")]
+    restock_ticks: u64,
+}
+
+fn default_restock_ticks() -> u64 {
+    6000
+}
+
+impl Shop {
+    pub fn new(raw: &Raw) -> Result<Shop, serde_json::Error> {
+        let res: Shop = serde_json::from_value(raw.dat().clone())?;
+        Ok(res)
+    }
+    pub fn get_canonical_name(&self) -> &str {
+        &self.canonical_name
+    }
+    pub fn get_sells(&self) -> &[ShopEntry] {
+        &self.sells
+    }
+    pub fn get_entry(&self, item: &str) -> Option<&ShopEntry> {
+        self.sells.iter().find(|entry| entry.item == item)
+    }
+    /**
+     * Coins paid for one item of a type, None if the vendor won't take it
+     */
+    pub fn get_buy_price(&self, item: ItemTypeId) -> Option<u64> {
+        self.buys
+            .iter()
+            .find(|(name, _)| item_type_id(name) == item)
+            .map(|(_, price)| *price)
+    }
+    pub fn get_repair_cost(&self) -> Option<u64> {
+        self.repair_cost
+    }
+    pub fn get_restock_ticks(&self) -> u64 {
+        self.restock_ticks
+    }
+    /**
+     * Stock of every limited entry when fully stocked
+     */
+    pub fn full_stock(&self) -> Vec<(String, u32)> {
+        self.sells
+            .iter()
+            .filter_map(|entry| Some((entry.item.clone(), entry.stock?)))
+            .collect()
+    }
+}
+
+/**
+ * Where a vendor stands when a world is created
+ */
+#[derive(Deserialize, Debug)]
+pub struct VendorPlacement {
+    shop: String,
+    //npc kind, for quests that send players to talk to them
+    kind: String,
+    position: Position,
+}
+
+impl VendorPlacement {
+    pub fn new(raw: &Raw) -> Result<VendorPlacement, serde_json::Error> {
+        let res: VendorPlacement = serde_json::from_value(raw.dat().clone())?;
+        Ok(res)
+    }
+    pub fn get_shop(&self) -> &str {
+        &self.shop
+    }
+    pub fn get_kind(&self) -> &str {
+        &self.kind
+    }
+    pub fn get_position(&self) -> &Position {
+        &self.position
+    }
+}
+
+/**
+ * An npc that trades with players, holding what is left of its limited stock
+ */
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Vendor {
+    pub shop: String,
+    pub stock: Vec<(String, u32)>,
+    pub restock_tick: u64,
+}
+
+impl Vendor {
+    pub fn new(shop: &Shop, tick: u64) -> Self {
+        Vendor {
+            shop: shop.get_canonical_name().to_owned(),
+            stock: shop.full_stock(),
+            restock_tick: tick + shop.get_restock_ticks(),
+        }
+    }
+    /**
+     * How many of an item are left, None if the vendor never runs out
+     */
+    pub fn remaining(&self, item: &str) -> Option<u32> {
+        self.stock
+            .iter()
+            .find(|(name, _)| name == item)
+            .map(|(_, count)| *count)
+    }
+    /**
+     * Take items out of stock, false and nothing taken if there aren't enough
+     */
+    pub fn take(&mut self, item: &str, count: u32) -> bool {
+        for (name, left) in self.stock.iter_mut() {
+            if name == item {
+                if *left < count {
+                    return false;
+                }
+                *left -= count;
+            }
+        }
+        true
+    }
+}
+
+impl component::ComponentType for Vendor {}
+
+#[test]
+fn test_shop() {
+    let shop: Shop = serde_json::from_str(
+        r#"{ "canonical_name" : "general",
+        "sells" : [{ "item" : "healthpotion", "price" : 10, "stock" : 3 }, { "item" : "rock", "price" : 1 }],
+        "buys" : [["rock", 1]] }"#,
+    )
+    .unwrap();
+    assert_eq!(shop.get_buy_price(item_type_id("rock")), Some(1));
+    assert_eq!(shop.get_buy_price(item_type_id("healthpotion")), None);
+    assert_eq!(shop.get_repair_cost(), None);
+    let mut vendor = Vendor::new(&shop, 0);
+    assert_eq!(vendor.remaining("rock"), None);
+    assert!(vendor.take("rock", 50));
+    assert!(!vendor.take("healthpotion", 4));
+    assert!(vendor.take("healthpotion", 3));
+    assert_eq!(vendor.remaining("healthpotion"), Some(0));
+}
@@ -0,0 +1,209 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{component, raws::Raw};
+
+//biggest cut to speed in percent, slows can't stop an entity outright
+pub const MIN_SPEED_PERCENT: i32 = -90;
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Stat {
+    MaxHealth,
+    Damage,
+    Armor,
+    //percent faster movement
+    Speed,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ModifierKind {
+    Flat(i32),
+    Percent(i32),
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct StatModifier {
+    pub stat: Stat,
+    pub kind: ModifierKind,
+}
+
+impl StatModifier {
+    pub fn flat(stat: Stat, amount: i32) -> Self {
+        StatModifier {
+            stat,
+            kind: ModifierKind::Flat(amount),
+        }
+    }
+    pub fn percent(stat: Stat, amount: i32) -> Self {
+        StatModifier {
+            stat,
+            kind: ModifierKind::Percent(amount),
+        }
+    }
+}
+
+/**
+ * Stats before levels, equipment and effects are applied
+ */
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct BaseStats {
+    pub max_health: i32,
+    pub damage: i32,
+    pub armor: i32,
+    pub ticks_per_step: u64,
+}
+
+impl component::ComponentType for BaseStats {}
+
+/**
+ * Stats after every modifier, kept up to date by the server whenever an input changes
+ */
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct Stats {
+    pub max_health: i32,
+    pub damage: i32,
+    pub armor: i32,
+    pub ticks_per_step: u64,
+}
+
+impl component::ComponentType for Stats {}
+
+fn modify(base: i32, stat: Stat, modifiers: &[StatModifier]) -> i32 {
+    let mut flat = 0;
+    let mut percent = 0;
+    for modifier in modifiers.iter().filter(|m| m.stat == stat) {
+        match modifier.kind {
+            ModifierKind::Flat(amount) => flat += amount,
+            ModifierKind::Percent(amount) => percent += amount,
+        }
+    }
+    //flat bonuses first so percentages scale them too
+    (base + flat) * (100 + percent).max(0) / 100
+}
+
+/**
+ * Run base stats through a list of modifiers
+ */
+pub fn compute(base: &BaseStats, modifiers: &[StatModifier]) -> Stats {
+    //speed is already a percentage, so both kinds of modifier just add up
+    let speed = modifiers
+        .iter()
+        .filter(|m| m.stat == Stat::Speed)
+        .map(|m| match m.kind {
+            ModifierKind::Flat(amount) | ModifierKind::Percent(amount) => amount,
+        })
+        .sum::<i32>()
+        .max(MIN_SPEED_PERCENT);
+    Stats {
+        max_health: modify(base.max_health, Stat::MaxHealth, modifiers).max(1),
+        damage: modify(base.damage, Stat::Damage, modifiers).max(0),
+        armor: modify(base.armor, Stat::Armor, modifiers),
+        ticks_per_step: (base.ticks_per_step * 100 / (100 + speed) as u64).max(1),
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Experience {
+    pub xp: u64,
+    pub level: u32,
+}
+
+impl Experience {
+    pub fn new() -> Self {
+        Experience { xp: 0, level: 1 }
+    }
+}
+
+impl Default for Experience {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl component::ComponentType for Experience {}
+
+/**
+ * Experience handed to whoever kills this entity
+ */
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct XpReward {
+    pub xp: u64,
+}
+
+impl component::ComponentType for XpReward {}
+
+#[derive(Deserialize, Debug, Default)]
+pub struct LevelCurve {
+    //total xp needed to reach level 2, 3, ...
+    thresholds: Vec<u64>,
+    //flat bonuses gained for every level past the first
+    #[serde(default)]
+    per_level: Vec<StatModifier>,
+}
+
+impl LevelCurve {
+    pub fn new(raw: &Raw) -> Result<LevelCurve, serde_json::Error> {
+        let res: LevelCurve = serde_json::from_value(raw.dat().clone())?;
+        Ok(res)
+    }
+    pub fn level_for_xp(&self, xp: u64) -> u32 {
+        1 + self.thresholds.iter().take_while(|t| **t <= xp).count() as u32
+    }
+    /**
+     * Total xp needed to reach a level
+     */
+    pub fn xp_for_level(&self, level: u32) -> u64 {
+        match level.checked_sub(2) {
+            Some(i) => self.thresholds.get(i as usize).copied().unwrap_or(u64::MAX),
+            None => 0,
+        }
+    }
+    pub fn get_max_level(&self) -> u32 {
+        self.thresholds.len() as u32 + 1
+    }
+    pub fn modifiers_for_level(&self, level: u32) -> Vec<StatModifier> {
+        let levels = level.saturating_sub(1) as i32;
+        self.per_level
+            .iter()
+            .map(|m| StatModifier {
+                stat: m.stat,
+                kind: match m.kind {
+                    ModifierKind::Flat(amount) => ModifierKind::Flat(amount * levels),
+                    ModifierKind::Percent(amount) => ModifierKind::Percent(amount * levels),
+                },
+            })
+            .collect()
+    }
+}
+
+#[test]
+fn test_compute_stats() {
+    let base = BaseStats {
+        max_health: 30,
+        damage: 4,
+        armor: 1,
+        ticks_per_step: 4,
+    };
+    let stats = compute(
+        &base,
+        &[
+            StatModifier::flat(Stat::Damage, 6),
+            StatModifier::percent(Stat::Damage, 50),
+            StatModifier::percent(Stat::Speed, 100),
+        ],
+    );
+    assert_eq!(stats.damage, 15);
+    assert_eq!(stats.max_health, 30);
+    assert_eq!(stats.ticks_per_step, 2);
+    //slows stretch out steps, but never stop movement outright
+    let slowed = compute(&base, &[StatModifier::percent(Stat::Speed, -50)]);
+    assert_eq!(slowed.ticks_per_step, 8);
+    let frozen = compute(&base, &[StatModifier::percent(Stat::Speed, -500)]);
+    assert_eq!(frozen.ticks_per_step, 40);
+    let curve: LevelCurve =
+        serde_json::from_str(r#"{ "thresholds" : [10, 30], "per_level" : [] }"#).unwrap();
+    assert_eq!(curve.level_for_xp(0), 1);
+    assert_eq!(curve.level_for_xp(10), 2);
+    assert_eq!(curve.level_for_xp(1000), 3);
+    assert_eq!(curve.xp_for_level(1), 0);
+    assert_eq!(curve.xp_for_level(3), 30);
+}
@@ -0,0 +1,132 @@
+use serde::{Deserialize, Serialize};
+
+use crate::entity_id::EntityId;
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TradePhase {
+    //waiting for the partner to agree to trade at all
+    Requested,
+    //both sides can change their offer
+    Negotiating,
+    //both sides were happy with the offers, which can no longer change without starting over
+    Locked,
+}
+
+/**
+ * One side of a trade. Items stay in the owner's inventory until the trade goes through
+ */
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct TradeSide {
+    pub entity: EntityId,
+    //item stacks and the count each had when offered
+    pub items: Vec<(EntityId, u32)>,
+    pub coins: u64,
+    //first phase, happy with both offers
+    pub ready: bool,
+    //second phase, confirmed the locked offers
+    pub confirmed: bool,
+}
+
+impl TradeSide {
+    pub fn new(entity: EntityId) -> Self {
+        TradeSide {
+            entity,
+            items: Vec::new(),
+            coins: 0,
+            ready: false,
+            confirmed: false,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Trade {
+    pub initiator: TradeSide,
+    pub partner: TradeSide,
+    pub phase: TradePhase,
+    pub last_change_tick: u64,
+}
+
+impl Trade {
+    pub fn new(initiator: EntityId, partner: EntityId, tick: u64) -> Self {
+        Trade {
+            initiator: TradeSide::new(initiator),
+            partner: TradeSide::new(partner),
+            phase: TradePhase::Requested,
+            last_change_tick: tick,
+        }
+    }
+    pub fn involves(&self, entity: EntityId) -> bool {
+        self.initiator.entity == entity || self.partner.entity == entity
+    }
+    pub fn includes_item(&self, item: EntityId) -> bool {
+        self.initiator
+            .items
+            .iter()
+            .chain(self.partner.items.iter())
+            .any(|(i, _)| *i == item)
+    }
+    pub fn side_mut(&mut self, entity: EntityId) -> Option<&mut TradeSide> {
+        if self.initiator.entity == entity {
+            Some(&mut self.initiator)
+        } else if self.partner.entity == entity {
+            Some(&mut self.partner)
+        } else {
+            None
+        }
+    }
+    /**
+     * Any change to an offer means both sides have to agree all over again
+     */
+    pub fn reset_agreement(&mut self) {
+        for side in [&mut self.initiator, &mut self.partner] {
+            side.ready = false;
+            side.confirmed = false;
+        }
+        if self.phase == TradePhase::Locked {
+            self.phase = TradePhase::Negotiating;
+        }
+    }
+    /**
+     * Move through the two phases as both sides agree, true once the trade should go through
+     */
+    pub fn agree(&mut self, entity: EntityId) -> bool {
+        let phase = self.phase;
+        let side = match self.side_mut(entity) {
+            Some(side) => side,
+            None => return false,
+        };
+        match phase {
+            TradePhase::Requested => return false,
+            TradePhase::Negotiating => side.ready = true,
+            TradePhase::Locked => side.confirmed = true,
+        }
+        match phase {
+            TradePhase::Negotiating if self.initiator.ready && self.partner.ready => {
+                self.phase = TradePhase::Locked;
+                false
+            }
+            TradePhase::Locked => self.initiator.confirmed && self.partner.confirmed,
+            _ => false,
+        }
+    }
+}
+
+#[test]
+fn test_trade_phases() {
+    let a = EntityId::new_with_number(1);
+    let b = EntityId::new_with_number(2);
+    let mut trade = Trade::new(a, b, 0);
+    assert!(!trade.agree(a));
+    trade.phase = TradePhase::Negotiating;
+    assert!(!trade.agree(a));
+    assert!(!trade.agree(b));
+    assert_eq!(trade.phase, TradePhase::Locked);
+    assert!(!trade.agree(a));
+    trade.reset_agreement();
+    assert_eq!(trade.phase, TradePhase::Negotiating);
+    trade.agree(a);
+    trade.agree(b);
+    trade.agree(a);
+    assert!(trade.agree(b));
+}
@@ -0,0 +1,166 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{chunk, raws::Raw};
+
+//the longest a world can make players wait for anything
+pub const MAX_RULE_TICKS: u64 = 1_000_000;
+
+/**
+ * Rules picked when a world is created and stored with it
+ */
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct WorldRules {
+    #[serde(default = "default_pvp")]
+    pub pvp: bool,
+    //whether allies can hurt each other when pvp is on
+    #[serde(default)]
+    pub friendly_fire: bool,
+    //players further apart in level than this can't attack each other
+    #[serde(default)]
+    pub max_level_gap: Option<u32>,
+    //how long a dead player has to wait before respawning
+    #[serde(default = "default_respawn_delay")]
+    pub respawn_delay_ticks: u64,
+    //share of the xp earned towards the next level that is lost on death
+    #[serde(default = "default_xp_loss_percent")]
+    pub xp_loss_percent: u32,
+    //whether dead players leave their inventory behind in a corpse
+    #[serde(default = "default_drop_inventory")]
+    pub drop_inventory_on_death: bool,
+    #[serde(default = "default_corpse_lifetime")]
+    pub corpse_lifetime_ticks: u64,
+    //how long only the dead player can loot their corpse
+    #[serde(default = "default_corpse_owner")]
+    pub corpse_owner_ticks: u64,
+    //usernames allowed to mute players and read reports
+    #[serde(default)]
+    pub moderators: Vec<String>,
+}
+
+fn default_pvp() -> bool {
+    true
+}
+
+fn default_respawn_delay() -> u64 {
+    100
+}
+
+fn default_xp_loss_percent() -> u32 {
+    10
+}
+
+fn default_drop_inventory() -> bool {
+    true
+}
+
+fn default_corpse_lifetime() -> u64 {
+    6000
+}
+
+fn default_corpse_owner() -> u64 {
+    3000
+}
+
+impl Default for WorldRules {
+    fn default() -> Self {
+        WorldRules {
+            pvp: default_pvp(),
+            friendly_fire: false,
+            max_level_gap: None,
+            respawn_delay_ticks: default_respawn_delay(),
+            xp_loss_percent: default_xp_loss_percent(),
+            drop_inventory_on_death: default_drop_inventory(),
+            corpse_lifetime_ticks: default_corpse_lifetime(),
+            corpse_owner_ticks: default_corpse_owner(),
+            moderators: Vec::new(),
+        }
+    }
+}
+
+impl WorldRules {
+    /**
+     * Whether a client asked for rules the server can run with
+     */
+    pub fn is_valid(&self) -> bool {
+        self.xp_loss_percent <= 100
+            && self.respawn_delay_ticks <= MAX_RULE_TICKS
+            && self.corpse_lifetime_ticks <= MAX_RULE_TICKS
+            && self.corpse_owner_ticks <= MAX_RULE_TICKS
+    }
+}
+
+/**
+ * An area where players can't attack each other, regardless of the world rules
+ */
+#[derive(Deserialize, Debug)]
+pub struct SafeZone {
+    canonical_name: String,
+    from: chunk::Position,
+    to: chunk::Position,
+}
+
+impl SafeZone {
+    pub fn new(raw: &Raw) -> Result<SafeZone, serde_json::Error> {
+        let res: SafeZone = serde_json::from_value(raw.dat().clone())?;
+        Ok(res)
+    }
+    pub fn get_canonical_name(&self) -> &str {
+        &self.canonical_name
+    }
+    pub fn contains(&self, position: chunk::Position) -> bool {
+        chunk::area_contains((self.from, self.to), position)
+    }
+}
+
+/**
+ * An area only some players may build in, e.g. a town around the spawn
+ */
+#[derive(Deserialize, Debug)]
+pub struct BuildZone {
+    canonical_name: String,
+    from: chunk::Position,
+    to: chunk::Position,
+    //usernames allowed to place and break blocks here, nobody if empty
+    #[serde(default)]
+    builders: Vec<String>,
+}
+
+impl BuildZone {
+    pub fn new(raw: &Raw) -> Result<BuildZone, serde_json::Error> {
+        let res: BuildZone = serde_json::from_value(raw.dat().clone())?;
+        Ok(res)
+    }
+    pub fn get_canonical_name(&self) -> &str {
+        &self.canonical_name
+    }
+    pub fn contains(&self, position: chunk::Position) -> bool {
+        chunk::area_contains((self.from, self.to), position)
+    }
+    pub fn allows(&self, username: &str) -> bool {
+        self.builders
+            .iter()
+            .any(|builder| builder.eq_ignore_ascii_case(username))
+    }
+}
+
+#[test]
+fn test_rules_bounds() {
+    assert!(WorldRules::default().is_valid());
+    let rules = WorldRules {
+        corpse_lifetime_ticks: u64::MAX,
+        ..WorldRules::default()
+    };
+    assert!(!rules.is_valid());
+}
+
+#[test]
+fn test_build_zone() {
+    let zone: BuildZone = serde_json::from_str(
+        r#"{ "canonical_name" : "town", "from" : [0, 0], "to" : [4, 4], "builders" : ["Mayor"] }"#,
+    )
+    .unwrap();
+    assert!(zone.contains((4, 0)));
+    assert!(!zone.contains((5, 0)));
+    assert!(zone.allows("mayor"));
+    assert!(!zone.allows("someone"));
+}
@@ -0,0 +1,226 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    chunk::{self, LocationAttributes},
+    component,
+    effect::Effect,
+    entity_id::EntityId,
+    raws::Raw,
+};
+
+//a full day and night, twenty minutes at twenty ticks a second
+pub const DAY_LENGTH_TICKS: u64 = 24000;
+//how often each region rolls new weather
+pub const WEATHER_CHANGE_TICKS: u64 = 6000;
+
+/**
+ * The entity holding world wide state like the clock and weather, replicated to every player
+ */
+pub fn world_entity() -> EntityId {
+    EntityId::new_with_number(0)
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DayPhase {
+    Dawn,
+    Day,
+    Dusk,
+    Night,
+}
+
+impl DayPhase {
+    /**
+     * The phase for a tick, dawn and dusk each take a twelfth of the day
+     */
+    pub fn at(tick: u64) -> DayPhase {
+        let time = tick % DAY_LENGTH_TICKS;
+        let twelfth = DAY_LENGTH_TICKS / 12;
+        if time < twelfth {
+            DayPhase::Dawn
+        } else if time < twelfth * 6 {
+            DayPhase::Day
+        } else if time < twelfth * 7 {
+            DayPhase::Dusk
+        } else {
+            DayPhase::Night
+        }
+    }
+    pub fn is_night(&self) -> bool {
+        matches!(self, DayPhase::Night)
+    }
+}
+
+/**
+ * Only rewritten when the phase changes, clients work out the exact time from the tick
+ */
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct WorldTime {
+    pub day: u64,
+    pub phase: DayPhase,
+    pub day_length_ticks: u64,
+}
+
+impl WorldTime {
+    pub fn at(tick: u64) -> Self {
+        WorldTime {
+            day: tick / DAY_LENGTH_TICKS,
+            phase: DayPhase::at(tick),
+            day_length_ticks: DAY_LENGTH_TICKS,
+        }
+    }
+}
+
+impl component::ComponentType for WorldTime {}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum WeatherState {
+    Clear,
+    Rain,
+    Snow,
+    Storm,
+}
+
+/**
+ * Pick the weather for a climate from two rolls between 0 and 1
+ */
+pub fn choose_weather(
+    attributes: &LocationAttributes,
+    wet_roll: f32,
+    storm_roll: f32,
+) -> WeatherState {
+    if wet_roll >= attributes.get_humidity() {
+        return WeatherState::Clear;
+    }
+    //high ground catches more storms
+    let storm_chance = 0.1 + (attributes.get_altitude() / 1000.0).clamp(0.0, 0.4);
+    if storm_roll < storm_chance {
+        WeatherState::Storm
+    } else if attributes.get_temperature() <= 0.0 {
+        WeatherState::Snow
+    } else {
+        WeatherState::Rain
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct RegionWeather {
+    pub region: String,
+    pub state: WeatherState,
+}
+
+/**
+ * Current weather of every region
+ */
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct Weather {
+    pub regions: Vec<RegionWeather>,
+}
+
+impl Weather {
+    pub fn get(&self, region: &str) -> WeatherState {
+        self.regions
+            .iter()
+            .find(|r| r.region == region)
+            .map_or(WeatherState::Clear, |r| r.state)
+    }
+}
+
+impl component::ComponentType for Weather {}
+
+/**
+ * A named area of the world with its own climate
+ */
+#[derive(Deserialize, Debug)]
+pub struct Region {
+    canonical_name: String,
+    from: chunk::Position,
+    to: chunk::Position,
+    #[serde(default)]
+    attributes: LocationAttributes,
+    //canonical names of the spawn tables used by day and by night
+    #[serde(default)]
+    spawn_table: Option<String>,
+    #[serde(default)]
+    night_spawn_table: Option<String>,
+}
+
+impl Region {
+    pub fn new(raw: &Raw) -> Result<Region, serde_json::Error> {
+        let res: Region = serde_json::from_value(raw.dat().clone())?;
+        Ok(res)
+    }
+    pub fn get_canonical_name(&self) -> &str {
+        &self.canonical_name
+    }
+    pub fn contains(&self, position: chunk::Position) -> bool {
+        chunk::area_contains((self.from, self.to), position)
+    }
+    pub fn get_attributes(&self) -> &LocationAttributes {
+        &self.attributes
+    }
+    /**
+     * The spawn table for a phase of the day, night falls back to the day table
+     */
+    pub fn get_spawn_table(&self, phase: DayPhase) -> Option<&str> {
+        match (phase.is_night(), &self.night_spawn_table) {
+            (true, Some(table)) => Some(table),
+            _ => self.spawn_table.as_deref(),
+        }
+    }
+}
+
+/**
+ * What a kind of weather does to anything out in it
+ */
+#[derive(Deserialize, Debug)]
+pub struct WeatherRule {
+    weather: WeatherState,
+    //step time as a percentage of normal
+    #[serde(default = "default_movement_percent")]
+    movement_percent: u64,
+    //effects the weather puts out, like rain on fire
+    #[serde(default)]
+    removes: Vec<Effect>,
+    #[serde(default)]
+    applies: Option<(Effect, i32, u64)>,
+}
+
+fn default_movement_percent() -> u64 {
+    100
+}
+
+impl WeatherRule {
+    pub fn new(raw: &Raw) -> Result<WeatherRule, serde_json::Error> {
+        let res: WeatherRule = serde_json::from_value(raw.dat().clone())?;
+        Ok(res)
+    }
+    pub fn get_weather(&self) -> WeatherState {
+        self.weather
+    }
+    pub fn get_movement_percent(&self) -> u64 {
+        self.movement_percent
+    }
+    pub fn get_removes(&self) -> &[Effect] {
+        &self.removes
+    }
+    /**
+     * Effect, magnitude and duration in ticks
+     */
+    pub fn get_applies(&self) -> Option<(Effect, i32, u64)> {
+        self.applies
+    }
+}
+
+#[test]
+fn test_day_and_weather() {
+    assert_eq!(DayPhase::at(0), DayPhase::Dawn);
+    assert_eq!(DayPhase::at(DAY_LENGTH_TICKS / 4), DayPhase::Day);
+    assert_eq!(DayPhase::at(DAY_LENGTH_TICKS - 1), DayPhase::Night);
+    assert_eq!(WorldTime::at(DAY_LENGTH_TICKS * 3).day, 3);
+    let cold = LocationAttributes::new(-5.0, 0.0, 0.8);
+    assert_eq!(choose_weather(&cold, 0.9, 0.5), WeatherState::Clear);
+    assert_eq!(choose_weather(&cold, 0.2, 0.5), WeatherState::Snow);
+    assert_eq!(choose_weather(&cold, 0.2, 0.05), WeatherState::Storm);
+    let warm = LocationAttributes::default();
+    assert_eq!(choose_weather(&warm, 0.2, 0.5), WeatherState::Rain);
+}
@@ -0,0 +1,425 @@
+use mmolib::{
+    ai::{Action, Ai, BehaviorNode, Condition},
+    chunk,
+    combat::{AttackStats, Dead, Health},
+    entity_id::EntityId,
+    movement::Movement,
+    player::Player,
+    position::Position,
+    server_request_type::Direction,
+};
+use rand::Rng;
+
+use crate::{
+    combat, movement,
+    pathfinding::{self, TraversalCosts},
+    server_world::{ServerWorldError, ServerWorldRef},
+    visibility,
+};
+
+//how many behavior tree nodes all entities together may evaluate in one tick
+pub const NODE_BUDGET_PER_TICK: usize = 4096;
+//chance out of 100 that a wandering entity takes a step when it could
+const WANDER_CHANCE: u32 = 25;
+
+/**
+ * What an entity knows about its surroundings while thinking
+ */
+pub struct Blackboard {
+    pub health_percent: u32,
+    pub attack_range: u32,
+    pub target: Option<(EntityId, u32)>,
+    pub nearest_player: Option<(EntityId, u32)>,
+}
+
+struct Evaluation<'a> {
+    blackboard: &'a Blackboard,
+    budget: &'a mut usize,
+    target: Option<(EntityId, u32)>,
+    decision: Option<Action>,
+}
+
+fn check(condition: &Condition, state: &Evaluation) -> bool {
+    match condition {
+        Condition::HasTarget => state.target.is_some(),
+        Condition::TargetWithin(range) => state.target.is_some_and(|(_, d)| d <= *range),
+        Condition::PlayerWithin(range) => state
+            .blackboard
+            .nearest_player
+            .is_some_and(|(_, d)| d <= *range),
+        Condition::HealthBelow(percent) => state.blackboard.health_percent < *percent,
+        Condition::Not(condition) => !check(condition, state),
+    }
+}
+
+/**
+ * Whether an action can be done right now, choosing it if so
+ */
+fn choose(action: &Action, state: &mut Evaluation) -> bool {
+    let possible = match action {
+        Action::Idle | Action::Wander => true,
+        Action::Chase | Action::Flee => state.target.is_some(),
+        Action::Attack => state
+            .target
+            .is_some_and(|(_, d)| d <= state.blackboard.attack_range),
+        Action::AcquireTarget { range } => {
+            if state.target.is_some_and(|(_, d)| d <= *range) {
+                return true;
+            }
+            match state.blackboard.nearest_player {
+                Some((player, d)) if d <= *range => {
+                    state.target = Some((player, d));
+                    return true;
+                }
+                _ => false,
+            }
+        }
+    };
+    if possible {
+        state.decision = Some(action.clone());
+    }
+    possible
+}
+
+/**
+ * Evaluate a node, returning None when the budget runs out
+ */
+fn evaluate(node: &BehaviorNode, state: &mut Evaluation) -> Option<bool> {
+    if *state.budget == 0 {
+        return None;
+    }
+    *state.budget -= 1;
+    match node {
+        BehaviorNode::Sequence(children) => {
+            //a branch that fails partway leaves no decision or target behind
+            let (decision, target) = (state.decision.clone(), state.target);
+            for child in children {
+                if !evaluate(child, state)? {
+                    state.decision = decision;
+                    state.target = target;
+                    return Some(false);
+                }
+            }
+            Some(true)
+        }
+        BehaviorNode::Selector(children) => {
+            for child in children {
+                if evaluate(child, state)? {
+                    return Some(true);
+                }
+            }
+            Some(false)
+        }
+        BehaviorNode::Condition(condition) => Some(check(condition, state)),
+        BehaviorNode::Action(action) => Some(choose(action, state)),
+    }
+}
+
+/**
+ * The chosen action, if any, and the target with its distance
+ */
+pub type Decision = (Option<Action>, Option<(EntityId, u32)>);
+
+/**
+ * Pick an action and target for an entity. None if the budget ran out
+ */
+pub fn decide(
+    root: &BehaviorNode,
+    blackboard: &Blackboard,
+    budget: &mut usize,
+) -> Option<Decision> {
+    let mut state = Evaluation {
+        blackboard,
+        budget,
+        target: blackboard.target,
+        decision: None,
+    };
+    evaluate(root, &mut state)?;
+    Some((state.decision, state.target))
+}
+
+async fn block_position_of(
+    world: &ServerWorldRef,
+    entity_id: EntityId,
+) -> Result<Option<chunk::Position>, ServerWorldError> {
+    Ok(world
+        .get_optional_component::<Position>(entity_id)
+        .await?
+        .and_then(|p| p.block_position()))
+}
+
+async fn step(
+    world: &ServerWorldRef,
+    entity_id: EntityId,
+    from: chunk::Position,
+    direction: Direction,
+) -> Result<bool, ServerWorldError> {
+    match direction.step(from) {
+        Some(to) if movement::is_walkable(world, to).await? => {
+            movement::move_entity(world, entity_id, direction).await?;
+            Ok(true)
+        }
+        _ => Ok(false),
+    }
+}
+
+async fn execute(
+    world: &ServerWorldRef,
+    entity_id: EntityId,
+    from: chunk::Position,
+    action: Action,
+    target: Option<EntityId>,
+) -> Result<(), ServerWorldError> {
+    let target_position = match target {
+        Some(target) => block_position_of(world, target).await?,
+        None => None,
+    };
+    let can_move = world
+        .get_optional_component::<Movement>(entity_id)
+        .await?
+        .unwrap_or_default()
+        .next_move_tick
+        <= world.get_tick();
+    match action {
+        Action::Idle | Action::AcquireTarget { .. } => {}
+        Action::Attack => {
+            if let Some(target) = target {
+                combat::attack(world, entity_id, target).await?;
+            }
+        }
+        Action::Chase if can_move => {
+            if let Some(goal) = target_position {
+                let path = world
+                    .get_pathfinder()
+                    .find_path(
+                        world,
+                        from,
+                        goal,
+                        TraversalCosts::default(),
+                        pathfinding::DEFAULT_SEARCH_BUDGET,
+                    )
+                    .await?;
+                //the last block of the path is the target itself
+                if let Some(path) = path.filter(|p| p.len() > 1) {
+                    if let Some(direction) =
+                        pathfinding::path_to_directions(from, &path[..1]).first()
+                    {
+                        step(world, entity_id, from, *direction).await?;
+                    }
+                }
+            }
+        }
+        Action::Flee if can_move => {
+            if let Some(threat) = target_position {
+                let mut directions = Direction::ALL.to_vec();
+                directions.sort_by_key(|d| {
+                    std::cmp::Reverse(
+                        d.step(from)
+                            .map_or(0, |p| chunk::chebyshev_distance(p, threat)),
+                    )
+                });
+                for direction in directions {
+                    if step(world, entity_id, from, direction).await? {
+                        break;
+                    }
+                }
+            }
+        }
+        Action::Wander if can_move => {
+            //the rng can't be held across an await
+            let (wander, direction) = {
+                let mut rng = rand::thread_rng();
+                (
+                    rng.gen_range(0..100) < WANDER_CHANCE,
+                    Direction::ALL[rng.gen_range(0..Direction::ALL.len())],
+                )
+            };
+            if wander {
+                step(world, entity_id, from, direction).await?;
+            }
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+async fn think(
+    world: &ServerWorldRef,
+    entity_id: EntityId,
+    players: &[(EntityId, chunk::Position)],
+    budget: &mut usize,
+) -> Result<(), ServerWorldError> {
+    let mut ai = match world.get_optional_component::<Ai>(entity_id).await? {
+        Some(ai) => ai,
+        None => return Ok(()),
+    };
+    let tree = match world.get_behavior_tree(&ai.tree) {
+        Some(tree) => tree,
+        None => return Ok(()),
+    };
+    let from = match block_position_of(world, entity_id).await? {
+        Some(from) => from,
+        None => return Ok(()),
+    };
+    let health_percent = match world.get_optional_component::<Health>(entity_id).await? {
+        Some(health) if health.max > 0 => (health.current.max(0) * 100 / health.max) as u32,
+        _ => 100,
+    };
+    let mut target = None;
+    if let Some(t) = ai.target {
+        let alive = !world.has_component::<Dead>(t).await?;
+        if let (true, Some(p)) = (alive, block_position_of(world, t).await?) {
+            target = Some((t, chunk::chebyshev_distance(from, p)));
+        }
+    }
+    let mut nearest_player = None;
+    for (player, position) in players {
+        let distance = chunk::chebyshev_distance(from, *position);
+        if distance > visibility::VIEW_RADIUS
+            || nearest_player.is_some_and(|(_, d)| d <= distance)
+            || world.has_component::<Dead>(*player).await?
+            || !visibility::has_line_of_sight(world, from, *position).await?
+        {
+            continue;
+        }
+        nearest_player = Some((*player, distance));
+    }
+    let blackboard = Blackboard {
+        health_percent,
+        attack_range: world
+            .get_optional_component::<AttackStats>(entity_id)
+            .await?
+            .unwrap_or_default()
+            .range,
+        target,
+        nearest_player,
+    };
+    let (decision, target) = match decide(tree.get_root(), &blackboard, budget) {
+        Some(res) => res,
+        None => return Ok(()),
+    };
+    let target = target.map(|(t, _)| t);
+    if target != ai.target {
+        ai.target = target;
+        world.set_component(entity_id, ai).await?;
+    }
+    if let Some(action) = decision {
+        execute(world, entity_id, from, action, target).await?;
+    }
+    Ok(())
+}
+
+/**
+ * Tick the behavior tree of every entity with an Ai component
+ */
+pub async fn run(world: &ServerWorldRef) -> Result<(), ServerWorldError> {
+    let mut players = Vec::new();
+    for player in world
+        .get_entities_with_component_type_ids([
+            mmolib::component::get_type_id::<Player>(),
+            mmolib::component::get_type_id::<Position>(),
+        ])
+        .await?
+    {
+        if let Some(p) = block_position_of(world, player).await? {
+            players.push((player, p));
+        }
+    }
+    let mut entities: Vec<EntityId> = world
+        .get_entities_with_component_type_ids([
+            mmolib::component::get_type_id::<Ai>(),
+            mmolib::component::get_type_id::<Position>(),
+        ])
+        .await?
+        .into_iter()
+        .collect();
+    if entities.is_empty() {
+        return Ok(());
+    }
+    //start somewhere different each tick so a tight budget doesn't starve the same entities
+    entities.sort_by_key(|e| e.id());
+    let offset = (world.get_tick() % entities.len() as u64) as usize;
+    entities.rotate_left(offset);
+    let mut budget = NODE_BUDGET_PER_TICK;
+    for entity_id in entities {
+        if budget == 0 {
+            break;
+        }
+        think(world, entity_id, &players, &mut budget).await?;
+    }
+    Ok(())
+}
+
+#[test]
+fn test_decide() {
+    let tree: BehaviorNode = serde_json::from_str(
+        r#"{ "Selector" : [
+            { "Sequence" : [ { "Condition" : { "HealthBelow" : 25 } }, { "Action" : "Flee" } ] },
+            { "Sequence" : [
+                { "Action" : { "AcquireTarget" : { "range" : 8 } } },
+                { "Selector" : [
+                    { "Sequence" : [ { "Condition" : { "TargetWithin" : 1 } }, { "Action" : "Attack" } ] },
+                    { "Action" : "Chase" }
+                ] }
+            ] },
+            { "Action" : "Wander" }
+        ] }"#,
+    )
+    .unwrap();
+    let player = EntityId::new_with_number(7);
+    let mut blackboard = Blackboard {
+        health_percent: 100,
+        attack_range: 1,
+        target: None,
+        nearest_player: None,
+    };
+    let mut budget = 100;
+    assert_eq!(
+        decide(&tree, &blackboard, &mut budget).unwrap().0,
+        Some(Action::Wander)
+    );
+    blackboard.nearest_player = Some((player, 5));
+    let (action, target) = decide(&tree, &blackboard, &mut budget).unwrap();
+    assert_eq!(action, Some(Action::Chase));
+    assert_eq!(target.unwrap().0, player);
+    blackboard.nearest_player = Some((player, 1));
+    assert_eq!(
+        decide(&tree, &blackboard, &mut budget).unwrap().0,
+        Some(Action::Attack)
+    );
+    let mut budget = 2;
+    assert!(decide(&tree, &blackboard, &mut budget).is_none());
+}
+
+#[test]
+fn test_failed_sequence_is_undone() {
+    let tree: BehaviorNode = serde_json::from_str(
+        r#"{ "Selector" : [
+            { "Sequence" : [
+                { "Action" : { "AcquireTarget" : { "range" : 8 } } },
+                { "Action" : "Chase" },
+                { "Condition" : { "HealthBelow" : 25 } }
+            ] },
+            { "Action" : "Wander" }
+        ] }"#,
+    )
+    .unwrap();
+    let player = EntityId::new_with_number(7);
+    let mut blackboard = Blackboard {
+        health_percent: 100,
+        attack_range: 1,
+        target: None,
+        nearest_player: Some((player, 5)),
+    };
+    let mut budget = 100;
+    //the chase and the target it picked go with the sequence that failed after them
+    assert_eq!(
+        decide(&tree, &blackboard, &mut budget).unwrap(),
+        (Some(Action::Wander), None)
+    );
+    blackboard.health_percent = 10;
+    assert_eq!(
+        decide(&tree, &blackboard, &mut budget).unwrap(),
+        (Some(Action::Chase), Some((player, 5)))
+    );
+}
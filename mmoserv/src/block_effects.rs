@@ -0,0 +1,114 @@
+use mmolib::{
+    block_type::BlockLayer,
+    combat::{Dead, Health},
+    position::Position,
+};
+
+use crate::{
+    server_world::{ServerWorldError, ServerWorldRef},
+    status_effects,
+};
+
+/**
+ * Give every living entity standing on an effect layer block that block's effect
+ */
+pub async fn run(world: &ServerWorldRef) -> Result<(), ServerWorldError> {
+    let entities = world
+        .get_entities_with_component_type_ids([
+            mmolib::component::get_type_id::<Position>(),
+            mmolib::component::get_type_id::<Health>(),
+        ])
+        .await?;
+    for entity_id in entities {
+        //bodies keep their health and position until they respawn
+        if world.has_component::<Dead>(entity_id).await? {
+            continue;
+        }
+        let position = match world
+            .get_optional_component::<Position>(entity_id)
+            .await?
+            .and_then(|p| p.block_position())
+        {
+            Some(position) => position,
+            None => continue,
+        };
+        let block = match world.get_block(position).await {
+            Ok(block) => block,
+            Err(ServerWorldError::ChunkNotFound(_)) => continue,
+            Err(e) => return Err(e),
+        };
+        let block_type = match world.get_block_type(block) {
+            Some(block_type) => block_type,
+            None => continue,
+        };
+        if let BlockLayer::Effect(effect) = block_type.get_layer() {
+            status_effects::apply_effect(
+                world,
+                entity_id,
+                effect,
+                block_type.get_effect_magnitude(),
+                block_type.get_effect_duration_ticks(),
+                None,
+            )
+            .await?;
+        }
+    }
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_effect_blocks() -> Result<(), ServerWorldError> {
+    use mmolib::{
+        block_type::block_type_id,
+        chunk,
+        effect::{ActiveEffects, Effect},
+        entity_id::EntityId,
+    };
+    let world = crate::server_world::test_world("block_effects").await;
+    let mut blocks = [[block_type_id("dirt"); chunk::CHUNK_SIZE]; chunk::CHUNK_SIZE];
+    blocks[3][3] = block_type_id("acid");
+    world
+        .insert_chunk(
+            chunk::chunk_id_from_position((0, 0)),
+            chunk::Chunk::new_from_array(blocks),
+        )
+        .await?;
+    let standing = EntityId::new();
+    let walker = EntityId::new();
+    let body = EntityId::new();
+    for entity_id in [standing, walker, body] {
+        world.set_component(entity_id, Health::new(10)).await?;
+    }
+    world.set_component(standing, Position::new(3, 3)).await?;
+    world.set_component(body, Position::new(3, 3)).await?;
+    world.set_component(body, Dead { since_tick: 0 }).await?;
+    world.set_component(walker, Position::new(1, 1)).await?;
+    //systems find entities through redis, so write them out as a tick would
+    world.write_all_changes().await?;
+    run(&world).await?;
+    let poison = world
+        .get_optional_component::<ActiveEffects>(standing)
+        .await?
+        .unwrap()
+        .effects
+        .into_iter()
+        .find(|e| e.effect == Effect::Poison)
+        .unwrap();
+    assert_eq!(poison.magnitude, 2);
+    assert_eq!(poison.remaining_ticks, 100);
+    for entity_id in [walker, body] {
+        assert!(world
+            .get_optional_component::<ActiveEffects>(entity_id)
+            .await?
+            .is_none_or(|effects| !effects.has(Effect::Poison)));
+    }
+    //stepping onto the block applies it on the next run
+    world.set_component(walker, Position::new(3, 3)).await?;
+    run(&world).await?;
+    assert!(world
+        .get_optional_component::<ActiveEffects>(walker)
+        .await?
+        .unwrap()
+        .has(Effect::Poison));
+    Ok(())
+}
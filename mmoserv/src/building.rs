@@ -0,0 +1,213 @@
+use mmolib::{
+    block_type::{BlockLayer, BlockTypeId},
+    chunk, combat::Dead, entity_id::EntityId, player::Player, position::Position,
+    server_response_type::ServerResponseType,
+};
+
+use crate::server_world::{ServerWorldError, ServerWorldRef};
+
+//how many blocks away from the player a block may be placed or broken
+pub const BUILD_REACH: u32 = 1;
+
+/**
+ * Checks that the actor stands next to the target block
+ */
+async fn check_reach(
+    world: &ServerWorldRef,
+    actor: EntityId,
+    target: chunk::Position,
+) -> Result<Option<ServerResponseType>, ServerWorldError> {
+    let actor_position = world.get_component_ref::<Position>(actor).await?;
+    match actor_position.block_position() {
+        Some(p) if p != target && chunk::chebyshev_distance(p, target) <= BUILD_REACH => Ok(None),
+        _ => Ok(Some(ServerResponseType::Error {
+            message: "block out of reach",
+        })),
+    }
+}
+
+/**
+ * Checks that the actor stands next to the target block and may build there
+ */
+async fn check_build(
+    world: &ServerWorldRef,
+    actor: EntityId,
+    target: chunk::Position,
+) -> Result<Option<ServerResponseType>, ServerWorldError> {
+    if world.has_component::<Dead>(actor).await? {
+        return Ok(Some(ServerResponseType::PermissionDenied {}));
+    }
+    if let Some(rejection) = check_reach(world, actor, target).await? {
+        return Ok(Some(rejection));
+    }
+    let allowed = match world.get_optional_component::<Player>(actor).await? {
+        Some(player) => world.can_build(&player.username, target),
+        None => false,
+    };
+    if !allowed {
+        return Ok(Some(ServerResponseType::PermissionDenied {}));
+    }
+    Ok(None)
+}
+
+/**
+ * Whether a block of one layer can be placed over the block currently there
+ */
+fn can_place_on(current: Option<BlockLayer>, placed: BlockLayer) -> bool {
+    match current {
+        //anything can be built on open ground
+        Some(BlockLayer::Ground) => true,
+        //pits can only be filled back in with ground
+        Some(BlockLayer::Pit) => matches!(placed, BlockLayer::Ground),
+        _ => false,
+    }
+}
+
+pub async fn place_block(
+    world: &ServerWorldRef,
+    actor: EntityId,
+    target: chunk::Position,
+    block_type_id: BlockTypeId,
+) -> Result<ServerResponseType, ServerWorldError> {
+    if let Some(rejection) = check_build(world, actor, target).await? {
+        return Ok(rejection);
+    }
+    let placed = match world.get_block_type(block_type_id) {
+        Some(placed) => placed,
+        None => {
+            return Ok(ServerResponseType::Error {
+                message: "unknown block type",
+            })
+        }
+    };
+    if !placed.is_placeable() {
+        return Ok(ServerResponseType::PermissionDenied {});
+    }
+    let current = world.get_block(target).await?;
+    if !can_place_on(
+        world.get_block_type(current).map(|b| b.get_layer()),
+        placed.get_layer(),
+    ) {
+        return Ok(ServerResponseType::Error {
+            message: "cannot place block here",
+        });
+    }
+    if matches!(placed.get_layer(), BlockLayer::Solid)
+        && !world.get_entities_at(target).await?.is_empty()
+    {
+        return Ok(ServerResponseType::Error {
+            message: "block is occupied",
+        });
+    }
+    if !world
+        .replace_block(target, Some(current), block_type_id)
+        .await?
+    {
+        //someone else changed it since it was checked
+        return Ok(ServerResponseType::Error {
+            message: "block changed",
+        });
+    }
+    Ok(ServerResponseType::Ok {})
+}
+
+pub async fn break_block(
+    world: &ServerWorldRef,
+    actor: EntityId,
+    target: chunk::Position,
+) -> Result<ServerResponseType, ServerWorldError> {
+    if let Some(rejection) = check_build(world, actor, target).await? {
+        return Ok(rejection);
+    }
+    let current = world.get_block(target).await?;
+    let broken = match world.get_block_type(current) {
+        Some(broken) => broken,
+        None => {
+            return Ok(ServerResponseType::Error {
+                message: "unknown block type",
+            })
+        }
+    };
+    if broken.is_indestructible() {
+        return Ok(ServerResponseType::PermissionDenied {});
+    }
+    match broken.get_breaks_into() {
+        Some(replacement) => {
+            if !world.replace_block(target, Some(current), replacement).await? {
+                return Ok(ServerResponseType::Error {
+                    message: "block changed",
+                });
+            }
+            Ok(ServerResponseType::Ok {})
+        }
+        None => Ok(ServerResponseType::Error {
+            message: "block cannot be broken",
+        }),
+    }
+}
+
+#[test]
+fn test_can_place_on() {
+    assert!(can_place_on(Some(BlockLayer::Ground), BlockLayer::Solid));
+    assert!(can_place_on(Some(BlockLayer::Pit), BlockLayer::Ground));
+    assert!(!can_place_on(Some(BlockLayer::Pit), BlockLayer::Solid));
+    assert!(!can_place_on(Some(BlockLayer::Solid), BlockLayer::Ground));
+    assert!(!can_place_on(None, BlockLayer::Ground));
+}
+
+#[tokio::test]
+async fn test_place_and_break() -> Result<(), ServerWorldError> {
+    use mmolib::block_type::block_type_id;
+    let world = crate::server_world::test_world("building").await;
+    let floor = block_type_id("stonefloor");
+    let wall = block_type_id("stonewall");
+    world
+        .insert_chunk(
+            chunk::chunk_id_from_position((0, 0)),
+            chunk::Chunk::new_from_array([[floor; chunk::CHUNK_SIZE]; chunk::CHUNK_SIZE]),
+        )
+        .await?;
+    let actor = EntityId::new();
+    world.set_component(actor, Position::new(2, 2)).await?;
+    world
+        .set_component(
+            actor,
+            Player {
+                username: "builder".to_owned(),
+            },
+        )
+        .await?;
+    assert!(matches!(
+        place_block(&world, actor, (3, 2), wall).await?,
+        ServerResponseType::Ok {}
+    ));
+    assert_eq!(world.get_block((3, 2)).await?, wall);
+    assert!(matches!(
+        place_block(&world, actor, (3, 2), floor).await?,
+        ServerResponseType::Error { .. }
+    ));
+    assert!(matches!(
+        place_block(&world, actor, (6, 2), wall).await?,
+        ServerResponseType::Error { .. }
+    ));
+    assert!(matches!(
+        break_block(&world, actor, (3, 2)).await?,
+        ServerResponseType::Ok {}
+    ));
+    assert_eq!(world.get_block((3, 2)).await?, floor);
+    //the dead can't build
+    world.set_component(actor, Dead { since_tick: 0 }).await?;
+    assert!(matches!(
+        place_block(&world, actor, (3, 2), wall).await?,
+        ServerResponseType::PermissionDenied {}
+    ));
+    world.remove_component::<Dead>(actor).await?;
+    //the spawn is a build zone nobody is listed for
+    world.set_component(actor, Position::new(9, 9)).await?;
+    assert!(matches!(
+        break_block(&world, actor, (10, 9)).await?,
+        ServerResponseType::PermissionDenied {}
+    ));
+    assert_eq!(world.get_block((10, 9)).await?, floor);
+    Ok(())
+}
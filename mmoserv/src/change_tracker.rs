@@ -24,6 +24,15 @@ impl ChangeTracker {
             key: (entity_id, mmolib::component::get_type_id::<T>()),
         }
     }
+    pub fn new_add_component(
+        entity_id: mmolib::entity_id::EntityId,
+        component: mmolib::component::Component,
+    ) -> Self {
+        ChangeTracker {
+            key: (entity_id, component.get_type_id()),
+            change_type: ChangeType::Add(component),
+        }
+    }
     pub fn new_remove(
         entity_id: mmolib::entity_id::EntityId,
         component_type_id: mmolib::component::ComponentTypeId,
@@ -59,4 +68,19 @@ impl ChangeTracker {
     ) {
         self.key
     }
+    /**
+     * The update sent to clients for this change
+     */
+    pub fn to_component_update(&self) -> mmolib::server_response_type::ComponentUpdate {
+        let update_type = match &self.change_type {
+            ChangeType::Add(c) => {
+                mmolib::server_response_type::ComponentUpdateType::Added { packet: c.to_value() }
+            }
+            ChangeType::Change(c) => {
+                mmolib::server_response_type::ComponentUpdateType::Changed { packet: c.to_value() }
+            }
+            ChangeType::Remove => mmolib::server_response_type::ComponentUpdateType::Removed,
+        };
+        mmolib::server_response_type::ComponentUpdate::new(self.key.0, self.key.1, update_type)
+    }
 }
@@ -0,0 +1,206 @@
+use mmolib::{
+    character::{is_valid_name, Character, CharacterClass, CharacterCreation, Race},
+    combat::{AttackStats, Defense, Health},
+    entity_id::EntityId,
+    item::Inventory,
+    movement::{Collider, Movement},
+    player::Player,
+    server_response_type::ServerResponseType,
+    stats::{BaseStats, Experience},
+};
+
+use crate::{
+    inventory, progression,
+    server_world::{ServerWorldError, ServerWorldRef},
+};
+
+pub const STARTING_INVENTORY_CAPACITY: usize = 20;
+
+/**
+ * Create a character for an account from the parameters sent with a Spawn request
+ */
+pub async fn spawn(
+    world: &ServerWorldRef,
+    username: &str,
+    parameters: &CharacterCreation,
+) -> Result<ServerResponseType, ServerWorldError> {
+    if !is_valid_name(&parameters.name) {
+        return Ok(ServerResponseType::Error {
+            message: "invalid character name",
+        });
+    }
+    let (class, race) = match (
+        world.get_class(&parameters.class),
+        world.get_race(&parameters.race),
+    ) {
+        (Some(class), Some(race)) => (class, race),
+        _ => {
+            return Ok(ServerResponseType::Error {
+                message: "unknown class or race",
+            })
+        }
+    };
+    if !race.allows_appearance(&parameters.appearance) {
+        return Ok(ServerResponseType::Error {
+            message: "appearance not allowed for race",
+        });
+    }
+    let entity_id = EntityId::new();
+    if !world.link_account(username, entity_id).await? {
+        return Ok(ServerResponseType::Error {
+            message: "account already has a character",
+        });
+    }
+    if !world.reserve_character_name(&parameters.name).await? {
+        world.unlink_account(username).await?;
+        return Ok(ServerResponseType::Error {
+            message: "character name taken",
+        });
+    }
+    if let Err(e) = create(world, username, parameters, class, race, entity_id).await {
+        //leave nothing half made behind
+        world.despawn_entity(entity_id).await?;
+        world.release_character_name(&parameters.name).await?;
+        world.unlink_account(username).await?;
+        return Err(e);
+    }
+    Ok(ServerResponseType::Spawned { entity_id })
+}
+
+async fn create(
+    world: &ServerWorldRef,
+    username: &str,
+    parameters: &CharacterCreation,
+    class: &CharacterClass,
+    race: &Race,
+    entity_id: EntityId,
+) -> Result<(), ServerWorldError> {
+    world
+        .set_component(
+            entity_id,
+            Player {
+                username: username.to_owned(),
+            },
+        )
+        .await?;
+    world
+        .set_component(
+            entity_id,
+            Character {
+                name: parameters.name.clone(),
+                class: parameters.class.clone(),
+                race: parameters.race.clone(),
+                appearance: parameters.appearance.clone(),
+            },
+        )
+        .await?;
+    world
+        .set_component(entity_id, world.get_spawn_point())
+        .await?;
+    world.set_component(entity_id, Collider {}).await?;
+    world
+        .set_component(entity_id, Movement::new(race.get_ticks_per_step()))
+        .await?;
+    world
+        .set_component(
+            entity_id,
+            Health::new(class.get_base_health() + race.get_health_bonus()),
+        )
+        .await?;
+    world
+        .set_component(
+            entity_id,
+            AttackStats {
+                damage: class.get_base_damage(),
+                ..AttackStats::default()
+            },
+        )
+        .await?;
+    world
+        .set_component(
+            entity_id,
+            Defense {
+                armor: class.get_base_armor(),
+            },
+        )
+        .await?;
+    world
+        .set_component(
+            entity_id,
+            BaseStats {
+                max_health: class.get_base_health() + race.get_health_bonus(),
+                damage: class.get_base_damage(),
+                armor: class.get_base_armor(),
+                ticks_per_step: race.get_ticks_per_step(),
+            },
+        )
+        .await?;
+    world.set_component(entity_id, Experience::new()).await?;
+    progression::refresh_stats(world, entity_id).await?;
+    world
+        .set_component(entity_id, Inventory::new(STARTING_INVENTORY_CAPACITY))
+        .await?;
+    for (item_name, count) in class.get_starting_items() {
+        inventory::give_item(
+            world,
+            entity_id,
+            mmolib::item::item_type_id(item_name),
+            *count,
+        )
+        .await?;
+    }
+    Ok(())
+}
+
+/**
+ * The character an account left in the world last session, if any
+ */
+pub async fn restore(
+    world: &ServerWorldRef,
+    username: &str,
+) -> Result<Option<EntityId>, ServerWorldError> {
+    match world.get_account_entity(username).await? {
+        Some(entity_id) if world.has_component::<Player>(entity_id).await? => Ok(Some(entity_id)),
+        _ => Ok(None),
+    }
+}
+
+#[tokio::test]
+async fn test_spawn_claims_account_once() -> Result<(), ServerWorldError> {
+    use mmolib::character::Appearance;
+    let world = crate::server_world::test_world("character").await;
+    //accounts and names stay taken between runs of the test
+    let run = EntityId::new().id() % 1_000_000;
+    let parameters = |name: String| CharacterCreation {
+        name,
+        class: "warrior".to_owned(),
+        race: "human".to_owned(),
+        appearance: Appearance {
+            skin: "tan".to_owned(),
+            hair: "red".to_owned(),
+        },
+    };
+    let first = format!("first{}", run);
+    let second = format!("second{}", run);
+    let entity_id = match spawn(&world, &first, &parameters(format!("Hero{}", run))).await? {
+        ServerResponseType::Spawned { entity_id } => entity_id,
+        _ => panic!("first spawn failed"),
+    };
+    //usable before the tick is written
+    assert_eq!(restore(&world, &first).await?, Some(entity_id));
+    assert!(matches!(
+        spawn(&world, &first, &parameters(format!("Other{}", run))).await?,
+        ServerResponseType::Error { .. }
+    ));
+    //a taken name doesn't leave the account claimed
+    assert!(matches!(
+        spawn(&world, &second, &parameters(format!("Hero{}", run))).await?,
+        ServerResponseType::Error { .. }
+    ));
+    assert_eq!(world.get_account_entity(&second).await?, None);
+    assert!(matches!(
+        spawn(&world, &second, &parameters(format!("Other{}", run))).await?,
+        ServerResponseType::Spawned { .. }
+    ));
+    Ok(())
+}
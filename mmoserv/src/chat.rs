@@ -0,0 +1,360 @@
+use mmolib::{
+    chat::{self, ChatChannel, ChatCommand, ChatLine},
+    chunk,
+    entity_id::EntityId,
+    player::Player,
+    position::Position,
+    server_response_type::ServerResponseType,
+};
+
+use crate::{
+    guild, moderation,
+    server_world::{ServerWorldError, ServerWorldRef},
+};
+
+//longest message anyone can send
+pub const MAX_MESSAGE_LENGTH: usize = 256;
+//how far local chat carries
+pub const LOCAL_CHAT_RANGE: u32 = 20;
+
+async fn username_of(
+    world: &ServerWorldRef,
+    entity_id: EntityId,
+) -> Result<Option<String>, ServerWorldError> {
+    Ok(world
+        .get_optional_component::<Player>(entity_id)
+        .await?
+        .map(|player| player.username))
+}
+
+async fn block_position_of(
+    world: &ServerWorldRef,
+    entity_id: EntityId,
+) -> Result<Option<chunk::Position>, ServerWorldError> {
+    Ok(world
+        .get_optional_component::<Position>(entity_id)
+        .await?
+        .and_then(|p| p.block_position()))
+}
+
+/**
+ * Everyone who should read a message sent by an entity on a channel
+ */
+async fn recipients(
+    world: &ServerWorldRef,
+    sender: EntityId,
+    channel: ChatChannel,
+) -> Result<Option<Vec<EntityId>>, ServerWorldError> {
+    Ok(match channel {
+        ChatChannel::World => Some(world.get_online().await),
+        ChatChannel::Local => {
+            let origin = match block_position_of(world, sender).await? {
+                Some(origin) => origin,
+                None => return Ok(None),
+            };
+            let mut near = Vec::new();
+            for entity_id in world.get_online().await {
+                if let Some(position) = block_position_of(world, entity_id).await? {
+                    if chunk::chebyshev_distance(origin, position) <= LOCAL_CHAT_RANGE {
+                        near.push(entity_id);
+                    }
+                }
+            }
+            Some(near)
+        }
+        ChatChannel::Party => world.get_party(sender).await.map(|party| party.members),
+        ChatChannel::Guild => guild::guild_of(world, sender)
+            .await?
+            .map(|guild| guild.get_members()),
+        //whispers are sent by name, see whisper
+        ChatChannel::Whisper => None,
+    })
+}
+
+/**
+ * Where a channel's lines are kept for someone, None for channels without history
+ */
+async fn history_name(
+    world: &ServerWorldRef,
+    entity_id: EntityId,
+    channel: ChatChannel,
+) -> Result<Option<String>, ServerWorldError> {
+    Ok(match channel {
+        ChatChannel::World => Some("world".to_owned()),
+        //only matters to whoever was nearby at the time
+        ChatChannel::Local => None,
+        ChatChannel::Party => world
+            .get_party(entity_id)
+            .await
+            .map(|party| format!("party:{}", party.id)),
+        ChatChannel::Guild => guild::guild_of(world, entity_id)
+            .await?
+            .map(|guild| format!("guild:{}", guild.id)),
+        ChatChannel::Whisper => username_of(world, entity_id)
+            .await?
+            .map(|username| format!("whisper:{}", username)),
+    })
+}
+
+async fn deliver(world: &ServerWorldRef, recipients: &[EntityId], line: &ChatLine) {
+    for recipient in recipients {
+        world
+            .send_to(
+                *recipient,
+                ServerResponseType::ChatMessage {
+                    message: line.message.clone(),
+                    username: line.username.clone(),
+                    channel: line.channel,
+                    emote: line.emote,
+                    to: line.to.clone(),
+                },
+            )
+            .await;
+        world
+            .record_received_chat(*recipient, line, moderation::REPORT_CONTEXT_LINES)
+            .await;
+    }
+}
+
+fn check_length(message: &str) -> Result<(), ServerResponseType> {
+    if message.is_empty() || message.chars().count() > MAX_MESSAGE_LENGTH {
+        return Err(ServerResponseType::Error {
+            message: "message is empty or too long",
+        });
+    }
+    Ok(())
+}
+
+/**
+ * Handle a line typed into chat, either a message for a channel or a slash command.
+ * Messages go out with the next tick
+ */
+pub async fn send(
+    world: &ServerWorldRef,
+    sender: EntityId,
+    message: &str,
+    channel: ChatChannel,
+    to: Option<&str>,
+) -> Result<ServerResponseType, ServerWorldError> {
+    let username = match username_of(world, sender).await? {
+        Some(username) => username,
+        None => return Ok(ServerResponseType::PermissionDenied {}),
+    };
+    let command = match parse_for(message, channel, to) {
+        Ok(command) => command,
+        Err(message) => return Ok(ServerResponseType::Error { message }),
+    };
+    //looking up who is online is fine while muted and doesn't count as talking
+    if !matches!(command, ChatCommand::Who) {
+        if !world.allow_chat(sender).await {
+            return Ok(ServerResponseType::Error {
+                message: "sending messages too fast",
+            });
+        }
+        if let Some(response) = moderation::check_muted(world, sender).await? {
+            return Ok(response);
+        }
+    }
+    match command {
+        ChatCommand::Say { channel, message } => {
+            say(world, sender, username, channel, message, false).await
+        }
+        //emotes on the whisper channel go to whoever the whisper is addressed to
+        ChatCommand::Me {
+            channel: ChatChannel::Whisper,
+            action,
+        } => match to {
+            Some(to) => whisper(world, sender, username, to, action, true).await,
+            None => Ok(ServerResponseType::Error {
+                message: "whispered emotes need a recipient",
+            }),
+        },
+        ChatCommand::Me { channel, action } => {
+            say(world, sender, username, channel, action, true).await
+        }
+        ChatCommand::Whisper { to, message } => {
+            whisper(world, sender, username, &to, message, false).await
+        }
+        ChatCommand::Who => who(world).await,
+    }
+}
+
+/**
+ * Whispers sent without /w name their recipient alongside the message
+ */
+fn parse_for(
+    message: &str,
+    channel: ChatChannel,
+    to: Option<&str>,
+) -> Result<ChatCommand, &'static str> {
+    match chat::parse_command(message, channel)? {
+        ChatCommand::Say {
+            channel: ChatChannel::Whisper,
+            message,
+        } => match to {
+            Some(to) => Ok(ChatCommand::Whisper {
+                to: to.to_owned(),
+                message,
+            }),
+            None => Err("usage: /w <user> <message>"),
+        },
+        command => Ok(command),
+    }
+}
+
+async fn say(
+    world: &ServerWorldRef,
+    sender: EntityId,
+    username: String,
+    channel: ChatChannel,
+    message: String,
+    emote: bool,
+) -> Result<ServerResponseType, ServerWorldError> {
+    if let Err(response) = check_length(&message) {
+        return Ok(response);
+    }
+    let message = match moderation::filter_message(world, &message) {
+        Ok(message) => message,
+        Err(response) => return Ok(response),
+    };
+    let recipients = match recipients(world, sender, channel).await? {
+        Some(recipients) => recipients,
+        None => {
+            return Ok(ServerResponseType::Error {
+                message: "not in a group for that channel",
+            })
+        }
+    };
+    let line = ChatLine {
+        channel,
+        username,
+        message,
+        emote,
+        to: None,
+        tick: world.get_tick(),
+    };
+    deliver(world, &recipients, &line).await;
+    if let Some(name) = history_name(world, sender, channel).await? {
+        world.push_chat_history(&name, &line).await?;
+    }
+    Ok(ServerResponseType::Ok {})
+}
+
+async fn whisper(
+    world: &ServerWorldRef,
+    sender: EntityId,
+    username: String,
+    to: &str,
+    message: String,
+    emote: bool,
+) -> Result<ServerResponseType, ServerWorldError> {
+    if let Err(response) = check_length(&message) {
+        return Ok(response);
+    }
+    let message = match moderation::filter_message(world, &message) {
+        Ok(message) => message,
+        Err(response) => return Ok(response),
+    };
+    let recipient = match world.get_account_entity(to).await? {
+        Some(recipient) if world.is_online(recipient).await => recipient,
+        _ => {
+            return Ok(ServerResponseType::Error {
+                message: "player is not online",
+            })
+        }
+    };
+    let line = ChatLine {
+        channel: ChatChannel::Whisper,
+        username: username.clone(),
+        message,
+        emote,
+        to: Some(to.to_owned()),
+        tick: world.get_tick(),
+    };
+    //the sender sees their own whisper echoed back
+    let mut recipients = vec![recipient];
+    if recipient != sender {
+        recipients.push(sender);
+    }
+    deliver(world, &recipients, &line).await;
+    world
+        .push_chat_history(&format!("whisper:{}", username), &line)
+        .await?;
+    if to != username {
+        world
+            .push_chat_history(&format!("whisper:{}", to), &line)
+            .await?;
+    }
+    Ok(ServerResponseType::Ok {})
+}
+
+async fn who(world: &ServerWorldRef) -> Result<ServerResponseType, ServerWorldError> {
+    let mut players = Vec::new();
+    for entity_id in world.get_online().await {
+        if let Some(username) = username_of(world, entity_id).await? {
+            players.push(username);
+        }
+    }
+    players.sort();
+    Ok(ServerResponseType::PlayerList { players })
+}
+
+/**
+ * The recent lines of a channel, for catching up after joining
+ */
+pub async fn history(
+    world: &ServerWorldRef,
+    actor: EntityId,
+    channel: ChatChannel,
+) -> Result<ServerResponseType, ServerWorldError> {
+    let lines = match history_name(world, actor, channel).await? {
+        Some(name) => world.get_chat_history(&name).await?,
+        None => Vec::new(),
+    };
+    Ok(ServerResponseType::ChatHistory { channel, lines })
+}
+
+#[tokio::test]
+async fn test_whisper_emote() -> Result<(), ServerWorldError> {
+    let world = crate::server_world::test_world("chat").await;
+    let (sender, recipient) = (EntityId::new(), EntityId::new());
+    let names = [format!("a{}", sender.id()), format!("b{}", recipient.id())];
+    for (entity_id, username) in [(sender, &names[0]), (recipient, &names[1])] {
+        world
+            .set_component(
+                entity_id,
+                Player {
+                    username: username.clone(),
+                },
+            )
+            .await?;
+        world.link_account(username, entity_id).await?;
+    }
+    world.set_online(&[sender, recipient]).await;
+    assert!(matches!(
+        send(&world, sender, "/me waves", ChatChannel::Whisper, None).await?,
+        ServerResponseType::Error {
+            message: "whispered emotes need a recipient"
+        }
+    ));
+    assert!(matches!(
+        send(
+            &world,
+            sender,
+            "/me waves",
+            ChatChannel::Whisper,
+            Some(&names[1])
+        )
+        .await?,
+        ServerResponseType::Ok {}
+    ));
+    world.write_all_changes().await?;
+    let lines = world
+        .get_chat_history(&format!("whisper:{}", names[1]))
+        .await?;
+    let line = lines.last().unwrap();
+    assert!(line.emote);
+    assert_eq!(line.message, "waves");
+    assert_eq!(line.to.as_deref(), Some(names[1].as_str()));
+    Ok(())
+}
@@ -0,0 +1,158 @@
+use mmolib::{
+    ai::Npc,
+    combat::{compute_damage, AttackStats, Dead, Defense, Health},
+    chunk,
+    entity_id::EntityId,
+    event::GameEvent,
+    player::Player,
+    position::Position,
+    server_response_type::ServerResponseType,
+    stats::XpReward,
+};
+
+use crate::{
+    death, loot, party, progression, pvp,
+    server_world::{ServerWorldError, ServerWorldRef},
+    visibility,
+};
+
+/**
+ * Deal damage to an entity, killing it if its health runs out
+ */
+pub async fn apply_damage(
+    world: &ServerWorldRef,
+    attacker: Option<EntityId>,
+    target: EntityId,
+    damage: i32,
+) -> Result<(), ServerWorldError> {
+    let mut health = match world.get_optional_component::<Health>(target).await? {
+        Some(health) if !health.is_dead() => health,
+        _ => return Ok(()),
+    };
+    let location = world
+        .get_optional_component::<Position>(target)
+        .await?
+        .and_then(|p| p.block_position());
+    health.current -= damage;
+    let dead = health.is_dead();
+    world.set_component(target, health).await?;
+    if let Some(attacker) = attacker {
+        world
+            .emit_event(
+                location,
+                GameEvent::Hit {
+                    attacker,
+                    target,
+                    damage,
+                },
+            )
+            .await;
+    }
+    if dead {
+        kill(world, attacker, target, location).await?;
+    }
+    Ok(())
+}
+
+async fn kill(
+    world: &ServerWorldRef,
+    killer: Option<EntityId>,
+    target: EntityId,
+    location: Option<chunk::Position>,
+) -> Result<(), ServerWorldError> {
+    let kind = world
+        .get_optional_component::<Npc>(target)
+        .await?
+        .map(|npc| npc.kind);
+    world
+        .emit_event(
+            location,
+            GameEvent::Death {
+                entity: target,
+                killer,
+                kind,
+            },
+        )
+        .await;
+    if let (Some(killer), Some(reward)) = (
+        killer,
+        world.get_optional_component::<XpReward>(target).await?,
+    ) {
+        party::grant_kill_xp(world, killer, reward.xp).await?;
+    }
+    if world.has_component::<Player>(target).await? {
+        //players stay around so they can respawn
+        death::kill_player(world, target, location).await
+    } else {
+        if let Some(location) = location {
+            loot::drop_death_loot(world, target, killer, location).await?;
+        }
+        world.despawn_entity(target).await
+    }
+}
+
+pub async fn attack(
+    world: &ServerWorldRef,
+    attacker: EntityId,
+    target: EntityId,
+) -> Result<ServerResponseType, ServerWorldError> {
+    if attacker == target
+        || world.has_component::<Dead>(attacker).await?
+        || !pvp::can_harm(world, attacker, target).await?
+    {
+        return Ok(ServerResponseType::PermissionDenied {});
+    }
+    let mut attack = world
+        .get_optional_component::<AttackStats>(attacker)
+        .await?
+        .unwrap_or_default();
+    let tick = world.get_tick();
+    if tick < attack.next_attack_tick {
+        return Ok(ServerResponseType::Error {
+            message: "attack on cooldown",
+        });
+    }
+    match world.get_optional_component::<Health>(target).await? {
+        Some(health) if !health.is_dead() => {}
+        _ => {
+            return Ok(ServerResponseType::Error {
+                message: "target cannot be attacked",
+            })
+        }
+    }
+    let from = world
+        .get_optional_component::<Position>(attacker)
+        .await?
+        .and_then(|p| p.block_position());
+    let to = world
+        .get_optional_component::<Position>(target)
+        .await?
+        .and_then(|p| p.block_position());
+    let (from, to) = match (from, to) {
+        (Some(from), Some(to)) => (from, to),
+        _ => {
+            return Ok(ServerResponseType::Error {
+                message: "target out of range",
+            })
+        }
+    };
+    if chunk::chebyshev_distance(from, to) > attack.range
+        || !visibility::has_line_of_sight(world, from, to).await?
+    {
+        return Ok(ServerResponseType::Error {
+            message: "target out of range",
+        });
+    }
+    let defense = world
+        .get_optional_component::<Defense>(target)
+        .await?
+        .unwrap_or_default();
+    let damage = compute_damage(&attack, &defense);
+    attack.next_attack_tick = tick + attack.cooldown_ticks;
+    world.set_component(attacker, attack).await?;
+    //weapons wear from swinging, armour from being hit
+    progression::wear_equipment(world, attacker).await?;
+    progression::wear_equipment(world, target).await?;
+    apply_damage(world, Some(attacker), target, damage).await?;
+    Ok(ServerResponseType::Ok {})
+}
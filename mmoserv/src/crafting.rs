@@ -0,0 +1,245 @@
+use mmolib::{
+    chunk,
+    combat::Dead,
+    entity_id::EntityId,
+    event::GameEvent,
+    position::Position,
+    recipe::{Crafting, Recipe},
+    server_response_type::ServerResponseType,
+};
+
+use crate::{
+    inventory, progression,
+    server_world::{ServerWorldError, ServerWorldRef},
+};
+
+//how far away a crafting station can be, 1 is adjacent
+pub const STATION_REACH: u32 = 1;
+
+async fn has_station_nearby(
+    world: &ServerWorldRef,
+    from: chunk::Position,
+    station: mmolib::block_type::BlockTypeId,
+) -> Result<bool, ServerWorldError> {
+    let area = chunk::area_around(from, from, STATION_REACH);
+    let snapshot = world.snapshot_area(area).await?;
+    for x in area.0 .0..=area.1 .0 {
+        for y in area.0 .1..=area.1 .1 {
+            if snapshot.get_block((x, y)) == Some(station) {
+                return Ok(true);
+            }
+        }
+    }
+    Ok(false)
+}
+
+/**
+ * Why the actor can't craft a recipe right now, if anything
+ */
+async fn check_recipe(
+    world: &ServerWorldRef,
+    actor: EntityId,
+    recipe: &Recipe,
+) -> Result<Option<&'static str>, ServerWorldError> {
+    if world.has_component::<Dead>(actor).await? {
+        return Ok(Some("dead"));
+    }
+    for (item_type_id, count) in recipe.get_inputs() {
+        if inventory::count_items(world, actor, item_type_id).await? < count {
+            return Ok(Some("missing ingredients"));
+        }
+    }
+    if let Some(station) = recipe.get_station() {
+        let from = world
+            .get_optional_component::<Position>(actor)
+            .await?
+            .and_then(|p| p.block_position());
+        let near = match from {
+            Some(from) => has_station_nearby(world, from, station).await?,
+            None => false,
+        };
+        if !near {
+            return Ok(Some("no crafting station nearby"));
+        }
+    }
+    Ok(None)
+}
+
+/**
+ * Consume the inputs and hand out the outputs, dropping whatever doesn't fit on the floor
+ */
+async fn complete(
+    world: &ServerWorldRef,
+    actor: EntityId,
+    recipe: &Recipe,
+) -> Result<(), ServerWorldError> {
+    let location = world
+        .get_optional_component::<Position>(actor)
+        .await?
+        .and_then(|p| p.block_position());
+    if let Some(reason) = check_recipe(world, actor, recipe).await? {
+        world
+            .emit_event(
+                location,
+                GameEvent::CraftFailed {
+                    entity: actor,
+                    recipe: recipe.get_canonical_name().to_owned(),
+                    reason: reason.to_owned(),
+                },
+            )
+            .await;
+        return Ok(());
+    }
+    for (item_type_id, count) in recipe.get_inputs() {
+        inventory::take_items(world, actor, item_type_id, count).await?;
+    }
+    for (item_type_id, count) in recipe.get_outputs() {
+        let leftover = inventory::give_item(world, actor, item_type_id, count).await?;
+        if let (true, Some(location)) = (leftover > 0, location) {
+            inventory::spawn_item_at(world, location, item_type_id, leftover).await?;
+        }
+    }
+    progression::grant_xp(world, actor, recipe.get_xp()).await?;
+    world
+        .emit_event(
+            location,
+            GameEvent::CraftCompleted {
+                entity: actor,
+                recipe: recipe.get_canonical_name().to_owned(),
+            },
+        )
+        .await;
+    Ok(())
+}
+
+pub async fn craft(
+    world: &ServerWorldRef,
+    actor: EntityId,
+    recipe_name: &str,
+) -> Result<ServerResponseType, ServerWorldError> {
+    if world.has_component::<Dead>(actor).await? {
+        return Ok(ServerResponseType::PermissionDenied {});
+    }
+    if world.has_component::<Crafting>(actor).await? {
+        return Ok(ServerResponseType::Error {
+            message: "already crafting",
+        });
+    }
+    let recipe = match world.get_recipe(recipe_name) {
+        Some(recipe) => recipe,
+        None => {
+            return Ok(ServerResponseType::Error {
+                message: "unknown recipe",
+            })
+        }
+    };
+    if let Some(reason) = check_recipe(world, actor, recipe).await? {
+        return Ok(ServerResponseType::Error { message: reason });
+    }
+    if recipe.get_craft_ticks() == 0 {
+        complete(world, actor, recipe).await?;
+    } else {
+        let tick = world.get_tick();
+        world
+            .set_component(
+                actor,
+                Crafting {
+                    recipe: recipe_name.to_owned(),
+                    started_tick: tick,
+                    finish_tick: tick + recipe.get_craft_ticks(),
+                },
+            )
+            .await?;
+    }
+    Ok(ServerResponseType::Ok {})
+}
+
+/**
+ * Finish every craft whose time is up, rechecking the recipe in case things changed meanwhile
+ */
+pub async fn run(world: &ServerWorldRef) -> Result<(), ServerWorldError> {
+    let entities = world
+        .get_entities_with_component_type_ids([mmolib::component::get_type_id::<Crafting>()])
+        .await?;
+    for entity_id in entities {
+        let crafting = match world.get_optional_component::<Crafting>(entity_id).await? {
+            Some(crafting) if crafting.finish_tick <= world.get_tick() => crafting,
+            _ => continue,
+        };
+        world.remove_component::<Crafting>(entity_id).await?;
+        if let Some(recipe) = world.get_recipe(&crafting.recipe) {
+            complete(world, entity_id, recipe).await?;
+        }
+    }
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_craft() -> Result<(), ServerWorldError> {
+    use mmolib::{
+        block_type::block_type_id,
+        item::{item_type_id, Inventory},
+    };
+    let world = crate::server_world::test_world("crafting").await;
+    let mut blocks = [[block_type_id("dirt"); chunk::CHUNK_SIZE]; chunk::CHUNK_SIZE];
+    blocks[3][3] = block_type_id("acid");
+    world
+        .insert_chunk(
+            chunk::chunk_id_from_position((0, 0)),
+            chunk::Chunk::new_from_array(blocks),
+        )
+        .await?;
+    let actor = EntityId::new();
+    world.set_component(actor, Position::new(2, 2)).await?;
+    world.set_component(actor, Inventory::new(10)).await?;
+    assert!(matches!(
+        craft(&world, actor, "nothing").await?,
+        ServerResponseType::Error {
+            message: "unknown recipe"
+        }
+    ));
+    assert!(matches!(
+        craft(&world, actor, "healthpotion").await?,
+        ServerResponseType::Error {
+            message: "missing ingredients"
+        }
+    ));
+    inventory::give_item(&world, actor, item_type_id("rock"), 2).await?;
+    world.set_component(actor, Position::new(10, 10)).await?;
+    assert!(matches!(
+        craft(&world, actor, "healthpotion").await?,
+        ServerResponseType::Error {
+            message: "no crafting station nearby"
+        }
+    ));
+    world.set_component(actor, Position::new(2, 2)).await?;
+    assert!(matches!(
+        craft(&world, actor, "healthpotion").await?,
+        ServerResponseType::Ok {}
+    ));
+    assert!(matches!(
+        craft(&world, actor, "healthpotion").await?,
+        ServerResponseType::Error {
+            message: "already crafting"
+        }
+    ));
+    world.write_all_changes().await?;
+    let finish_tick = world
+        .get_optional_component::<Crafting>(actor)
+        .await?
+        .unwrap()
+        .finish_tick;
+    //nothing is used up until the timer runs out
+    while world.advance_tick() < finish_tick {
+        run(&world).await?;
+        assert_eq!(inventory::count_items(&world, actor, item_type_id("rock")).await?, 2);
+    }
+    run(&world).await?;
+    assert!(!world.has_component::<Crafting>(actor).await?);
+    assert_eq!(inventory::count_items(&world, actor, item_type_id("rock")).await?, 0);
+    assert_eq!(
+        inventory::count_items(&world, actor, item_type_id("healthpotion")).await?,
+        1
+    );
+    Ok(())
+}
@@ -0,0 +1,330 @@
+use mmolib::{
+    block_type::BlockTypeId,
+    chunk,
+    combat::{Corpse, Dead, Health, RespawnPoint},
+    effect::ActiveEffects,
+    entity_id::EntityId,
+    event::GameEvent,
+    item::{Equipment, Inventory},
+    position::Position,
+    recipe::Crafting,
+    server_response_type::ServerResponseType,
+    stats::Experience,
+    world_rules::WorldRules,
+};
+
+use crate::{
+    progression,
+    server_world::{ServerWorldError, ServerWorldRef},
+};
+
+//how far away a respawn anchor can be bound from, 1 is adjacent
+pub const BIND_REACH: u32 = 1;
+
+/**
+ * Take away part of the xp earned towards the next level. Levels themselves are never lost
+ */
+async fn lose_xp(
+    world: &ServerWorldRef,
+    entity_id: EntityId,
+    percent: u32,
+) -> Result<(), ServerWorldError> {
+    let mut experience = match world.get_optional_component::<Experience>(entity_id).await? {
+        Some(experience) => experience,
+        None => return Ok(()),
+    };
+    let floor = world.get_level_curve().xp_for_level(experience.level);
+    let progress = experience.xp.saturating_sub(floor);
+    let lost = progress * percent.min(100) as u64 / 100;
+    if lost > 0 {
+        experience.xp -= lost;
+        world.set_component(entity_id, experience).await?;
+    }
+    Ok(())
+}
+
+/**
+ * Move everything a player carries into a corpse where they died
+ */
+async fn leave_corpse(
+    world: &ServerWorldRef,
+    entity_id: EntityId,
+    location: chunk::Position,
+    rules: &WorldRules,
+) -> Result<(), ServerWorldError> {
+    let mut inventory = match world.get_optional_component::<Inventory>(entity_id).await? {
+        Some(inventory) if !inventory.items.is_empty() => inventory,
+        _ => return Ok(()),
+    };
+    let corpse_id = EntityId::new();
+    world
+        .set_component(
+            corpse_id,
+            Corpse {
+                owner: entity_id,
+                owner_until_tick: world.get_tick().saturating_add(rules.corpse_owner_ticks),
+                despawn_tick: world.get_tick().saturating_add(rules.corpse_lifetime_ticks),
+            },
+        )
+        .await?;
+    world
+        .set_component(corpse_id, Position::from_block_position(location))
+        .await?;
+    world
+        .set_component(
+            corpse_id,
+            Inventory {
+                capacity: inventory.items.len(),
+                items: std::mem::take(&mut inventory.items),
+            },
+        )
+        .await?;
+    world.set_component(entity_id, inventory).await?;
+    if world.has_component::<Equipment>(entity_id).await? {
+        world.remove_component::<Equipment>(entity_id).await?;
+        progression::refresh_stats(world, entity_id).await?;
+    }
+    Ok(())
+}
+
+/**
+ * Mark a player as dead and apply the death penalties from the world rules
+ */
+pub async fn kill_player(
+    world: &ServerWorldRef,
+    entity_id: EntityId,
+    location: Option<chunk::Position>,
+) -> Result<(), ServerWorldError> {
+    let rules = world.get_rules().await;
+    world
+        .set_component(
+            entity_id,
+            Dead {
+                since_tick: world.get_tick(),
+            },
+        )
+        .await?;
+    if world.has_component::<Crafting>(entity_id).await? {
+        world.remove_component::<Crafting>(entity_id).await?;
+    }
+    //effects stop with death, so poison can't keep hitting the body
+    if world.has_component::<ActiveEffects>(entity_id).await? {
+        world.remove_component::<ActiveEffects>(entity_id).await?;
+        progression::refresh_stats(world, entity_id).await?;
+    }
+    lose_xp(world, entity_id, rules.xp_loss_percent).await?;
+    if let (true, Some(location)) = (rules.drop_inventory_on_death, location) {
+        leave_corpse(world, entity_id, location, &rules).await?;
+    }
+    Ok(())
+}
+
+pub async fn bind_respawn(
+    world: &ServerWorldRef,
+    actor: EntityId,
+    anchor: chunk::Position,
+) -> Result<ServerResponseType, ServerWorldError> {
+    if world.has_component::<Dead>(actor).await? {
+        return Ok(ServerResponseType::PermissionDenied {});
+    }
+    let position = match world
+        .get_optional_component::<Position>(actor)
+        .await?
+        .and_then(|p| p.block_position())
+    {
+        Some(p) if chunk::chebyshev_distance(p, anchor) <= BIND_REACH => p,
+        _ => {
+            return Ok(ServerResponseType::Error {
+                message: "respawn point out of reach",
+            })
+        }
+    };
+    let block: BlockTypeId = world.get_block(anchor).await?;
+    if !world
+        .get_block_type(block)
+        .is_some_and(|b| b.is_respawn_anchor())
+    {
+        return Ok(ServerResponseType::Error {
+            message: "cannot bind respawn here",
+        });
+    }
+    //players come back standing where they bound, next to the anchor
+    world
+        .set_component(
+            actor,
+            RespawnPoint {
+                position: Position::from_block_position(position),
+            },
+        )
+        .await?;
+    Ok(ServerResponseType::Ok {})
+}
+
+pub async fn respawn(
+    world: &ServerWorldRef,
+    actor: EntityId,
+) -> Result<ServerResponseType, ServerWorldError> {
+    let dead = match world.get_optional_component::<Dead>(actor).await? {
+        Some(dead) => dead,
+        None => {
+            return Ok(ServerResponseType::Error {
+                message: "not dead",
+            })
+        }
+    };
+    let respawn_tick = dead
+        .since_tick
+        .saturating_add(world.get_rules().await.respawn_delay_ticks);
+    if world.get_tick() < respawn_tick {
+        return Ok(ServerResponseType::Error {
+            message: "cannot respawn yet",
+        });
+    }
+    world.remove_component::<Dead>(actor).await?;
+    if world.has_component::<ActiveEffects>(actor).await? {
+        world.remove_component::<ActiveEffects>(actor).await?;
+        progression::refresh_stats(world, actor).await?;
+    }
+    if let Some(mut health) = world.get_optional_component::<Health>(actor).await? {
+        health.current = health.max;
+        world.set_component(actor, health).await?;
+    }
+    let position = match world.get_optional_component::<RespawnPoint>(actor).await? {
+        Some(point) => point.position,
+        None => world.get_spawn_point(),
+    };
+    let location = position.block_position();
+    world.set_component(actor, position).await?;
+    world
+        .emit_event(location, GameEvent::Respawned { entity: actor })
+        .await;
+    Ok(ServerResponseType::Ok {})
+}
+
+/**
+ * Remove corpses that have been lying around too long, along with whatever is left in them
+ */
+pub async fn run(world: &ServerWorldRef) -> Result<(), ServerWorldError> {
+    let corpses = world
+        .get_entities_with_component_type_ids([mmolib::component::get_type_id::<Corpse>()])
+        .await?;
+    for corpse_id in corpses {
+        match world.get_optional_component::<Corpse>(corpse_id).await? {
+            Some(corpse) if corpse.despawn_tick <= world.get_tick() => {}
+            _ => continue,
+        }
+        if let Some(inventory) = world.get_optional_component::<Inventory>(corpse_id).await? {
+            for item_id in inventory.items {
+                world.despawn_entity(item_id).await?;
+            }
+        }
+        world.despawn_entity(corpse_id).await?;
+    }
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_death_respawn_and_corpse() -> Result<(), ServerWorldError> {
+    use crate::inventory;
+    use mmolib::{
+        component::get_type_id,
+        effect::{ActiveEffect, Effect},
+        item::{item_type_id, Item},
+    };
+    let world = crate::server_world::test_world("death").await;
+    let rules = world.get_rules().await;
+    let player = EntityId::new();
+    let looter = EntityId::new();
+    for entity_id in [player, looter] {
+        world.set_component(entity_id, Position::new(5, 5)).await?;
+        world.set_component(entity_id, Inventory::new(10)).await?;
+    }
+    world
+        .set_component(player, Health { current: 0, max: 10 })
+        .await?;
+    world
+        .set_component(
+            player,
+            ActiveEffects {
+                effects: vec![ActiveEffect {
+                    effect: Effect::Poison,
+                    magnitude: 1,
+                    remaining_ticks: 100,
+                    source: None,
+                }],
+            },
+        )
+        .await?;
+    inventory::give_item(&world, player, item_type_id("rock"), 2).await?;
+    inventory::give_item(&world, player, item_type_id("arrow"), 5).await?;
+    inventory::give_item(&world, player, item_type_id("healthpotion"), 1).await?;
+    kill_player(&world, player, Some((5, 5))).await?;
+    assert!(world.has_component::<Dead>(player).await?);
+    assert!(!world.has_component::<ActiveEffects>(player).await?);
+    assert_eq!(inventory::count_items(&world, player, item_type_id("rock")).await?, 0);
+    world.write_all_changes().await?;
+    let mut corpse_id = None;
+    for entity_id in world
+        .get_entities_with_component_type_ids([get_type_id::<Corpse>()])
+        .await?
+    {
+        let corpse = world.get_optional_component::<Corpse>(entity_id).await?;
+        if corpse.is_some_and(|c| c.owner == player) {
+            corpse_id = Some(entity_id);
+        }
+    }
+    let corpse_id = corpse_id.expect("no corpse left");
+    let items = world
+        .get_optional_component::<Inventory>(corpse_id)
+        .await?
+        .unwrap()
+        .items;
+    assert_eq!(items.len(), 3);
+    //only the owner can loot at first
+    assert!(matches!(
+        inventory::take_from(&world, looter, corpse_id, items[0]).await?,
+        ServerResponseType::PermissionDenied {}
+    ));
+    assert!(matches!(
+        respawn(&world, player).await?,
+        ServerResponseType::Error {
+            message: "cannot respawn yet"
+        }
+    ));
+    for _ in 0..rules.respawn_delay_ticks {
+        world.advance_tick();
+    }
+    assert!(matches!(
+        respawn(&world, player).await?,
+        ServerResponseType::Ok {}
+    ));
+    assert!(!world.has_component::<Dead>(player).await?);
+    assert_eq!(
+        world.get_optional_component::<Health>(player).await?.unwrap().current,
+        10
+    );
+    world.set_component(player, Position::new(5, 5)).await?;
+    assert!(matches!(
+        inventory::take_from(&world, player, corpse_id, items[0]).await?,
+        ServerResponseType::Ok {}
+    ));
+    //then anyone can
+    for _ in rules.respawn_delay_ticks..rules.corpse_owner_ticks {
+        world.advance_tick();
+    }
+    assert!(matches!(
+        inventory::take_from(&world, looter, corpse_id, items[1]).await?,
+        ServerResponseType::Ok {}
+    ));
+    world.write_all_changes().await?;
+    run(&world).await?;
+    assert!(world.has_component::<Corpse>(corpse_id).await?);
+    for _ in rules.corpse_owner_ticks..rules.corpse_lifetime_ticks {
+        world.advance_tick();
+    }
+    run(&world).await?;
+    assert!(!world.has_component::<Corpse>(corpse_id).await?);
+    //whatever was left rots with it
+    assert!(!world.has_component::<Item>(items[2]).await?);
+    Ok(())
+}
@@ -0,0 +1,284 @@
+use mmolib::{
+    block_type::{block_type_id, BlockLayer, BlockTypeId},
+    chunk,
+    combat::Health,
+    effect::{ActiveEffects, Effect},
+    position::Position,
+    server_request_type::Direction,
+};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    server_world::{ServerWorldError, ServerWorldRef},
+    weather,
+};
+
+//canonical name of the block shown while something burns
+pub const FIRE_BLOCK: &str = "fire";
+//fire spreads and burns out once every this many ticks
+pub const FIRE_TICK_INTERVAL: u64 = 10;
+
+/**
+ * A block on fire, remembered so it can burn into the right thing
+ */
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct BurningBlock {
+    pub original: BlockTypeId,
+    pub burns_out_tick: u64,
+}
+
+/**
+ * Set a flammable block alight, false if it can't burn
+ */
+pub async fn ignite(
+    world: &ServerWorldRef,
+    position: chunk::Position,
+) -> Result<bool, ServerWorldError> {
+    if world.is_burning(position).await {
+        return Ok(false);
+    }
+    let original = match world.get_loaded_block(position).await {
+        Some(original) => original,
+        None => return Ok(false),
+    };
+    let burn_ticks = match world.get_block_type(original) {
+        Some(block) if block.get_flammability() > 0 => block.get_burn_ticks(),
+        _ => return Ok(false),
+    };
+    //someone may have changed the block since it was read
+    if !world
+        .replace_block(position, Some(original), block_type_id(FIRE_BLOCK))
+        .await?
+    {
+        return Ok(false);
+    }
+    world
+        .set_burning(
+            position,
+            BurningBlock {
+                original,
+                burns_out_tick: world.get_tick().saturating_add(burn_ticks),
+            },
+        )
+        .await?;
+    Ok(true)
+}
+
+/**
+ * Replace a burning block with its ashes, whether it burnt down or was put out
+ */
+async fn burn_out(
+    world: &ServerWorldRef,
+    position: chunk::Position,
+    burning: &BurningBlock,
+) -> Result<(), ServerWorldError> {
+    world.clear_burning(position).await?;
+    let remains = world
+        .get_block_type(burning.original)
+        .and_then(|b| b.get_burns_into())
+        .or_else(|| {
+            world
+                .get_block_type(block_type_id(FIRE_BLOCK))
+                .and_then(|b| b.get_burns_into())
+        })
+        .unwrap_or(burning.original);
+    //leave whatever replaced the fire alone
+    world
+        .replace_block(position, Some(block_type_id(FIRE_BLOCK)), remains)
+        .await?;
+    Ok(())
+}
+
+async fn is_extinguished(
+    world: &ServerWorldRef,
+    position: chunk::Position,
+) -> Result<bool, ServerWorldError> {
+    for direction in Direction::CARDINAL {
+        if let Some(neighbour) = direction.step(position) {
+            let wet = world
+                .get_loaded_block(neighbour)
+                .await
+                .and_then(|b| world.get_block_type(b))
+                .is_some_and(|b| matches!(b.get_layer(), BlockLayer::Water));
+            if wet {
+                return Ok(true);
+            }
+        }
+    }
+    //weather that puts out burning entities puts out burning blocks too
+    let weather = weather::weather_at(world, position).await?;
+    Ok(world
+        .get_weather_rule(weather)
+        .is_some_and(|rule| rule.get_removes().contains(&Effect::Fire)))
+}
+
+async fn spread_from(
+    world: &ServerWorldRef,
+    position: chunk::Position,
+) -> Result<(), ServerWorldError> {
+    for direction in Direction::CARDINAL {
+        let neighbour = match direction.step(position) {
+            Some(neighbour) => neighbour,
+            None => continue,
+        };
+        let flammability = match world.get_loaded_block(neighbour).await {
+            Some(block) => world
+                .get_block_type(block)
+                .map_or(0, |b| b.get_flammability()),
+            None => continue,
+        };
+        //the rng can't be held across an await
+        let catches = flammability > 0 && rand::thread_rng().gen_range(0..100) < flammability;
+        if catches {
+            ignite(world, neighbour).await?;
+        }
+    }
+    Ok(())
+}
+
+/**
+ * Burning entities can set the ground under them alight
+ */
+async fn ignite_under_burning_entities(world: &ServerWorldRef) -> Result<(), ServerWorldError> {
+    let entities = world
+        .get_entities_with_component_type_ids([
+            mmolib::component::get_type_id::<ActiveEffects>(),
+            mmolib::component::get_type_id::<Position>(),
+            mmolib::component::get_type_id::<Health>(),
+        ])
+        .await?;
+    for entity_id in entities {
+        let on_fire = world
+            .get_optional_component::<ActiveEffects>(entity_id)
+            .await?
+            .is_some_and(|e| e.has(Effect::Fire));
+        if !on_fire {
+            continue;
+        }
+        let position = match world
+            .get_optional_component::<Position>(entity_id)
+            .await?
+            .and_then(|p| p.block_position())
+        {
+            Some(position) => position,
+            None => continue,
+        };
+        let flammability = match world.get_loaded_block(position).await {
+            Some(block) => world
+                .get_block_type(block)
+                .map_or(0, |b| b.get_flammability()),
+            None => continue,
+        };
+        if flammability > 0 && rand::thread_rng().gen_range(0..100) < flammability {
+            ignite(world, position).await?;
+        }
+    }
+    Ok(())
+}
+
+/**
+ * Spread, burn out and put out fires. Entities standing in fire get burnt by the fire block's effect layer
+ */
+pub async fn run(world: &ServerWorldRef) -> Result<(), ServerWorldError> {
+    if !world.get_tick().is_multiple_of(FIRE_TICK_INTERVAL) {
+        return Ok(());
+    }
+    for (position, burning) in world.get_burning_blocks().await {
+        //something else replaced the fire, like a block placed on it
+        if world.get_loaded_block(position).await != Some(block_type_id(FIRE_BLOCK)) {
+            world.clear_burning(position).await?;
+            continue;
+        }
+        if burning.burns_out_tick <= world.get_tick() || is_extinguished(world, position).await? {
+            burn_out(world, position, &burning).await?;
+        } else {
+            spread_from(world, position).await?;
+        }
+    }
+    ignite_under_burning_entities(world).await
+}
+
+#[tokio::test]
+async fn test_fire_spreads_and_burns_out() -> Result<(), ServerWorldError> {
+    let world = crate::server_world::test_world("fire").await;
+    let mut blocks = [[block_type_id("grass"); chunk::CHUNK_SIZE]; chunk::CHUNK_SIZE];
+    blocks[5][5] = block_type_id("stone");
+    world
+        .insert_chunk(
+            chunk::chunk_id_from_position((0, 0)),
+            chunk::Chunk::new_from_array(blocks),
+        )
+        .await?;
+    //fires left over from earlier runs would spread onto the fresh chunk
+    for (position, _) in world.get_burning_blocks().await {
+        world.clear_burning(position).await?;
+    }
+    //stone doesn't burn
+    assert!(!ignite(&world, (5, 5)).await?);
+    assert!(ignite(&world, (10, 10)).await?);
+    assert!(!ignite(&world, (10, 10)).await?);
+    assert_eq!(
+        world.get_loaded_block((10, 10)).await,
+        Some(block_type_id(FIRE_BLOCK))
+    );
+    //fires are still there after a restart
+    world.write_all_changes().await?;
+    assert!(
+        crate::server_world::test_world("fire")
+            .await
+            .is_burning((10, 10))
+            .await
+    );
+    let burns_out_tick = world
+        .get_burning_blocks()
+        .await
+        .into_iter()
+        .find(|(position, _)| *position == (10, 10))
+        .unwrap()
+        .1
+        .burns_out_tick;
+    let mut spread = false;
+    while world.get_tick() < burns_out_tick {
+        if world.advance_tick().is_multiple_of(FIRE_TICK_INTERVAL) {
+            run(&world).await?;
+        }
+        spread |= world
+            .get_burning_blocks()
+            .await
+            .iter()
+            .any(|(position, _)| *position != (10, 10));
+    }
+    assert!(spread);
+    while !world.advance_tick().is_multiple_of(FIRE_TICK_INTERVAL) {}
+    run(&world).await?;
+    //grass burns down to dirt
+    assert!(!world.is_burning((10, 10)).await);
+    assert_eq!(
+        world.get_loaded_block((10, 10)).await,
+        Some(block_type_id("dirt"))
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_fire_put_out_by_water() -> Result<(), ServerWorldError> {
+    let world = crate::server_world::test_world("fire_water").await;
+    let mut blocks = [[block_type_id("grass"); chunk::CHUNK_SIZE]; chunk::CHUNK_SIZE];
+    blocks[4][5] = block_type_id("water");
+    world
+        .insert_chunk(
+            chunk::chunk_id_from_position((0, 0)),
+            chunk::Chunk::new_from_array(blocks),
+        )
+        .await?;
+    assert!(ignite(&world, (5, 5)).await?);
+    while !world.advance_tick().is_multiple_of(FIRE_TICK_INTERVAL) {}
+    run(&world).await?;
+    assert!(!world.is_burning((5, 5)).await);
+    assert_eq!(
+        world.get_loaded_block((5, 5)).await,
+        Some(block_type_id("dirt"))
+    );
+    Ok(())
+}
@@ -0,0 +1,147 @@
+use mmolib::{
+    block_type::{BlockLayer, BlockType},
+    chunk,
+    server_request_type::Direction,
+};
+
+use crate::server_world::{ServerWorldError, ServerWorldRef};
+
+//deepest flowing water, sources count as one deeper
+pub const MAX_FLOW_LEVEL: u8 = 3;
+//water moves once every this many ticks
+pub const FLUID_TICK_INTERVAL: u64 = 5;
+//blocks the simulation may look at in one fluid tick, the rest wait for the next one
+pub const FLUID_UPDATES_PER_TICK: usize = 256;
+
+/**
+ * How deep the water in a block is, None for blocks water can't flow into
+ */
+fn level_of(block: Option<&BlockType>) -> Option<u8> {
+    let block = block?;
+    match block.get_layer() {
+        BlockLayer::Water if block.is_fluid_source() => Some(MAX_FLOW_LEVEL + 1),
+        BlockLayer::Water => Some(block.get_fluid_level()),
+        BlockLayer::Pit => Some(0),
+        _ => None,
+    }
+}
+
+/**
+ * The level a block settles at given its neighbours, every block away from the deepest one loses a level
+ */
+pub fn settle(neighbour_levels: &[u8]) -> u8 {
+    neighbour_levels
+        .iter()
+        .max()
+        .map_or(0, |l| l.saturating_sub(1))
+        .min(MAX_FLOW_LEVEL)
+}
+
+async fn update(world: &ServerWorldRef, position: chunk::Position) -> Result<(), ServerWorldError> {
+    let block_id = match world.get_loaded_block(position).await {
+        Some(block_id) => block_id,
+        None => return Ok(()),
+    };
+    let block = world.get_block_type(block_id);
+    let current = match level_of(block) {
+        Some(level) if level <= MAX_FLOW_LEVEL => level,
+        //sources and dry land don't change
+        _ => return Ok(()),
+    };
+    let mut neighbours = Vec::new();
+    for direction in Direction::CARDINAL {
+        if let Some(neighbour) = direction.step(position) {
+            //water doesn't flow in from chunks that aren't loaded
+            if let Some(level) = world
+                .get_loaded_block(neighbour)
+                .await
+                .and_then(|b| level_of(world.get_block_type(b)))
+            {
+                neighbours.push(level);
+            }
+        }
+    }
+    let level = settle(&neighbours);
+    if level == current {
+        return Ok(());
+    }
+    let new_block = if level == 0 {
+        //drained water leaves behind whatever it filled
+        match block.and_then(|b| b.get_breaks_into()) {
+            Some(dry) => dry,
+            None => return Ok(()),
+        }
+    } else {
+        match world.get_fluid_block(level) {
+            Some(wet) => wet,
+            None => return Ok(()),
+        }
+    };
+    //a player may have built over the block since it was read
+    world
+        .replace_block(position, Some(block_id), new_block)
+        .await?;
+    Ok(())
+}
+
+/**
+ * Let water flow into and drain out of the blocks waiting on the simulation
+ */
+pub async fn run(world: &ServerWorldRef) -> Result<(), ServerWorldError> {
+    if !world.get_tick().is_multiple_of(FLUID_TICK_INTERVAL) {
+        return Ok(());
+    }
+    for position in world.take_fluid_updates(FLUID_UPDATES_PER_TICK).await {
+        update(world, position).await?;
+    }
+    Ok(())
+}
+
+#[test]
+fn test_settle() {
+    assert_eq!(settle(&[]), 0);
+    assert_eq!(settle(&[0, 0]), 0);
+    assert_eq!(settle(&[MAX_FLOW_LEVEL + 1, 1]), MAX_FLOW_LEVEL);
+    assert_eq!(settle(&[2, 1]), 1);
+    assert_eq!(settle(&[1]), 0);
+}
+
+#[tokio::test]
+async fn test_broken_block_floods() -> Result<(), ServerWorldError> {
+    use mmolib::{
+        block_type::block_type_id, entity_id::EntityId, player::Player, position::Position,
+        server_response_type::ServerResponseType,
+    };
+    let world = crate::server_world::test_world("fluid").await;
+    world
+        .insert_chunk(
+            chunk::chunk_id_from_position((0, 0)),
+            chunk::Chunk::new_from_array(
+                [[block_type_id("dirt"); chunk::CHUNK_SIZE]; chunk::CHUNK_SIZE],
+            ),
+        )
+        .await?;
+    world.set_block((3, 3), block_type_id("water")).await?;
+    let digger = EntityId::new();
+    world.set_component(digger, Position::new(2, 2)).await?;
+    world
+        .set_component(
+            digger,
+            Player {
+                username: "digger".to_owned(),
+            },
+        )
+        .await?;
+    assert!(matches!(
+        crate::building::break_block(&world, digger, (2, 3)).await?,
+        ServerResponseType::Ok {}
+    ));
+    for _ in 0..3 {
+        while !world.advance_tick().is_multiple_of(FLUID_TICK_INTERVAL) {}
+        run(&world).await?;
+    }
+    assert_eq!(world.get_block((2, 3)).await?, block_type_id("water3"));
+    //the dirt around it holds the water in
+    assert_eq!(world.get_block((1, 3)).await?, block_type_id("dirt"));
+    Ok(())
+}
@@ -0,0 +1,62 @@
+use mmolib::{entity_id::EntityId, server_response_type::ServerResponseType};
+
+use crate::{
+    behavior, block_effects, crafting, death, fire, fluid, projectile, replication,
+    server_world::{ServerWorldError, ServerWorldRef},
+    quests, shop, spawning, status_effects, trading, weather,
+};
+
+pub const TICKS_PER_SECOND: u64 = 20;
+
+/**
+ * Run one world tick and build the Ticked response for every connected player entity
+ */
+pub async fn tick(
+    world: &ServerWorldRef,
+    viewers: &[EntityId],
+) -> Result<Vec<(EntityId, ServerResponseType)>, ServerWorldError> {
+    let joined = world.set_online(viewers).await;
+    weather::run(world).await?;
+    spawning::run(world).await?;
+    behavior::run(world).await?;
+    projectile::run(world).await?;
+    block_effects::run(world).await?;
+    status_effects::run(world).await?;
+    crafting::run(world).await?;
+    death::run(world).await?;
+    fluid::run(world).await?;
+    fire::run(world).await?;
+    trading::run(world).await?;
+    shop::run(world).await?;
+    //last, so it sees every event from this tick
+    quests::run(world).await?;
+    let updates = world.get_pending_changes().await;
+    let events = world.get_pending_events().await;
+    //players who just joined haven't seen the clock or weather yet, which only update now and then
+    let mut joined_updates = replication::world_state(world).await?;
+    joined_updates.extend(updates.iter().cloned());
+    let mut responses = Vec::new();
+    for viewer in viewers {
+        let updates = if joined.contains(viewer) {
+            &joined_updates
+        } else {
+            &updates
+        };
+        responses.push((
+            *viewer,
+            replication::build_ticked(world, *viewer, updates, &events).await?,
+        ));
+    }
+    //chat only reaches players who are around to read it
+    for (recipient, message) in world.take_outbox().await {
+        if viewers.contains(&recipient) {
+            responses.push((recipient, message));
+        }
+    }
+    world.clear_changes().await;
+    world.clear_block_updates().await;
+    world.clear_events().await;
+    world.write_all_changes().await?;
+    world.advance_tick();
+    Ok(responses)
+}
@@ -0,0 +1,301 @@
+use mmolib::{
+    entity_id::EntityId,
+    event::GameEvent,
+    group::{self, Guild, GuildMembership, GuildRank},
+    player::Player,
+    server_request_type::GuildActionType,
+    server_response_type::ServerResponseType,
+};
+
+use crate::server_world::{ServerWorldError, ServerWorldRef};
+
+/**
+ * The guild an entity belongs to, if it still exists
+ */
+pub async fn guild_of(
+    world: &ServerWorldRef,
+    entity_id: EntityId,
+) -> Result<Option<Guild>, ServerWorldError> {
+    match world
+        .get_optional_component::<GuildMembership>(entity_id)
+        .await?
+    {
+        Some(membership) => world.get_guild(&membership.guild).await,
+        None => Ok(None),
+    }
+}
+
+async fn announce(world: &ServerWorldRef, guild: &Guild) {
+    world
+        .emit_event(
+            None,
+            GameEvent::GuildUpdated {
+                guild: guild.clone(),
+            },
+        )
+        .await;
+}
+
+async fn remove_member(
+    world: &ServerWorldRef,
+    guild: &mut Guild,
+    entity_id: EntityId,
+) -> Result<(), ServerWorldError> {
+    guild.remove(entity_id);
+    world.remove_component::<GuildMembership>(entity_id).await?;
+    world
+        .emit_event(
+            None,
+            GameEvent::GuildLeft {
+                entity: entity_id,
+                guild: guild.name.clone(),
+            },
+        )
+        .await;
+    Ok(())
+}
+
+async fn disband(world: &ServerWorldRef, mut guild: Guild) -> Result<(), ServerWorldError> {
+    for member in guild.get_members() {
+        remove_member(world, &mut guild, member).await?;
+    }
+    world.remove_guild(&guild.name).await;
+    Ok(())
+}
+
+pub async fn handle_guild_action(
+    world: &ServerWorldRef,
+    actor: EntityId,
+    action: &GuildActionType,
+) -> Result<ServerResponseType, ServerWorldError> {
+    //every action checks a guild then writes it back, so two at once could undo each other
+    //or both take the same name
+    let _guilds = world.lock_guilds().await;
+    match action {
+        GuildActionType::Create { name } => create(world, actor, name).await,
+        GuildActionType::Accept => accept(world, actor).await,
+        GuildActionType::Decline => {
+            world.take_guild_invite(actor).await;
+            Ok(ServerResponseType::Ok {})
+        }
+        _ => {
+            let guild = match guild_of(world, actor).await? {
+                Some(guild) => guild,
+                None => {
+                    return Ok(ServerResponseType::Error {
+                        message: "not in a guild",
+                    })
+                }
+            };
+            manage(world, actor, guild, action).await
+        }
+    }
+}
+
+async fn create(
+    world: &ServerWorldRef,
+    actor: EntityId,
+    name: &str,
+) -> Result<ServerResponseType, ServerWorldError> {
+    if !group::is_valid_guild_name(name) {
+        return Ok(ServerResponseType::Error {
+            message: "invalid guild name",
+        });
+    }
+    if guild_of(world, actor).await?.is_some() {
+        return Ok(ServerResponseType::Error {
+            message: "already in a guild",
+        });
+    }
+    if world.get_guild(name).await?.is_some() {
+        return Ok(ServerResponseType::Error {
+            message: "guild name taken",
+        });
+    }
+    let guild = Guild::new(EntityId::new(), name, actor);
+    world.put_guild(guild.clone()).await?;
+    world
+        .set_component(
+            actor,
+            GuildMembership {
+                guild: guild.name.clone(),
+            },
+        )
+        .await?;
+    announce(world, &guild).await;
+    Ok(ServerResponseType::Ok {})
+}
+
+async fn accept(
+    world: &ServerWorldRef,
+    actor: EntityId,
+) -> Result<ServerResponseType, ServerWorldError> {
+    let name = match world.take_guild_invite(actor).await {
+        Some(name) => name,
+        None => {
+            return Ok(ServerResponseType::Error {
+                message: "no guild invite",
+            })
+        }
+    };
+    if guild_of(world, actor).await?.is_some() {
+        return Ok(ServerResponseType::Error {
+            message: "already in a guild",
+        });
+    }
+    let mut guild = match world.get_guild(&name).await? {
+        Some(guild) => guild,
+        None => {
+            return Ok(ServerResponseType::Error {
+                message: "guild no longer exists",
+            })
+        }
+    };
+    if guild.roster.len() >= group::MAX_GUILD_SIZE {
+        return Ok(ServerResponseType::Error {
+            message: "guild is full",
+        });
+    }
+    guild.roster.push((actor, GuildRank::Member));
+    world
+        .set_component(
+            actor,
+            GuildMembership {
+                guild: guild.name.clone(),
+            },
+        )
+        .await?;
+    announce(world, &guild).await;
+    world.put_guild(guild).await?;
+    Ok(ServerResponseType::Ok {})
+}
+
+/**
+ * Actions taken on a guild the actor is already in
+ */
+async fn manage(
+    world: &ServerWorldRef,
+    actor: EntityId,
+    mut guild: Guild,
+    action: &GuildActionType,
+) -> Result<ServerResponseType, ServerWorldError> {
+    let rank = guild.rank_of(actor).unwrap_or(GuildRank::Member);
+    match action {
+        GuildActionType::Invite(target) => {
+            if rank < GuildRank::Officer {
+                return Ok(ServerResponseType::PermissionDenied {});
+            }
+            if *target == actor || !world.has_component::<Player>(*target).await? {
+                return Ok(ServerResponseType::Error {
+                    message: "can only invite other players",
+                });
+            }
+            if guild_of(world, *target).await?.is_some() {
+                return Ok(ServerResponseType::Error {
+                    message: "already in a guild",
+                });
+            }
+            if guild.roster.len() >= group::MAX_GUILD_SIZE {
+                return Ok(ServerResponseType::Error {
+                    message: "guild is full",
+                });
+            }
+            world.invite_to_guild(&guild.name, *target).await;
+            world
+                .emit_event(
+                    None,
+                    GameEvent::GuildInvite {
+                        guild: guild.name.clone(),
+                        from: actor,
+                        to: *target,
+                    },
+                )
+                .await;
+        }
+        GuildActionType::Leave => {
+            if guild.roster.len() == 1 {
+                disband(world, guild).await?;
+                return Ok(ServerResponseType::Ok {});
+            }
+            if rank == GuildRank::Leader {
+                return Ok(ServerResponseType::Error {
+                    message: "promote a new leader before leaving",
+                });
+            }
+            remove_member(world, &mut guild, actor).await?;
+            announce(world, &guild).await;
+            world.put_guild(guild).await?;
+        }
+        GuildActionType::Kick(target) => {
+            match guild.rank_of(*target) {
+                //can only kick those of lower rank
+                Some(target_rank) if target_rank < rank && rank >= GuildRank::Officer => {}
+                Some(_) => return Ok(ServerResponseType::PermissionDenied {}),
+                None => {
+                    return Ok(ServerResponseType::Error {
+                        message: "not a member of your guild",
+                    })
+                }
+            }
+            remove_member(world, &mut guild, *target).await?;
+            announce(world, &guild).await;
+            world.put_guild(guild).await?;
+        }
+        GuildActionType::Promote(target) | GuildActionType::Demote(target) => {
+            if rank != GuildRank::Leader || *target == actor {
+                return Ok(ServerResponseType::PermissionDenied {});
+            }
+            let target_rank = match guild.rank_of(*target) {
+                Some(target_rank) => target_rank,
+                None => {
+                    return Ok(ServerResponseType::Error {
+                        message: "not a member of your guild",
+                    })
+                }
+            };
+            let new_rank = match action {
+                GuildActionType::Promote(_) => target_rank.promoted(),
+                _ => target_rank.demoted(),
+            };
+            if new_rank == GuildRank::Leader {
+                //there is only ever one leader, promoting hands it over
+                guild.set_rank(actor, GuildRank::Officer);
+            }
+            guild.set_rank(*target, new_rank);
+            announce(world, &guild).await;
+            world.put_guild(guild).await?;
+        }
+        GuildActionType::Disband => {
+            if rank != GuildRank::Leader {
+                return Ok(ServerResponseType::PermissionDenied {});
+            }
+            disband(world, guild).await?;
+        }
+        GuildActionType::Create { .. } | GuildActionType::Accept | GuildActionType::Decline => {
+            return Ok(ServerResponseType::Error {
+                message: "already in a guild",
+            })
+        }
+    }
+    Ok(ServerResponseType::Ok {})
+}
+
+#[tokio::test]
+async fn test_create_guild_once() -> Result<(), ServerWorldError> {
+    let world = crate::server_world::test_world("guild").await;
+    //fresh name each run as the test database outlives the test
+    let name = format!("guild {}", EntityId::new().id() % 1_000_000);
+    let (a, b) = (EntityId::new(), EntityId::new());
+    let create = GuildActionType::Create { name: name.clone() };
+    let (first, second) = tokio::join!(
+        handle_guild_action(&world, a, &create),
+        handle_guild_action(&world, b, &create)
+    );
+    let created = [first?, second?]
+        .iter()
+        .filter(|r| matches!(r, ServerResponseType::Ok {}))
+        .count();
+    assert_eq!(created, 1);
+    assert_eq!(world.get_guild(&name).await?.unwrap().roster.len(), 1);
+    Ok(())
+}
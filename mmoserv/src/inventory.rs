@@ -0,0 +1,549 @@
+use mmolib::{
+    chunk,
+    combat::{Corpse, Dead, Health},
+    entity_id::EntityId,
+    item::{Durability, Inventory, Item, ItemEffect, ItemTypeId},
+    loot::{Chest, LootOwner},
+    position::Position,
+    server_response_type::ServerResponseType,
+};
+
+use crate::{
+    combat, loot, progression, pvp,
+    server_world::{ServerWorldError, ServerWorldRef},
+    status_effects,
+};
+
+//how far away an item can be picked up or used on something, 1 is adjacent
+pub const ITEM_REACH: u32 = 1;
+
+/**
+ * Top up existing stacks of an item type, returning how many didn't fit
+ */
+async fn merge_into_stacks(
+    world: &ServerWorldRef,
+    inventory: &Inventory,
+    item_type_id: ItemTypeId,
+    mut count: u32,
+) -> Result<u32, ServerWorldError> {
+    let max_stack = world
+        .get_item_type(item_type_id)
+        .map_or(1, |t| t.get_max_stack());
+    for stack_id in &inventory.items {
+        if count == 0 {
+            break;
+        }
+        if let Some(mut stack) = world.get_optional_component::<Item>(*stack_id).await? {
+            if stack.item_type_id == item_type_id && stack.count < max_stack {
+                let moved = count.min(max_stack - stack.count);
+                stack.count += moved;
+                count -= moved;
+                world.set_component(*stack_id, stack).await?;
+            }
+        }
+    }
+    Ok(count)
+}
+
+/**
+ * Create a new item entity outside of any inventory or the world
+ */
+async fn new_item(
+    world: &ServerWorldRef,
+    item_type_id: ItemTypeId,
+    count: u32,
+) -> Result<EntityId, ServerWorldError> {
+    let item_id = EntityId::new();
+    world
+        .set_component(item_id, Item::new(item_type_id, count))
+        .await?;
+    if let Some(max) = world
+        .get_item_type(item_type_id)
+        .and_then(|t| t.get_max_durability())
+    {
+        world.set_component(item_id, Durability::new(max)).await?;
+    }
+    Ok(item_id)
+}
+
+/**
+ * Create items directly in an inventory, returning how many didn't fit
+ */
+pub async fn give_item(
+    world: &ServerWorldRef,
+    owner: EntityId,
+    item_type_id: ItemTypeId,
+    count: u32,
+) -> Result<u32, ServerWorldError> {
+    let mut inventory = match world.get_optional_component::<Inventory>(owner).await? {
+        Some(inventory) => inventory,
+        None => return Ok(count),
+    };
+    let max_stack = world
+        .get_item_type(item_type_id)
+        .map_or(1, |t| t.get_max_stack());
+    let mut count = merge_into_stacks(world, &inventory, item_type_id, count).await?;
+    while count > 0 && !inventory.is_full() {
+        let stack_count = count.min(max_stack);
+        let stack_id = new_item(world, item_type_id, stack_count).await?;
+        inventory.items.push(stack_id);
+        count -= stack_count;
+    }
+    world.set_component(owner, inventory).await?;
+    Ok(count)
+}
+
+/**
+ * Total number of items of a type held in an inventory, not counting stacks on offer in a trade
+ */
+pub async fn count_items(
+    world: &ServerWorldRef,
+    owner: EntityId,
+    item_type_id: ItemTypeId,
+) -> Result<u32, ServerWorldError> {
+    let inventory = match world.get_optional_component::<Inventory>(owner).await? {
+        Some(inventory) => inventory,
+        None => return Ok(0),
+    };
+    let mut total = 0;
+    for stack_id in &inventory.items {
+        if world.is_item_in_trade(*stack_id).await {
+            continue;
+        }
+        if let Some(stack) = world.get_optional_component::<Item>(*stack_id).await? {
+            if stack.item_type_id == item_type_id {
+                total += stack.count;
+            }
+        }
+    }
+    Ok(total)
+}
+
+/**
+ * Remove items of a type from an inventory, leaving stacks on offer in a trade alone.
+ * Nothing is removed unless there are enough
+ */
+pub async fn take_items(
+    world: &ServerWorldRef,
+    owner: EntityId,
+    item_type_id: ItemTypeId,
+    mut count: u32,
+) -> Result<bool, ServerWorldError> {
+    let mut inventory = match world.get_optional_component::<Inventory>(owner).await? {
+        Some(inventory) => inventory,
+        None => return Ok(count == 0),
+    };
+    let mut stacks = Vec::new();
+    let mut available = 0;
+    for stack_id in &inventory.items {
+        if world.is_item_in_trade(*stack_id).await {
+            continue;
+        }
+        if let Some(stack) = world.get_optional_component::<Item>(*stack_id).await? {
+            if stack.item_type_id == item_type_id {
+                available += stack.count;
+                stacks.push((*stack_id, stack));
+            }
+        }
+    }
+    if available < count {
+        return Ok(false);
+    }
+    for (stack_id, mut stack) in stacks {
+        if count == 0 {
+            break;
+        }
+        let taken = count.min(stack.count);
+        stack.count -= taken;
+        count -= taken;
+        if stack.count == 0 {
+            inventory.remove(stack_id);
+            progression::unequip_if_equipped(world, owner, stack_id).await?;
+            world.despawn_entity(stack_id).await?;
+        } else {
+            world.set_component(stack_id, stack).await?;
+        }
+    }
+    world.set_component(owner, inventory).await?;
+    Ok(true)
+}
+
+/**
+ * Put a new item entity on the ground
+ */
+pub async fn spawn_item_at(
+    world: &ServerWorldRef,
+    position: chunk::Position,
+    item_type_id: ItemTypeId,
+    count: u32,
+) -> Result<EntityId, ServerWorldError> {
+    let item_id = new_item(world, item_type_id, count).await?;
+    world
+        .set_component(item_id, Position::from_block_position(position))
+        .await?;
+    Ok(item_id)
+}
+
+async fn block_position_of(
+    world: &ServerWorldRef,
+    entity_id: EntityId,
+) -> Result<Option<chunk::Position>, ServerWorldError> {
+    Ok(world
+        .get_optional_component::<Position>(entity_id)
+        .await?
+        .and_then(|p| p.block_position()))
+}
+
+async fn in_reach(
+    world: &ServerWorldRef,
+    actor: EntityId,
+    target: EntityId,
+) -> Result<bool, ServerWorldError> {
+    match (
+        block_position_of(world, actor).await?,
+        block_position_of(world, target).await?,
+    ) {
+        (Some(a), Some(b)) => Ok(chunk::chebyshev_distance(a, b) <= ITEM_REACH),
+        _ => Ok(false),
+    }
+}
+
+pub async fn pickup(
+    world: &ServerWorldRef,
+    actor: EntityId,
+    item_id: EntityId,
+) -> Result<ServerResponseType, ServerWorldError> {
+    if world.has_component::<Dead>(actor).await? {
+        return Ok(ServerResponseType::PermissionDenied {});
+    }
+    //two players grabbing the same stack at once would otherwise both get all of it
+    let _economy = world.lock_economy().await;
+    let mut inventory = match world.get_optional_component::<Inventory>(actor).await? {
+        Some(inventory) => inventory,
+        None => {
+            return Ok(ServerResponseType::Error {
+                message: "no inventory",
+            })
+        }
+    };
+    let mut item = match world.get_optional_component::<Item>(item_id).await? {
+        Some(item) => item,
+        None => {
+            return Ok(ServerResponseType::Error {
+                message: "not an item",
+            })
+        }
+    };
+    if !in_reach(world, actor, item_id).await? {
+        return Ok(ServerResponseType::Error {
+            message: "item out of reach",
+        });
+    }
+    if let Some(owner) = world.get_optional_component::<LootOwner>(item_id).await? {
+        if !owner.allows(actor, world.get_tick()) {
+            return Ok(ServerResponseType::PermissionDenied {});
+        }
+    }
+    let remaining = merge_into_stacks(world, &inventory, item.item_type_id, item.count).await?;
+    if remaining == 0 {
+        //everything went onto existing stacks
+        world.despawn_entity(item_id).await?;
+    } else if !inventory.is_full() {
+        item.count = remaining;
+        world.set_component(item_id, item).await?;
+        world.remove_component::<Position>(item_id).await?;
+        world.remove_component::<LootOwner>(item_id).await?;
+        inventory.items.push(item_id);
+        world.set_component(actor, inventory).await?;
+    } else if remaining < item.count {
+        //part of the stack fit, the rest stays on the ground
+        item.count = remaining;
+        world.set_component(item_id, item).await?;
+    } else {
+        return Ok(ServerResponseType::Error {
+            message: "inventory full",
+        });
+    }
+    Ok(ServerResponseType::Ok {})
+}
+
+/**
+ * Move an item out of a container lying in the world, like a corpse, into the actor's inventory
+ */
+pub async fn take_from(
+    world: &ServerWorldRef,
+    actor: EntityId,
+    container_id: EntityId,
+    item_id: EntityId,
+) -> Result<ServerResponseType, ServerWorldError> {
+    if actor == container_id || world.has_component::<Dead>(actor).await? {
+        return Ok(ServerResponseType::PermissionDenied {});
+    }
+    let _economy = world.lock_economy().await;
+    let mut container = match world.get_optional_component::<Inventory>(container_id).await? {
+        Some(container) if container.contains(item_id) => container,
+        _ => {
+            return Ok(ServerResponseType::Error {
+                message: "item not in container",
+            })
+        }
+    };
+    //only things lying around can be looted, not other players' bags
+    let allowed = match world.get_optional_component::<Corpse>(container_id).await? {
+        Some(corpse) => corpse.allows(actor, world.get_tick()),
+        None => world
+            .get_optional_component::<Chest>(container_id)
+            .await?
+            .is_some_and(|chest| chest.opened),
+    };
+    if !allowed {
+        return Ok(ServerResponseType::PermissionDenied {});
+    }
+    if !in_reach(world, actor, container_id).await? {
+        return Ok(ServerResponseType::Error {
+            message: "container out of reach",
+        });
+    }
+    let mut inventory = match world.get_optional_component::<Inventory>(actor).await? {
+        Some(inventory) => inventory,
+        None => {
+            return Ok(ServerResponseType::Error {
+                message: "no inventory",
+            })
+        }
+    };
+    let mut item = match world.get_optional_component::<Item>(item_id).await? {
+        Some(item) => item,
+        None => {
+            return Ok(ServerResponseType::Error {
+                message: "not an item",
+            })
+        }
+    };
+    let remaining = merge_into_stacks(world, &inventory, item.item_type_id, item.count).await?;
+    if remaining == 0 {
+        container.remove(item_id);
+        world.set_component(container_id, container).await?;
+        world.despawn_entity(item_id).await?;
+    } else if !inventory.is_full() {
+        item.count = remaining;
+        world.set_component(item_id, item).await?;
+        container.remove(item_id);
+        world.set_component(container_id, container).await?;
+        inventory.items.push(item_id);
+        world.set_component(actor, inventory).await?;
+    } else if remaining < item.count {
+        item.count = remaining;
+        world.set_component(item_id, item).await?;
+    } else {
+        return Ok(ServerResponseType::Error {
+            message: "inventory full",
+        });
+    }
+    Ok(ServerResponseType::Ok {})
+}
+
+/**
+ * Open a chest, filling it from its loot table the first time
+ */
+pub async fn open(
+    world: &ServerWorldRef,
+    actor: EntityId,
+    container_id: EntityId,
+) -> Result<ServerResponseType, ServerWorldError> {
+    if world.has_component::<Dead>(actor).await? {
+        return Ok(ServerResponseType::PermissionDenied {});
+    }
+    //so two players opening it together don't both fill it
+    let _economy = world.lock_economy().await;
+    let mut chest = match world.get_optional_component::<Chest>(container_id).await? {
+        Some(chest) => chest,
+        None => {
+            return Ok(ServerResponseType::Error {
+                message: "not a chest",
+            })
+        }
+    };
+    if !in_reach(world, actor, container_id).await? {
+        return Ok(ServerResponseType::Error {
+            message: "container out of reach",
+        });
+    }
+    if !chest.opened {
+        chest.opened = true;
+        world.set_component(container_id, chest.clone()).await?;
+        loot::fill_chest(world, container_id, &chest.table).await?;
+    }
+    Ok(ServerResponseType::Ok {})
+}
+
+pub async fn drop(
+    world: &ServerWorldRef,
+    actor: EntityId,
+    item_id: EntityId,
+) -> Result<ServerResponseType, ServerWorldError> {
+    if world.has_component::<Dead>(actor).await? {
+        return Ok(ServerResponseType::PermissionDenied {});
+    }
+    let mut inventory = match world.get_optional_component::<Inventory>(actor).await? {
+        Some(inventory) if inventory.contains(item_id) => inventory,
+        _ => {
+            return Ok(ServerResponseType::Error {
+                message: "item not in inventory",
+            })
+        }
+    };
+    if world.is_item_in_trade(item_id).await {
+        return Ok(ServerResponseType::Error {
+            message: "item is being traded",
+        });
+    }
+    let position = match world.get_optional_component::<Position>(actor).await? {
+        Some(position) => position,
+        None => {
+            return Ok(ServerResponseType::Error {
+                message: "not in the world",
+            })
+        }
+    };
+    inventory.remove(item_id);
+    world.set_component(actor, inventory).await?;
+    world.set_component(item_id, position).await?;
+    progression::unequip_if_equipped(world, actor, item_id).await?;
+    Ok(ServerResponseType::Ok {})
+}
+
+async fn apply_item_effect(
+    world: &ServerWorldRef,
+    actor: EntityId,
+    target: EntityId,
+    effect: &ItemEffect,
+) -> Result<(), ServerWorldError> {
+    match effect {
+        ItemEffect::Heal(amount) => {
+            if let Some(mut health) = world.get_optional_component::<Health>(target).await? {
+                if !health.is_dead() {
+                    health.current = (health.current + amount).min(health.max);
+                    world.set_component(target, health).await?;
+                }
+            }
+            Ok(())
+        }
+        ItemEffect::Damage(amount) => {
+            combat::apply_damage(world, Some(actor), target, *amount).await
+        }
+        ItemEffect::ApplyEffect {
+            effect,
+            magnitude,
+            duration_ticks,
+        } => {
+            status_effects::apply_effect(
+                world,
+                target,
+                *effect,
+                *magnitude,
+                *duration_ticks,
+                Some(actor),
+            )
+            .await
+        }
+    }
+}
+
+pub async fn use_on(
+    world: &ServerWorldRef,
+    actor: EntityId,
+    item_id: EntityId,
+    target: EntityId,
+) -> Result<ServerResponseType, ServerWorldError> {
+    if world.has_component::<Dead>(actor).await? {
+        return Ok(ServerResponseType::PermissionDenied {});
+    }
+    let mut inventory = match world.get_optional_component::<Inventory>(actor).await? {
+        Some(inventory) if inventory.contains(item_id) => inventory,
+        _ => {
+            return Ok(ServerResponseType::Error {
+                message: "item not in inventory",
+            })
+        }
+    };
+    let mut item = match world.get_optional_component::<Item>(item_id).await? {
+        Some(item) => item,
+        None => {
+            return Ok(ServerResponseType::Error {
+                message: "not an item",
+            })
+        }
+    };
+    if world.is_item_in_trade(item_id).await {
+        return Ok(ServerResponseType::Error {
+            message: "item is being traded",
+        });
+    }
+    let item_type = match world.get_item_type(item.item_type_id) {
+        Some(item_type) if !item_type.get_on_use().is_empty() => item_type,
+        _ => {
+            return Ok(ServerResponseType::Error {
+                message: "item cannot be used",
+            })
+        }
+    };
+    if target != actor && !in_reach(world, actor, target).await? {
+        return Ok(ServerResponseType::Error {
+            message: "target out of reach",
+        });
+    }
+    let harmful = item_type.get_on_use().iter().any(|e| e.is_harmful());
+    if harmful && target != actor && !pvp::can_harm(world, actor, target).await? {
+        return Ok(ServerResponseType::PermissionDenied {});
+    }
+    for effect in item_type.get_on_use() {
+        apply_item_effect(world, actor, target, effect).await?;
+    }
+    if item_type.is_consumable() {
+        item.count -= 1;
+        if item.count == 0 {
+            inventory.remove(item_id);
+            world.set_component(actor, inventory).await?;
+            world.despawn_entity(item_id).await?;
+        } else {
+            world.set_component(item_id, item).await?;
+        }
+    }
+    Ok(ServerResponseType::Ok {})
+}
+
+#[tokio::test]
+async fn test_pickup_once_and_keep_offered_stacks() -> Result<(), ServerWorldError> {
+    use mmolib::{item::item_type_id, trade::Trade};
+    let world = crate::server_world::test_world("inventory").await;
+    let (a, b) = (EntityId::new(), EntityId::new());
+    for actor in [a, b] {
+        world.set_component(actor, Position::new(5, 5)).await?;
+        world.set_component(actor, Inventory::new(10)).await?;
+    }
+    let rocks = spawn_item_at(&world, (5, 6), item_type_id("rock"), 3).await?;
+    let (first, second) = tokio::join!(pickup(&world, a, rocks), pickup(&world, b, rocks));
+    let picked_up = [first?, second?]
+        .iter()
+        .filter(|r| matches!(r, ServerResponseType::Ok {}))
+        .count();
+    assert_eq!(picked_up, 1);
+    let held = count_items(&world, a, item_type_id("rock")).await?
+        + count_items(&world, b, item_type_id("rock")).await?;
+    assert_eq!(held, 3);
+    //rocks on offer can't be used up
+    let holder = if count_items(&world, a, item_type_id("rock")).await? == 3 {
+        a
+    } else {
+        b
+    };
+    let mut trade = Trade::new(a, b, world.get_tick());
+    trade.side_mut(holder).unwrap().items = vec![(rocks, 3)];
+    world.put_trade(trade).await;
+    assert_eq!(count_items(&world, holder, item_type_id("rock")).await?, 0);
+    assert!(!take_items(&world, holder, item_type_id("rock"), 1).await?);
+    world.take_trade(a).await;
+    assert!(take_items(&world, holder, item_type_id("rock"), 1).await?);
+    Ok(())
+}
@@ -0,0 +1,180 @@
+use mmolib::{
+    chunk,
+    entity_id::EntityId,
+    item::Inventory,
+    loot::{Chest, LootContext, LootDrop},
+    position::Position,
+};
+use rand::SeedableRng;
+
+use crate::{
+    inventory, party,
+    server_world::{ServerWorldError, ServerWorldRef},
+};
+
+//stacks a chest holds, anything rolled past that is left beside it
+pub const CHEST_CAPACITY: usize = 10;
+
+/**
+ * Put the chests from the raws into a new world, closed until someone opens them
+ */
+pub async fn place_chests(world: &ServerWorldRef) -> Result<(), ServerWorldError> {
+    for placement in world.get_chest_placements() {
+        let chest_id = EntityId::new();
+        world
+            .set_component(chest_id, placement.get_position().clone())
+            .await?;
+        world
+            .set_component(chest_id, Inventory::new(CHEST_CAPACITY))
+            .await?;
+        world
+            .set_component(
+                chest_id,
+                Chest {
+                    table: placement.get_table().to_owned(),
+                    opened: false,
+                },
+            )
+            .await?;
+    }
+    Ok(())
+}
+
+/**
+ * Roll a chest's loot table into it, dropping whatever doesn't fit on the ground beside it
+ */
+pub async fn fill_chest(
+    world: &ServerWorldRef,
+    chest_id: EntityId,
+    table: &str,
+) -> Result<(), ServerWorldError> {
+    let drops = {
+        let mut rng = rand::rngs::StdRng::seed_from_u64(chest_id.id() ^ world.get_tick());
+        mmolib::loot::roll(
+            world.get_loot_tables(),
+            table,
+            &LootContext::default(),
+            &mut rng,
+        )
+    };
+    let location = world
+        .get_optional_component::<Position>(chest_id)
+        .await?
+        .and_then(|p| p.block_position());
+    for (item_type_id, count) in drops {
+        let leftover = inventory::give_item(world, chest_id, item_type_id, count).await?;
+        if let (true, Some(location)) = (leftover > 0, location) {
+            inventory::spawn_item_at(world, location, item_type_id, leftover).await?;
+        }
+    }
+    Ok(())
+}
+
+/**
+ * Roll a loot table and put the results on the ground. The seed makes the roll reproducible
+ */
+pub async fn drop_loot(
+    world: &ServerWorldRef,
+    table: &str,
+    position: chunk::Position,
+    context: &LootContext,
+    seed: u64,
+) -> Result<Vec<EntityId>, ServerWorldError> {
+    let drops = {
+        let mut rng = rand::rngs::StdRng::seed_from_u64(seed);
+        mmolib::loot::roll(world.get_loot_tables(), table, context, &mut rng)
+    };
+    let mut res = Vec::new();
+    for (item_type_id, count) in drops {
+        let max_stack = world
+            .get_item_type(item_type_id)
+            .map_or(1, |item_type| item_type.get_max_stack());
+        for stack in mmolib::loot::split_into_stacks(count, max_stack) {
+            res.push(inventory::spawn_item_at(world, position, item_type_id, stack).await?);
+        }
+    }
+    Ok(res)
+}
+
+/**
+ * Drop the loot of an entity that just died where it died
+ */
+pub async fn drop_death_loot(
+    world: &ServerWorldRef,
+    entity_id: EntityId,
+    killer: Option<EntityId>,
+    position: chunk::Position,
+) -> Result<(), ServerWorldError> {
+    let loot = match world.get_optional_component::<LootDrop>(entity_id).await? {
+        Some(loot) => loot,
+        None => return Ok(()),
+    };
+    let killed_by_player = match killer {
+        Some(killer) => {
+            world
+                .has_component::<mmolib::player::Player>(killer)
+                .await?
+        }
+        None => false,
+    };
+    let items = drop_loot(
+        world,
+        &loot.table,
+        position,
+        &LootContext { killed_by_player },
+        entity_id.id() ^ world.get_tick(),
+    )
+    .await?;
+    match killer {
+        Some(killer) => party::reserve_loot(world, killer, &items).await,
+        None => Ok(()),
+    }
+}
+
+#[tokio::test]
+async fn test_open_chest() -> Result<(), ServerWorldError> {
+    use mmolib::server_response_type::ServerResponseType;
+    let world = crate::server_world::test_world("loot").await;
+    let (actor, chest_id) = (EntityId::new(), EntityId::new());
+    world.set_component(actor, Position::new(3, 3)).await?;
+    world.set_component(actor, Inventory::new(10)).await?;
+    world.set_component(chest_id, Position::new(3, 4)).await?;
+    world
+        .set_component(chest_id, Inventory::new(CHEST_CAPACITY))
+        .await?;
+    world
+        .set_component(
+            chest_id,
+            Chest {
+                table: "potions".to_owned(),
+                opened: false,
+            },
+        )
+        .await?;
+    let items_in = |inventory: Option<Inventory>| inventory.unwrap().items;
+    assert!(matches!(
+        inventory::open(&world, actor, chest_id).await?,
+        ServerResponseType::Ok {}
+    ));
+    let items = items_in(world.get_optional_component::<Inventory>(chest_id).await?);
+    assert_eq!(items.len(), 1);
+    //opening it again doesn't fill it again
+    assert!(matches!(
+        inventory::open(&world, actor, chest_id).await?,
+        ServerResponseType::Ok {}
+    ));
+    assert_eq!(
+        items_in(world.get_optional_component::<Inventory>(chest_id).await?),
+        items
+    );
+    assert!(matches!(
+        inventory::take_from(&world, actor, chest_id, items[0]).await?,
+        ServerResponseType::Ok {}
+    ));
+    assert!(items_in(world.get_optional_component::<Inventory>(chest_id).await?).is_empty());
+    assert_eq!(
+        items_in(world.get_optional_component::<Inventory>(actor).await?).len(),
+        1
+    );
+    Ok(())
+}
@@ -12,9 +12,40 @@ use server_world::ServerWorld;
 use tokio::time::Instant;
 
 mod args;
+mod behavior;
+mod block_effects;
+mod building;
 mod change_tracker;
+mod character;
+mod chat;
+mod combat;
+mod crafting;
+mod death;
+mod fire;
+mod fluid;
+mod game_loop;
+mod guild;
+mod inventory;
+mod loot;
+mod moderation;
+mod movement;
+mod party;
+mod pathfinding;
+mod player_action;
+mod progression;
+mod projectile;
+mod pvp;
 mod query;
+mod quests;
+mod replication;
+mod request;
 mod server_world;
+mod shop;
+mod spawning;
+mod status_effects;
+mod trading;
+mod visibility;
+mod weather;
 #[tokio::main]
 async fn main() -> Result<(), server_world::ServerWorldError> {
     let args = args::Args::parse();
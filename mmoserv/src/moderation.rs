@@ -0,0 +1,233 @@
+use mmolib::{
+    entity_id::EntityId,
+    moderation::{self, Muted, Report},
+    player::Player,
+    server_request_type::ServerRequestType,
+    server_response_type::ServerResponseType,
+};
+
+use crate::server_world::{ServerWorldError, ServerWorldRef};
+
+//lines kept for each player to give reports context
+pub const REPORT_CONTEXT_LINES: usize = 20;
+
+pub async fn is_moderator(world: &ServerWorldRef, username: &str) -> bool {
+    world
+        .get_rules()
+        .await
+        .moderators
+        .iter()
+        .any(|moderator| moderator.to_lowercase() == username.to_lowercase())
+}
+
+/**
+ * Seconds since the unix epoch, what mutes are timed by
+ */
+pub fn unix_time() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs())
+}
+
+/**
+ * Why a player can't talk right now, clearing their mute if it has run out
+ */
+pub async fn check_muted(
+    world: &ServerWorldRef,
+    entity_id: EntityId,
+) -> Result<Option<ServerResponseType>, ServerWorldError> {
+    match world.get_optional_component::<Muted>(entity_id).await? {
+        Some(muted) if muted.is_active(unix_time()) => {
+            Ok(Some(ServerResponseType::Error { message: "muted" }))
+        }
+        Some(_) => {
+            world.remove_component::<Muted>(entity_id).await?;
+            Ok(None)
+        }
+        None => Ok(None),
+    }
+}
+
+/**
+ * A message with filtered words starred out, or the error to send back if it was blocked
+ */
+pub fn filter_message(world: &ServerWorldRef, message: &str) -> Result<String, ServerResponseType> {
+    moderation::apply_filters(world.get_word_filters(), message).ok_or(ServerResponseType::Error {
+        message: "message not allowed",
+    })
+}
+
+/**
+ * Flag a line the actor was recently sent
+ */
+pub async fn report(
+    world: &ServerWorldRef,
+    actor: EntityId,
+    username: &str,
+    message: &str,
+) -> Result<ServerResponseType, ServerWorldError> {
+    let reporter = match world.get_optional_component::<Player>(actor).await? {
+        Some(player) => player.username,
+        None => return Ok(ServerResponseType::PermissionDenied {}),
+    };
+    let context = world.get_received_chat(actor).await;
+    let line = match context
+        .iter()
+        .rev()
+        .find(|line| line.username == username && line.message == message)
+    {
+        Some(line) => line.clone(),
+        None => {
+            return Ok(ServerResponseType::Error {
+                message: "no such message",
+            })
+        }
+    };
+    if !world.allow_report(actor).await {
+        return Ok(ServerResponseType::Error {
+            message: "reporting too often",
+        });
+    }
+    world
+        .push_report(&Report {
+            reporter,
+            line,
+            context,
+            tick: world.get_tick(),
+        })
+        .await?;
+    Ok(ServerResponseType::Ok {})
+}
+
+/**
+ * Requests only moderators may make. They act on accounts, so no character is needed
+ */
+pub async fn handle_moderator_request(
+    world: &ServerWorldRef,
+    moderator: &str,
+    request: &ServerRequestType,
+) -> Result<ServerResponseType, ServerWorldError> {
+    if !is_moderator(world, moderator).await {
+        return Ok(ServerResponseType::PermissionDenied {});
+    }
+    match request {
+        ServerRequestType::Mute {
+            username,
+            duration_secs,
+            reason,
+            ..
+        } => {
+            let entity_id = match world.get_account_entity(username).await? {
+                Some(entity_id) => entity_id,
+                None => {
+                    return Ok(ServerResponseType::Error {
+                        message: "no such player",
+                    })
+                }
+            };
+            world
+                .set_component(
+                    entity_id,
+                    Muted {
+                        until: duration_secs.map(|d| unix_time().saturating_add(d)),
+                        reason: reason.clone(),
+                        by: moderator.to_owned(),
+                    },
+                )
+                .await?;
+            Ok(ServerResponseType::Ok {})
+        }
+        ServerRequestType::Unmute { username, .. } => {
+            if let Some(entity_id) = world.get_account_entity(username).await? {
+                world.remove_component::<Muted>(entity_id).await?;
+            }
+            Ok(ServerResponseType::Ok {})
+        }
+        ServerRequestType::GetReports { .. } => Ok(ServerResponseType::Reports {
+            reports: world.get_reports().await?,
+        }),
+        _ => Ok(ServerResponseType::Error {
+            message: "request not handled by world",
+        }),
+    }
+}
+
+#[tokio::test]
+async fn test_mute_and_report() -> Result<(), ServerWorldError> {
+    use crate::chat;
+    use mmolib::chat::{ChatChannel, ChatLine};
+    let world = crate::server_world::test_world("moderation").await;
+    let mut rules = world.get_rules().await;
+    rules.moderators = vec!["Mod".to_owned()];
+    world.set_rules(rules).await?;
+    let (player, reporter) = (EntityId::new(), EntityId::new());
+    let names = [format!("p{}", player.id()), format!("r{}", reporter.id())];
+    for (entity_id, username) in [(player, &names[0]), (reporter, &names[1])] {
+        world
+            .set_component(
+                entity_id,
+                Player {
+                    username: username.clone(),
+                },
+            )
+            .await?;
+        world.link_account(username, entity_id).await?;
+    }
+    world.set_online(&[player, reporter]).await;
+    let mute = |duration_secs| ServerRequestType::Mute {
+        world_name: "moderation".to_owned(),
+        username: names[0].clone(),
+        duration_secs,
+        reason: "spam".to_owned(),
+    };
+    assert!(matches!(
+        handle_moderator_request(&world, "someone", &mute(None)).await?,
+        ServerResponseType::PermissionDenied {}
+    ));
+    //a mute that has already run out is cleared on the next message
+    assert!(matches!(
+        handle_moderator_request(&world, "mod", &mute(Some(0))).await?,
+        ServerResponseType::Ok {}
+    ));
+    world.write_all_changes().await?;
+    assert!(check_muted(&world, player).await?.is_none());
+    assert!(matches!(
+        handle_moderator_request(&world, "MOD", &mute(None)).await?,
+        ServerResponseType::Ok {}
+    ));
+    world.write_all_changes().await?;
+    assert!(check_muted(&world, player).await?.is_some());
+    //who doesn't use up any of the chat allowance
+    for _ in 0..=moderation::CHAT_BURST {
+        assert!(matches!(
+            chat::send(&world, reporter, "/who", ChatChannel::World, None).await?,
+            ServerResponseType::PlayerList { .. }
+        ));
+    }
+    assert!(matches!(
+        chat::send(&world, reporter, "hello", ChatChannel::World, None).await?,
+        ServerResponseType::Ok {}
+    ));
+    let line = ChatLine {
+        channel: ChatChannel::World,
+        username: names[0].clone(),
+        message: "buy gold".to_owned(),
+        emote: false,
+        to: None,
+        tick: world.get_tick(),
+    };
+    world
+        .record_received_chat(reporter, &line, REPORT_CONTEXT_LINES)
+        .await;
+    assert!(matches!(
+        report(&world, reporter, &names[0], "buy gold").await?,
+        ServerResponseType::Ok {}
+    ));
+    assert!(matches!(
+        report(&world, reporter, &names[0], "buy gold").await?,
+        ServerResponseType::Error {
+            message: "reporting too often"
+        }
+    ));
+    Ok(())
+}
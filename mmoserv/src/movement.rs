@@ -0,0 +1,107 @@
+use mmolib::{
+    combat::Dead, entity_id::EntityId, movement::Collider, movement::Movement, position::Position,
+    server_request_type::Direction, server_response_type::ServerResponseType,
+};
+
+use crate::{
+    pathfinding::TraversalCosts,
+    weather,
+    server_world::{ServerWorldError, ServerWorldRef},
+};
+
+/**
+ * Whether an entity could stand on a block, ignoring other entities
+ */
+pub async fn is_walkable(
+    world: &ServerWorldRef,
+    position: mmolib::chunk::Position,
+) -> Result<bool, ServerWorldError> {
+    let block = match world.get_block(position).await {
+        Ok(block) => block,
+        Err(ServerWorldError::ChunkNotFound(_)) => return Ok(false),
+        Err(e) => return Err(e),
+    };
+    //walking follows the same rules the pathfinder plans with
+    Ok(world
+        .get_block_type(block)
+        .and_then(|b| TraversalCosts::default().cost(&b.get_layer()))
+        .is_some())
+}
+
+async fn is_occupied(
+    world: &ServerWorldRef,
+    mover: EntityId,
+    position: mmolib::chunk::Position,
+) -> Result<bool, ServerWorldError> {
+    for entity_id in world.get_entities_at(position).await? {
+        if entity_id != mover && world.has_component::<Collider>(entity_id).await? {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+/**
+ * Reject a move, resending the authoritative position so the client can correct itself
+ */
+async fn reject(
+    world: &ServerWorldRef,
+    actor: EntityId,
+    message: &'static str,
+) -> Result<ServerResponseType, ServerWorldError> {
+    world.mark_changed::<Position>(actor).await?;
+    Ok(ServerResponseType::Error { message })
+}
+
+pub async fn move_entity(
+    world: &ServerWorldRef,
+    actor: EntityId,
+    direction: Direction,
+) -> Result<ServerResponseType, ServerWorldError> {
+    if world.has_component::<Dead>(actor).await? {
+        return Ok(ServerResponseType::PermissionDenied {});
+    }
+    let position = (*world.get_component_ref::<Position>(actor).await?).clone();
+    let mut movement = world
+        .get_optional_component::<Movement>(actor)
+        .await?
+        .unwrap_or_default();
+    let tick = world.get_tick();
+    if tick < movement.next_move_tick {
+        return reject(world, actor, "moving too fast").await;
+    }
+    let from = match position.block_position() {
+        Some(from) => from,
+        None => return reject(world, actor, "not in the world").await,
+    };
+    let to = match direction.step(from) {
+        Some(to) => to,
+        None => return reject(world, actor, "cannot move out of the world").await,
+    };
+    if !is_walkable(world, to).await? {
+        return reject(world, actor, "blocked").await;
+    }
+    if direction.is_diagonal() {
+        //no squeezing between two blocks diagonally
+        let (x, y) = direction.offset();
+        for side in [(x, 0), (0, y)] {
+            let side = Direction::from_offset(side).and_then(|d| d.step(from));
+            match side {
+                Some(side) if is_walkable(world, side).await? => {}
+                _ => return reject(world, actor, "blocked").await,
+            }
+        }
+    }
+    if is_occupied(world, actor, to).await? {
+        return reject(world, actor, "occupied").await;
+    }
+    let ticks_per_step =
+        (movement.ticks_per_step * weather::movement_percent_at(world, to).await? / 100).max(1);
+    movement.next_move_tick =
+        tick + mmolib::movement::step_cooldown(ticks_per_step, direction.is_diagonal());
+    world.set_component(actor, movement).await?;
+    world
+        .set_component(actor, Position::from_block_position(to))
+        .await?;
+    Ok(ServerResponseType::Ok {})
+}
@@ -0,0 +1,304 @@
+use mmolib::{
+    chunk,
+    combat::Dead,
+    entity_id::EntityId,
+    event::GameEvent,
+    group::{LootRule, Party},
+    loot::LootOwner,
+    player::Player,
+    position::Position,
+    server_request_type::PartyActionType,
+    server_response_type::ServerResponseType,
+};
+
+use crate::{
+    progression,
+    server_world::{ServerWorldError, ServerWorldRef},
+};
+
+//how close party members have to be to the kill to get a share of the xp
+pub const XP_SHARE_RANGE: u32 = 30;
+//how long round robin loot belongs to one member before anyone can take it
+pub const LOOT_RESERVE_TICKS: u64 = 1200;
+
+async fn announce(world: &ServerWorldRef, party: &Party) {
+    world
+        .emit_event(
+            None,
+            GameEvent::PartyUpdated {
+                party: party.clone(),
+            },
+        )
+        .await;
+}
+
+async fn left(world: &ServerWorldRef, entity: EntityId) {
+    world
+        .emit_event(None, GameEvent::PartyLeft { entity })
+        .await;
+}
+
+/**
+ * Take an entity out of its party, breaking the party up if only one member would be left
+ */
+async fn remove_member(world: &ServerWorldRef, mut party: Party, entity: EntityId) {
+    party.remove(entity);
+    left(world, entity).await;
+    if party.members.len() < 2 {
+        for member in party.members.drain(..) {
+            left(world, member).await;
+        }
+    } else {
+        announce(world, &party).await;
+    }
+    world.put_party(party).await;
+}
+
+/**
+ * The party an entity leads, or why it can't act as leader
+ */
+async fn led_party(world: &ServerWorldRef, actor: EntityId) -> Result<Party, ServerResponseType> {
+    match world.get_party(actor).await {
+        Some(party) if party.leader == actor => Ok(party),
+        Some(_) => Err(ServerResponseType::PermissionDenied {}),
+        None => Err(ServerResponseType::Error {
+            message: "not in a party",
+        }),
+    }
+}
+
+pub async fn handle_party_action(
+    world: &ServerWorldRef,
+    actor: EntityId,
+    action: &PartyActionType,
+) -> Result<ServerResponseType, ServerWorldError> {
+    //every action checks a party then stores it back, so two at once could undo each other
+    let _parties = world.lock_parties().await;
+    match action {
+        PartyActionType::Invite(target) => invite(world, actor, *target).await,
+        PartyActionType::Accept => accept(world, actor).await,
+        PartyActionType::Decline => {
+            world.take_party_invite(actor).await;
+            Ok(ServerResponseType::Ok {})
+        }
+        PartyActionType::Leave => match world.get_party(actor).await {
+            Some(party) => {
+                remove_member(world, party, actor).await;
+                Ok(ServerResponseType::Ok {})
+            }
+            None => Ok(ServerResponseType::Error {
+                message: "not in a party",
+            }),
+        },
+        PartyActionType::Kick(target) => {
+            let party = match led_party(world, actor).await {
+                Ok(party) => party,
+                Err(response) => return Ok(response),
+            };
+            if *target == actor || !party.is_member(*target) {
+                return Ok(ServerResponseType::Error {
+                    message: "not a member of your party",
+                });
+            }
+            remove_member(world, party, *target).await;
+            Ok(ServerResponseType::Ok {})
+        }
+        PartyActionType::Promote(target) => {
+            let mut party = match led_party(world, actor).await {
+                Ok(party) => party,
+                Err(response) => return Ok(response),
+            };
+            if !party.is_member(*target) {
+                return Ok(ServerResponseType::Error {
+                    message: "not a member of your party",
+                });
+            }
+            party.leader = *target;
+            announce(world, &party).await;
+            world.put_party(party).await;
+            Ok(ServerResponseType::Ok {})
+        }
+        PartyActionType::Disband => {
+            let mut party = match led_party(world, actor).await {
+                Ok(party) => party,
+                Err(response) => return Ok(response),
+            };
+            for member in party.members.drain(..) {
+                left(world, member).await;
+            }
+            world.put_party(party).await;
+            Ok(ServerResponseType::Ok {})
+        }
+        PartyActionType::SetRules(rules) => {
+            let mut party = match led_party(world, actor).await {
+                Ok(party) => party,
+                Err(response) => return Ok(response),
+            };
+            party.rules = rules.clone();
+            announce(world, &party).await;
+            world.put_party(party).await;
+            Ok(ServerResponseType::Ok {})
+        }
+    }
+}
+
+async fn invite(
+    world: &ServerWorldRef,
+    actor: EntityId,
+    target: EntityId,
+) -> Result<ServerResponseType, ServerWorldError> {
+    if actor == target || !world.has_component::<Player>(target).await? {
+        return Ok(ServerResponseType::Error {
+            message: "can only invite other players",
+        });
+    }
+    if world.get_party(target).await.is_some() {
+        return Ok(ServerResponseType::Error {
+            message: "already in a party",
+        });
+    }
+    if let Some(party) = world.get_party(actor).await {
+        if party.leader != actor {
+            return Ok(ServerResponseType::PermissionDenied {});
+        }
+        if party.is_full() {
+            return Ok(ServerResponseType::Error {
+                message: "party is full",
+            });
+        }
+    }
+    world.invite_to_party(actor, target).await;
+    world
+        .emit_event(
+            None,
+            GameEvent::PartyInvite {
+                from: actor,
+                to: target,
+            },
+        )
+        .await;
+    Ok(ServerResponseType::Ok {})
+}
+
+async fn accept(
+    world: &ServerWorldRef,
+    actor: EntityId,
+) -> Result<ServerResponseType, ServerWorldError> {
+    let inviter = match world.take_party_invite(actor).await {
+        Some(inviter) => inviter,
+        None => {
+            return Ok(ServerResponseType::Error {
+                message: "no party invite",
+            })
+        }
+    };
+    if world.get_party(actor).await.is_some() {
+        return Ok(ServerResponseType::Error {
+            message: "already in a party",
+        });
+    }
+    let mut party = match world.get_party(inviter).await {
+        //the inviter may have handed over leadership since
+        Some(party) if party.leader != inviter => {
+            return Ok(ServerResponseType::PermissionDenied {})
+        }
+        Some(party) => party,
+        None => world.create_party(inviter).await,
+    };
+    if party.is_full() {
+        return Ok(ServerResponseType::Error {
+            message: "party is full",
+        });
+    }
+    party.members.push(actor);
+    announce(world, &party).await;
+    world.put_party(party).await;
+    Ok(ServerResponseType::Ok {})
+}
+
+async fn block_position_of(
+    world: &ServerWorldRef,
+    entity_id: EntityId,
+) -> Result<Option<chunk::Position>, ServerWorldError> {
+    Ok(world
+        .get_optional_component::<Position>(entity_id)
+        .await?
+        .and_then(|p| p.block_position()))
+}
+
+/**
+ * Give out the xp for a kill, split between living party members near the killer if the party shares xp
+ */
+pub async fn grant_kill_xp(
+    world: &ServerWorldRef,
+    killer: EntityId,
+    xp: u64,
+) -> Result<(), ServerWorldError> {
+    //members can't leave or join halfway through the split
+    let _parties = world.lock_parties().await;
+    let party = match world.get_party(killer).await {
+        Some(party) if party.rules.share_xp => party,
+        _ => return progression::grant_xp(world, killer, xp).await,
+    };
+    let origin = block_position_of(world, killer).await?;
+    let mut sharers = vec![killer];
+    for member in party.members.into_iter().filter(|m| *m != killer) {
+        if world.has_component::<Dead>(member).await? {
+            continue;
+        }
+        let near = match (origin, block_position_of(world, member).await?) {
+            (Some(origin), Some(position)) => {
+                chunk::chebyshev_distance(origin, position) <= XP_SHARE_RANGE
+            }
+            _ => false,
+        };
+        if near {
+            sharers.push(member);
+        }
+    }
+    let share = xp / sharers.len() as u64;
+    //whatever doesn't divide evenly goes to the killer
+    let remainder = xp % sharers.len() as u64;
+    for sharer in sharers {
+        let amount = if sharer == killer {
+            share + remainder
+        } else {
+            share
+        };
+        progression::grant_xp(world, sharer, amount).await?;
+    }
+    Ok(())
+}
+
+/**
+ * Reserve freshly dropped loot for the next member in line when the killer's party uses round robin
+ */
+pub async fn reserve_loot(
+    world: &ServerWorldRef,
+    killer: EntityId,
+    items: &[EntityId],
+) -> Result<(), ServerWorldError> {
+    //two kills at once would otherwise both go to the same member
+    let _parties = world.lock_parties().await;
+    let mut party = match world.get_party(killer).await {
+        Some(party) if party.rules.loot == LootRule::RoundRobin => party,
+        _ => return Ok(()),
+    };
+    let owner = match party.take_next_looter() {
+        Some(owner) => owner,
+        None => return Ok(()),
+    };
+    world.put_party(party).await;
+    for item in items {
+        world
+            .set_component(
+                *item,
+                LootOwner {
+                    entity: owner,
+                    until_tick: world.get_tick() + LOOT_RESERVE_TICKS,
+                },
+            )
+            .await?;
+    }
+    Ok(())
+}
@@ -0,0 +1,299 @@
+use std::{cmp::Reverse, collections::BinaryHeap};
+
+use hashbrown::HashMap;
+use mmolib::{
+    block_type::BlockLayer,
+    chunk::{self, Position, CHUNK_SIZE},
+    server_request_type::Direction,
+};
+use serde::Deserialize;
+use tokio::sync::RwLock;
+
+use crate::server_world::{ServerWorld, ServerWorldError};
+
+//how many nodes a single search may expand before giving up
+pub const DEFAULT_SEARCH_BUDGET: usize = 4096;
+//how far outside the start/goal bounding box a path may wander
+const SEARCH_MARGIN: u32 = CHUNK_SIZE as u32;
+//paths kept before the least recently used are forgotten
+pub const MAX_CACHED_PATHS: usize = 1024;
+//step costs are scaled so diagonals can be approximated as 14/10
+const STRAIGHT_STEP: u32 = 10;
+const DIAGONAL_STEP: u32 = 14;
+
+/**
+ * Cost of entering a block on each layer, None means impassable
+ */
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[serde(default)]
+pub struct TraversalCosts {
+    pub ground: Option<u32>,
+    pub solid: Option<u32>,
+    pub water: Option<u32>,
+    pub pit: Option<u32>,
+    pub effect: Option<u32>,
+}
+
+impl Default for TraversalCosts {
+    fn default() -> Self {
+        TraversalCosts {
+            ground: Some(1),
+            solid: None,
+            water: Some(3),
+            pit: None,
+            effect: Some(2),
+        }
+    }
+}
+
+impl TraversalCosts {
+    pub fn cost(&self, layer: &BlockLayer) -> Option<u32> {
+        match layer {
+            BlockLayer::Ground => self.ground,
+            BlockLayer::Solid => self.solid,
+            BlockLayer::Water => self.water,
+            BlockLayer::Pit => self.pit,
+            BlockLayer::Effect(_) => self.effect,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+struct PathKey {
+    start: Position,
+    goal: Position,
+    costs: TraversalCosts,
+    //a search that ran out of budget may succeed with a bigger one
+    budget: usize,
+}
+
+struct CachedPath {
+    //inclusive corners of the area the search was allowed to look at
+    area: (Position, Position),
+    path: Option<Vec<Position>>,
+    last_used: u64,
+}
+
+/**
+ * Previous searches, forgetting the least recently used once full
+ */
+struct PathCache {
+    paths: HashMap<PathKey, CachedPath>,
+    uses: u64,
+}
+
+impl PathCache {
+    fn new() -> Self {
+        PathCache {
+            paths: HashMap::new(),
+            uses: 0,
+        }
+    }
+    fn get(&mut self, key: &PathKey) -> Option<Option<Vec<Position>>> {
+        self.uses += 1;
+        let cached = self.paths.get_mut(key)?;
+        cached.last_used = self.uses;
+        Some(cached.path.clone())
+    }
+    fn insert(&mut self, key: PathKey, area: (Position, Position), path: Option<Vec<Position>>) {
+        self.uses += 1;
+        if self.paths.len() >= MAX_CACHED_PATHS && !self.paths.contains_key(&key) {
+            let oldest = self
+                .paths
+                .iter()
+                .min_by_key(|(_, cached)| cached.last_used)
+                .map(|(key, _)| *key);
+            if let Some(oldest) = oldest {
+                self.paths.remove(&oldest);
+            }
+        }
+        self.paths.insert(
+            key,
+            CachedPath {
+                area,
+                path,
+                last_used: self.uses,
+            },
+        );
+    }
+}
+
+fn octile_distance(a: Position, b: Position) -> u32 {
+    let dx = a.0.abs_diff(b.0);
+    let dy = a.1.abs_diff(b.1);
+    STRAIGHT_STEP * dx.max(dy) + (DIAGONAL_STEP - STRAIGHT_STEP) * dx.min(dy)
+}
+
+/**
+ * A* over the block grid. cost_of returns the cost of entering a block or None if it can't be entered.
+ * Returns the blocks to walk through, excluding start and including goal.
+ */
+pub fn find_path_with(
+    start: Position,
+    goal: Position,
+    budget: usize,
+    cost_of: impl Fn(Position) -> Option<u32>,
+) -> Option<Vec<Position>> {
+    let mut open = BinaryHeap::new();
+    let mut came_from: HashMap<Position, Position> = HashMap::new();
+    let mut best: HashMap<Position, u32> = HashMap::new();
+    best.insert(start, 0);
+    open.push(Reverse((octile_distance(start, goal), 0, start)));
+    let mut expanded = 0;
+    while let Some(Reverse((_, g, current))) = open.pop() {
+        if current == goal {
+            let mut path = Vec::new();
+            let mut node = current;
+            while node != start {
+                path.push(node);
+                node = came_from[&node];
+            }
+            path.reverse();
+            return Some(path);
+        }
+        if best.get(&current).is_some_and(|b| *b < g) {
+            continue;
+        }
+        expanded += 1;
+        if expanded > budget {
+            return None;
+        }
+        for direction in Direction::ALL {
+            let next = match direction.step(current) {
+                Some(next) => next,
+                None => continue,
+            };
+            let enter_cost = match cost_of(next) {
+                Some(c) => c,
+                None => continue,
+            };
+            let step = if direction.is_diagonal() {
+                //don't cut corners around blocks we couldn't walk through
+                let (x, y) = direction.offset();
+                let side_a = Direction::from_offset((x, 0)).and_then(|d| d.step(current));
+                let side_b = Direction::from_offset((0, y)).and_then(|d| d.step(current));
+                if side_a.and_then(&cost_of).is_none() || side_b.and_then(&cost_of).is_none() {
+                    continue;
+                }
+                DIAGONAL_STEP
+            } else {
+                STRAIGHT_STEP
+            };
+            let next_g = g + step * enter_cost;
+            if best.get(&next).is_none_or(|b| next_g < *b) {
+                best.insert(next, next_g);
+                came_from.insert(next, current);
+                open.push(Reverse((next_g + octile_distance(next, goal), next_g, next)));
+            }
+        }
+    }
+    None
+}
+
+/**
+ * Turn a path into the moves needed to walk it
+ */
+pub fn path_to_directions(start: Position, path: &[Position]) -> Vec<Direction> {
+    let mut current = start;
+    let mut res = Vec::new();
+    for next in path {
+        let offset = (
+            (i64::from(next.0) - i64::from(current.0)) as i32,
+            (i64::from(next.1) - i64::from(current.1)) as i32,
+        );
+        if let Some(direction) = Direction::from_offset(offset) {
+            res.push(direction);
+        }
+        current = *next;
+    }
+    res
+}
+
+/**
+ * Pathfinding service with a cache of previous searches
+ */
+pub struct Pathfinder {
+    cache: RwLock<PathCache>,
+}
+
+impl Pathfinder {
+    pub fn new() -> Self {
+        Pathfinder {
+            cache: RwLock::new(PathCache::new()),
+        }
+    }
+    pub async fn find_path(
+        &self,
+        world: &ServerWorld,
+        start: Position,
+        goal: Position,
+        costs: TraversalCosts,
+        budget: usize,
+    ) -> Result<Option<Vec<Position>>, ServerWorldError> {
+        let key = PathKey {
+            start,
+            goal,
+            costs,
+            budget,
+        };
+        if let Some(path) = self.cache.write().await.get(&key) {
+            return Ok(path);
+        }
+        let area = chunk::area_around(start, goal, SEARCH_MARGIN);
+        let snapshot = world.snapshot_area(area).await?;
+        let path = find_path_with(start, goal, budget, |p| {
+            costs.cost(&snapshot.get_block_type(p)?.get_layer())
+        });
+        self.cache.write().await.insert(key, area, path.clone());
+        Ok(path)
+    }
+    /**
+     * Forget every cached path whose search could have been affected by a changed block
+     */
+    pub async fn invalidate(&self, position: Position) {
+        self.cache
+            .write()
+            .await
+            .paths
+            .retain(|_, cached| !chunk::area_contains(cached.area, position));
+    }
+}
+
+#[test]
+fn test_find_path_around_wall() {
+    //a wall along x = 2 with a gap at y = 4
+    let cost_of = |p: Position| {
+        if p.0 > 5 || p.1 > 5 || (p.0 == 2 && p.1 != 4) {
+            None
+        } else {
+            Some(1)
+        }
+    };
+    let path = find_path_with((0, 0), (4, 0), DEFAULT_SEARCH_BUDGET, cost_of).unwrap();
+    assert_eq!(*path.last().unwrap(), (4, 0));
+    assert!(path.contains(&(2, 4)));
+    assert_eq!(path_to_directions((0, 0), &path).len(), path.len());
+    assert!(find_path_with((0, 0), (4, 0), 3, cost_of).is_none());
+}
+
+#[test]
+fn test_path_cache_forgets_least_recently_used() {
+    let key = |x: u32, budget: usize| PathKey {
+        start: (x, 0),
+        goal: (0, 0),
+        costs: TraversalCosts::default(),
+        budget,
+    };
+    let mut cache = PathCache::new();
+    for x in 0..MAX_CACHED_PATHS as u32 {
+        cache.insert(key(x, 1), ((0, 0), (0, 0)), None);
+    }
+    //keep the first one in use so the second is the oldest
+    assert!(cache.get(&key(0, 1)).is_some());
+    cache.insert(key(MAX_CACHED_PATHS as u32, 1), ((0, 0), (0, 0)), None);
+    assert_eq!(cache.paths.len(), MAX_CACHED_PATHS);
+    assert!(cache.get(&key(0, 1)).is_some());
+    assert!(cache.get(&key(1, 1)).is_none());
+    //a bigger budget is a different search
+    assert!(cache.get(&key(0, 2)).is_none());
+}
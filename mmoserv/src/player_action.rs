@@ -0,0 +1,61 @@
+use mmolib::{
+    entity_id::EntityId, server_request_type::PlayerActionType,
+    server_response_type::ServerResponseType,
+};
+
+use crate::{
+    building, combat, crafting, death, inventory, movement, progression, projectile, quests, shop,
+    server_world::{ServerWorldError, ServerWorldRef},
+};
+
+/**
+ * Apply a player action on behalf of the entity the player controls
+ */
+pub async fn handle_player_action(
+    world: &ServerWorldRef,
+    actor: EntityId,
+    action: &PlayerActionType,
+) -> Result<ServerResponseType, ServerWorldError> {
+    match action {
+        PlayerActionType::Move(direction) => movement::move_entity(world, actor, *direction).await,
+        PlayerActionType::Attack(target) => combat::attack(world, actor, *target).await,
+        PlayerActionType::UseOn { item, target } => {
+            inventory::use_on(world, actor, *item, *target).await
+        }
+        PlayerActionType::Pickup(item) => inventory::pickup(world, actor, *item).await,
+        PlayerActionType::Drop(item) => inventory::drop(world, actor, *item).await,
+        PlayerActionType::PlaceBlock {
+            position,
+            block_type_id,
+        } => building::place_block(world, actor, *position, *block_type_id).await,
+        PlayerActionType::BreakBlock { position } => {
+            building::break_block(world, actor, *position).await
+        }
+        PlayerActionType::Craft { recipe } => crafting::craft(world, actor, recipe).await,
+        PlayerActionType::Equip(item) => progression::equip(world, actor, *item).await,
+        PlayerActionType::Unequip(item) => progression::unequip(world, actor, *item).await,
+        PlayerActionType::BindRespawn { position } => {
+            death::bind_respawn(world, actor, *position).await
+        }
+        PlayerActionType::Respawn => death::respawn(world, actor).await,
+        PlayerActionType::TakeFrom { container, item } => {
+            inventory::take_from(world, actor, *container, *item).await
+        }
+        PlayerActionType::Open(container) => inventory::open(world, actor, *container).await,
+        PlayerActionType::Talk(npc) => quests::talk(world, actor, *npc).await,
+        PlayerActionType::AcceptQuest { quest } => quests::accept(world, actor, quest).await,
+        PlayerActionType::TurnInQuest { quest } => quests::turn_in(world, actor, quest).await,
+        PlayerActionType::Buy {
+            vendor,
+            item,
+            count,
+        } => shop::buy(world, actor, *vendor, item, *count).await,
+        PlayerActionType::Sell { vendor, item } => shop::sell(world, actor, *vendor, *item).await,
+        PlayerActionType::Repair { vendor, item } => {
+            shop::repair(world, actor, *vendor, *item).await
+        }
+        PlayerActionType::Shoot { weapon, target } => {
+            projectile::shoot(world, actor, *weapon, target).await
+        }
+    }
+}
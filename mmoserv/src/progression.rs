@@ -0,0 +1,347 @@
+use mmolib::{
+    combat::{AttackStats, Dead, Defense, Health},
+    effect::{ActiveEffects, Effect},
+    entity_id::EntityId,
+    event::GameEvent,
+    item::{Durability, Equipment, Inventory, Item},
+    movement::Movement,
+    position::Position,
+    server_response_type::ServerResponseType,
+    stats::{self, BaseStats, Experience, Stat, StatModifier, Stats},
+};
+
+use crate::server_world::{ServerWorldError, ServerWorldRef};
+
+/**
+ * The stats an entity has before modifiers. Entities created without any get
+ * them taken from their current components the first time they're needed
+ */
+async fn base_stats(
+    world: &ServerWorldRef,
+    entity_id: EntityId,
+) -> Result<Option<BaseStats>, ServerWorldError> {
+    if let Some(base) = world.get_optional_component::<BaseStats>(entity_id).await? {
+        return Ok(Some(base));
+    }
+    let health = match world.get_optional_component::<Health>(entity_id).await? {
+        Some(health) => health,
+        None => return Ok(None),
+    };
+    let base = BaseStats {
+        max_health: health.max,
+        damage: world
+            .get_optional_component::<AttackStats>(entity_id)
+            .await?
+            .unwrap_or_default()
+            .damage,
+        armor: world
+            .get_optional_component::<Defense>(entity_id)
+            .await?
+            .unwrap_or_default()
+            .armor,
+        ticks_per_step: world
+            .get_optional_component::<Movement>(entity_id)
+            .await?
+            .unwrap_or_default()
+            .ticks_per_step,
+    };
+    world.set_component(entity_id, base.clone()).await?;
+    Ok(Some(base))
+}
+
+/**
+ * Modifiers from the unbroken items an entity has equipped
+ */
+async fn equipment_modifiers(
+    world: &ServerWorldRef,
+    entity_id: EntityId,
+) -> Result<Vec<StatModifier>, ServerWorldError> {
+    let mut modifiers = Vec::new();
+    let (equipment, inventory) = match (
+        world.get_optional_component::<Equipment>(entity_id).await?,
+        world.get_optional_component::<Inventory>(entity_id).await?,
+    ) {
+        (Some(equipment), Some(inventory)) => (equipment, inventory),
+        _ => return Ok(modifiers),
+    };
+    for item_id in equipment.items().filter(|i| inventory.contains(*i)) {
+        let broken = world
+            .get_optional_component::<Durability>(item_id)
+            .await?
+            .is_some_and(|d| d.is_broken());
+        if broken {
+            continue;
+        }
+        if let Some(item) = world.get_optional_component::<Item>(item_id).await? {
+            if let Some(item_type) = world.get_item_type(item.item_type_id) {
+                modifiers.extend_from_slice(item_type.get_modifiers());
+            }
+        }
+    }
+    Ok(modifiers)
+}
+
+/**
+ * Every modifier currently affecting an entity, from its level, effects and, if asked for, equipment
+ */
+async fn collect_modifiers(
+    world: &ServerWorldRef,
+    entity_id: EntityId,
+    with_equipment: bool,
+) -> Result<Vec<StatModifier>, ServerWorldError> {
+    let mut modifiers = Vec::new();
+    if let Some(experience) = world.get_optional_component::<Experience>(entity_id).await? {
+        modifiers.extend(world.get_level_curve().modifiers_for_level(experience.level));
+    }
+    if with_equipment {
+        modifiers.extend(equipment_modifiers(world, entity_id).await?);
+    }
+    if let Some(effects) = world.get_optional_component::<ActiveEffects>(entity_id).await? {
+        let strength = effects.total_magnitude(Effect::Strength);
+        if strength != 0 {
+            modifiers.push(StatModifier::flat(Stat::Damage, strength));
+        }
+        let haste = effects.total_magnitude(Effect::Haste);
+        if haste != 0 {
+            modifiers.push(StatModifier::percent(Stat::Speed, haste));
+        }
+    }
+    Ok(modifiers)
+}
+
+/**
+ * The damage an entity deals by itself, from its level and effects but none of its equipment
+ */
+pub async fn unarmed_damage(
+    world: &ServerWorldRef,
+    entity_id: EntityId,
+) -> Result<i32, ServerWorldError> {
+    let base = match base_stats(world, entity_id).await? {
+        Some(base) => base,
+        None => return Ok(0),
+    };
+    Ok(stats::compute(&base, &collect_modifiers(world, entity_id, false).await?).damage)
+}
+
+/**
+ * Recompute the derived stats of an entity after one of their inputs changed,
+ * pushing them into the components combat and movement read
+ */
+pub async fn refresh_stats(
+    world: &ServerWorldRef,
+    entity_id: EntityId,
+) -> Result<(), ServerWorldError> {
+    let base = match base_stats(world, entity_id).await? {
+        Some(base) => base,
+        None => return Ok(()),
+    };
+    let computed = stats::compute(&base, &collect_modifiers(world, entity_id, true).await?);
+    if world.get_optional_component::<Stats>(entity_id).await?.as_ref() == Some(&computed) {
+        return Ok(());
+    }
+    if let Some(mut health) = world.get_optional_component::<Health>(entity_id).await? {
+        if health.max != computed.max_health {
+            health.max = computed.max_health;
+            health.current = health.current.min(health.max);
+            world.set_component(entity_id, health).await?;
+        }
+    }
+    let mut attack = world
+        .get_optional_component::<AttackStats>(entity_id)
+        .await?
+        .unwrap_or_default();
+    if attack.damage != computed.damage {
+        attack.damage = computed.damage;
+        world.set_component(entity_id, attack).await?;
+    }
+    let mut defense = world
+        .get_optional_component::<Defense>(entity_id)
+        .await?
+        .unwrap_or_default();
+    if defense.armor != computed.armor {
+        defense.armor = computed.armor;
+        world.set_component(entity_id, defense).await?;
+    }
+    if let Some(mut movement) = world.get_optional_component::<Movement>(entity_id).await? {
+        if movement.ticks_per_step != computed.ticks_per_step {
+            movement.ticks_per_step = computed.ticks_per_step;
+            world.set_component(entity_id, movement).await?;
+        }
+    }
+    world.set_component(entity_id, computed).await
+}
+
+/**
+ * Hand out experience, levelling up as many times as it's enough for
+ */
+pub async fn grant_xp(
+    world: &ServerWorldRef,
+    entity_id: EntityId,
+    xp: u64,
+) -> Result<(), ServerWorldError> {
+    let mut experience = match world.get_optional_component::<Experience>(entity_id).await? {
+        Some(experience) if xp > 0 => experience,
+        _ => return Ok(()),
+    };
+    experience.xp += xp;
+    let level = world.get_level_curve().level_for_xp(experience.xp);
+    let previous = experience.level;
+    experience.level = level.max(previous);
+    world.set_component(entity_id, experience).await?;
+    if level <= previous {
+        return Ok(());
+    }
+    let location = world
+        .get_optional_component::<Position>(entity_id)
+        .await?
+        .and_then(|p| p.block_position());
+    for level in previous + 1..=level {
+        world
+            .emit_event(
+                location,
+                GameEvent::LevelUp {
+                    entity: entity_id,
+                    level,
+                },
+            )
+            .await;
+    }
+    refresh_stats(world, entity_id).await
+}
+
+pub async fn equip(
+    world: &ServerWorldRef,
+    actor: EntityId,
+    item_id: EntityId,
+) -> Result<ServerResponseType, ServerWorldError> {
+    if world.has_component::<Dead>(actor).await? {
+        return Ok(ServerResponseType::PermissionDenied {});
+    }
+    match world.get_optional_component::<Inventory>(actor).await? {
+        Some(inventory) if inventory.contains(item_id) => {}
+        _ => {
+            return Ok(ServerResponseType::Error {
+                message: "item not in inventory",
+            })
+        }
+    }
+    let slot = match world.get_optional_component::<Item>(item_id).await? {
+        Some(item) => world
+            .get_item_type(item.item_type_id)
+            .and_then(|t| t.get_slot()),
+        None => None,
+    };
+    let slot = match slot {
+        Some(slot) if !world.is_item_in_trade(item_id).await => slot,
+        _ => {
+            return Ok(ServerResponseType::Error {
+                message: "item cannot be equipped",
+            })
+        }
+    };
+    let mut equipment = world
+        .get_optional_component::<Equipment>(actor)
+        .await?
+        .unwrap_or_default();
+    if equipment.is_equipped(item_id) {
+        return Ok(ServerResponseType::Error {
+            message: "item already equipped",
+        });
+    }
+    //whatever was in the slot goes back to just being carried
+    equipment.equip(slot, item_id);
+    world.set_component(actor, equipment).await?;
+    refresh_stats(world, actor).await?;
+    Ok(ServerResponseType::Ok {})
+}
+
+pub async fn unequip(
+    world: &ServerWorldRef,
+    actor: EntityId,
+    item_id: EntityId,
+) -> Result<ServerResponseType, ServerWorldError> {
+    let mut equipment = match world.get_optional_component::<Equipment>(actor).await? {
+        Some(equipment) if equipment.is_equipped(item_id) => equipment,
+        _ => {
+            return Ok(ServerResponseType::Error {
+                message: "item not equipped",
+            })
+        }
+    };
+    equipment.unequip(item_id);
+    world.set_component(actor, equipment).await?;
+    refresh_stats(world, actor).await?;
+    Ok(ServerResponseType::Ok {})
+}
+
+/**
+ * Take an item off an entity if it's wearing it, for when the item leaves its inventory
+ */
+pub async fn unequip_if_equipped(
+    world: &ServerWorldRef,
+    owner: EntityId,
+    item_id: EntityId,
+) -> Result<(), ServerWorldError> {
+    if let Some(mut equipment) = world.get_optional_component::<Equipment>(owner).await? {
+        if equipment.unequip(item_id) {
+            world.set_component(owner, equipment).await?;
+            refresh_stats(world, owner).await?;
+        }
+    }
+    Ok(())
+}
+
+/**
+ * Wear down everything an entity has equipped, refreshing its stats if something broke
+ */
+pub async fn wear_equipment(
+    world: &ServerWorldRef,
+    entity_id: EntityId,
+) -> Result<(), ServerWorldError> {
+    let equipment = match world.get_optional_component::<Equipment>(entity_id).await? {
+        Some(equipment) => equipment,
+        None => return Ok(()),
+    };
+    let mut broke = false;
+    for item_id in equipment.items() {
+        broke |= wear(world, item_id).await?;
+    }
+    if broke {
+        refresh_stats(world, entity_id).await?;
+    }
+    Ok(())
+}
+
+/**
+ * Wear down a single item, whether it's equipped or not, returning whether it broke
+ */
+async fn wear(world: &ServerWorldRef, item_id: EntityId) -> Result<bool, ServerWorldError> {
+    match world.get_optional_component::<Durability>(item_id).await? {
+        Some(mut durability) if !durability.is_broken() => {
+            let broke = durability.wear(1);
+            world.set_component(item_id, durability).await?;
+            Ok(broke)
+        }
+        _ => Ok(false),
+    }
+}
+
+/**
+ * Wear down an item the owner is using, refreshing their stats if it broke while equipped
+ */
+pub async fn wear_item(
+    world: &ServerWorldRef,
+    owner: EntityId,
+    item_id: EntityId,
+) -> Result<(), ServerWorldError> {
+    if wear(world, item_id).await? {
+        let equipped = world
+            .get_optional_component::<Equipment>(owner)
+            .await?
+            .is_some_and(|equipment| equipment.is_equipped(item_id));
+        if equipped {
+            refresh_stats(world, owner).await?;
+        }
+    }
+    Ok(())
+}
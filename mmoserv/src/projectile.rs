@@ -0,0 +1,356 @@
+use mmolib::{
+    chunk,
+    combat::{compute_damage, AttackStats, Dead, Defense, Health, Projectile},
+    entity_id::EntityId,
+    event::GameEvent,
+    item::{item_type_id, Durability, Inventory, Item},
+    position::Position,
+    server_request_type::AimTarget,
+    server_response_type::ServerResponseType,
+};
+
+use crate::{
+    combat, inventory, progression, pvp,
+    server_world::{ServerWorldError, ServerWorldRef},
+    status_effects, visibility,
+};
+
+async fn block_position_of(
+    world: &ServerWorldRef,
+    entity_id: EntityId,
+) -> Result<Option<chunk::Position>, ServerWorldError> {
+    Ok(world
+        .get_optional_component::<Position>(entity_id)
+        .await?
+        .and_then(|p| p.block_position()))
+}
+
+/**
+ * Fire a ranged weapon from the actor's inventory
+ */
+pub async fn shoot(
+    world: &ServerWorldRef,
+    actor: EntityId,
+    weapon_id: EntityId,
+    target: &AimTarget,
+) -> Result<ServerResponseType, ServerWorldError> {
+    if world.has_component::<Dead>(actor).await? {
+        return Ok(ServerResponseType::PermissionDenied {});
+    }
+    let in_inventory = world
+        .get_optional_component::<Inventory>(actor)
+        .await?
+        .is_some_and(|inventory| inventory.contains(weapon_id));
+    let weapon = match world.get_optional_component::<Item>(weapon_id).await? {
+        Some(item) if in_inventory => item,
+        _ => {
+            return Ok(ServerResponseType::Error {
+                message: "item not in inventory",
+            })
+        }
+    };
+    let ranged = match world
+        .get_item_type(weapon.item_type_id)
+        .and_then(|t| t.get_ranged())
+    {
+        Some(ranged) => ranged,
+        None => {
+            return Ok(ServerResponseType::Error {
+                message: "not a ranged weapon",
+            })
+        }
+    };
+    let broken = world
+        .get_optional_component::<Durability>(weapon_id)
+        .await?
+        .is_some_and(|d| d.is_broken());
+    if broken {
+        return Ok(ServerResponseType::Error {
+            message: "weapon is broken",
+        });
+    }
+    let mut attack = world
+        .get_optional_component::<AttackStats>(actor)
+        .await?
+        .unwrap_or_default();
+    let tick = world.get_tick();
+    if tick < attack.next_attack_tick {
+        return Ok(ServerResponseType::Error {
+            message: "attack on cooldown",
+        });
+    }
+    let origin = match block_position_of(world, actor).await? {
+        Some(origin) => origin,
+        None => {
+            return Ok(ServerResponseType::Error {
+                message: "not in the world",
+            })
+        }
+    };
+    let aim = match target {
+        AimTarget::Entity(target) => {
+            if *target == actor || !pvp::can_harm(world, actor, *target).await? {
+                return Ok(ServerResponseType::PermissionDenied {});
+            }
+            match block_position_of(world, *target).await? {
+                Some(aim) => aim,
+                None => {
+                    return Ok(ServerResponseType::Error {
+                        message: "target out of range",
+                    })
+                }
+            }
+        }
+        AimTarget::Position(aim) => *aim,
+    };
+    if aim == origin {
+        return Ok(ServerResponseType::Error {
+            message: "nothing to aim at",
+        });
+    }
+    if let Some(ammo) = &ranged.ammo {
+        if !inventory::take_items(world, actor, item_type_id(ammo), 1).await? {
+            return Ok(ServerResponseType::Error {
+                message: "out of ammunition",
+            });
+        }
+    }
+    //the shooter's own damage adds to the weapon's, but not that of a melee weapon in hand
+    let damage = ranged.damage + progression::unarmed_damage(world, actor).await?;
+    attack.next_attack_tick = tick + ranged.cooldown_ticks;
+    world.set_component(actor, attack).await?;
+    progression::wear_item(world, actor, weapon_id).await?;
+    let projectile_id = EntityId::new();
+    world
+        .set_component(projectile_id, Position::from_block_position(origin))
+        .await?;
+    world
+        .set_component(
+            projectile_id,
+            Projectile {
+                shooter: actor,
+                origin,
+                aim,
+                step: 0,
+                range: ranged.range,
+                speed: ranged.speed.max(1),
+                damage,
+                effect: ranged.effect,
+            },
+        )
+        .await?;
+    world
+        .emit_event(
+            Some(origin),
+            GameEvent::ProjectileFired {
+                projectile: projectile_id,
+                shooter: actor,
+            },
+        )
+        .await;
+    Ok(ServerResponseType::Ok {})
+}
+
+/**
+ * The first living entity on a block the shooter is allowed to hurt
+ */
+async fn target_at(
+    world: &ServerWorldRef,
+    projectile: &Projectile,
+    position: chunk::Position,
+) -> Result<Option<EntityId>, ServerWorldError> {
+    for entity_id in world.get_entities_at(position).await? {
+        if entity_id == projectile.shooter {
+            continue;
+        }
+        match world.get_optional_component::<Health>(entity_id).await? {
+            Some(health) if !health.is_dead() => {}
+            _ => continue,
+        }
+        //shots pass through those the rules protect
+        if pvp::can_harm(world, projectile.shooter, entity_id).await? {
+            return Ok(Some(entity_id));
+        }
+    }
+    Ok(None)
+}
+
+async fn hit(
+    world: &ServerWorldRef,
+    projectile: &Projectile,
+    target: EntityId,
+) -> Result<(), ServerWorldError> {
+    let defense = world
+        .get_optional_component::<Defense>(target)
+        .await?
+        .unwrap_or_default();
+    let damage = compute_damage(
+        &AttackStats::new(projectile.damage, projectile.range, 0),
+        &defense,
+    );
+    progression::wear_equipment(world, target).await?;
+    combat::apply_damage(world, Some(projectile.shooter), target, damage).await?;
+    if let Some((effect, magnitude, duration_ticks)) = projectile.effect {
+        status_effects::apply_effect(
+            world,
+            target,
+            effect,
+            magnitude,
+            duration_ticks,
+            Some(projectile.shooter),
+        )
+        .await?;
+    }
+    Ok(())
+}
+
+async fn impact(
+    world: &ServerWorldRef,
+    projectile_id: EntityId,
+    projectile: &Projectile,
+    position: chunk::Position,
+    target: Option<EntityId>,
+) -> Result<(), ServerWorldError> {
+    world
+        .emit_event(
+            Some(position),
+            GameEvent::ProjectileImpact {
+                projectile: projectile_id,
+                shooter: projectile.shooter,
+                target,
+            },
+        )
+        .await;
+    world.despawn_entity(projectile_id).await
+}
+
+/**
+ * Move one projectile along its line, stopping at the first wall or target
+ */
+async fn advance(
+    world: &ServerWorldRef,
+    projectile_id: EntityId,
+    mut projectile: Projectile,
+) -> Result<(), ServerWorldError> {
+    let mut position = match projectile.position_at(projectile.step) {
+        Some(position) => position,
+        None => return world.despawn_entity(projectile_id).await,
+    };
+    let last_step = (projectile.step + projectile.speed).min(projectile.range);
+    //each step moves at most one block
+    let snapshot = world
+        .snapshot_area(chunk::area_around(position, position, projectile.speed))
+        .await?;
+    while projectile.step < last_step {
+        let next = match projectile.position_at(projectile.step + 1) {
+            Some(next) if !visibility::is_opaque(&snapshot, next) => next,
+            //hit a wall or the edge of the world
+            _ => return impact(world, projectile_id, &projectile, position, None).await,
+        };
+        projectile.step += 1;
+        position = next;
+        if let Some(target) = target_at(world, &projectile, position).await? {
+            hit(world, &projectile, target).await?;
+            return impact(world, projectile_id, &projectile, position, Some(target)).await;
+        }
+    }
+    if projectile.step >= projectile.range {
+        //spent, drops out of the air
+        return impact(world, projectile_id, &projectile, position, None).await;
+    }
+    world
+        .set_component(projectile_id, Position::from_block_position(position))
+        .await?;
+    world.set_component(projectile_id, projectile).await
+}
+
+pub async fn run(world: &ServerWorldRef) -> Result<(), ServerWorldError> {
+    let projectiles = world
+        .get_entities_with_component_type_ids([mmolib::component::get_type_id::<Projectile>()])
+        .await?;
+    for projectile_id in projectiles {
+        if let Some(projectile) = world
+            .get_optional_component::<Projectile>(projectile_id)
+            .await?
+        {
+            advance(world, projectile_id, projectile).await?;
+        }
+    }
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_shoot_wears_weapon() -> Result<(), ServerWorldError> {
+    use mmolib::stats::BaseStats;
+    let world = crate::server_world::test_world("projectile").await;
+    let actor = EntityId::new();
+    world.set_component(actor, Position::new(3, 3)).await?;
+    world.set_component(actor, Inventory::new(10)).await?;
+    world
+        .set_component(
+            actor,
+            BaseStats {
+                max_health: 10,
+                damage: 3,
+                armor: 0,
+                ticks_per_step: 1,
+            },
+        )
+        .await?;
+    inventory::give_item(&world, actor, item_type_id("bow"), 1).await?;
+    inventory::give_item(&world, actor, item_type_id("arrow"), 2).await?;
+    inventory::give_item(&world, actor, item_type_id("sword"), 1).await?;
+    let items = world
+        .get_optional_component::<Inventory>(actor)
+        .await?
+        .unwrap()
+        .items;
+    let (bow, sword) = (items[0], items[2]);
+    //a sword in hand doesn't make arrows hit harder
+    assert!(matches!(
+        progression::equip(&world, actor, sword).await?,
+        ServerResponseType::Ok {}
+    ));
+    //one shot left in it, and it doesn't need to be equipped to wear out
+    world
+        .set_component(
+            bow,
+            Durability {
+                current: 1,
+                max: 150,
+            },
+        )
+        .await?;
+    let aim = AimTarget::Position((3, 8));
+    assert!(matches!(
+        shoot(&world, actor, bow, &aim).await?,
+        ServerResponseType::Ok {}
+    ));
+    world.write_all_changes().await?;
+    let mut projectile = None;
+    for projectile_id in world
+        .get_entities_with_component_type_ids([mmolib::component::get_type_id::<Projectile>()])
+        .await?
+    {
+        projectile = world
+            .get_optional_component::<Projectile>(projectile_id)
+            .await?
+            .filter(|p| p.shooter == actor)
+            .or(projectile);
+    }
+    let projectile = projectile.unwrap();
+    //the bow's damage plus the shooter's
+    assert_eq!(projectile.damage, 7);
+    assert!(world
+        .get_optional_component::<Durability>(bow)
+        .await?
+        .unwrap()
+        .is_broken());
+    assert!(matches!(
+        shoot(&world, actor, bow, &aim).await?,
+        ServerResponseType::Error {
+            message: "weapon is broken"
+        }
+    ));
+    Ok(())
+}
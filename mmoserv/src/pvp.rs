@@ -0,0 +1,81 @@
+use mmolib::{
+    entity_id::EntityId, group::GuildMembership, player::Player, position::Position,
+    stats::Experience,
+};
+
+use crate::server_world::{ServerWorldError, ServerWorldRef};
+
+async fn level_of(world: &ServerWorldRef, entity_id: EntityId) -> Result<u32, ServerWorldError> {
+    Ok(world
+        .get_optional_component::<Experience>(entity_id)
+        .await?
+        .map_or(1, |e| e.level))
+}
+
+async fn in_safe_zone(
+    world: &ServerWorldRef,
+    entity_id: EntityId,
+) -> Result<bool, ServerWorldError> {
+    Ok(world
+        .get_optional_component::<Position>(entity_id)
+        .await?
+        .and_then(|p| p.block_position())
+        .is_some_and(|p| world.is_safe_zone(p)))
+}
+
+/**
+ * Whether two players are on the same side, meaning the same party or guild
+ */
+pub async fn are_allies(
+    world: &ServerWorldRef,
+    a: EntityId,
+    b: EntityId,
+) -> Result<bool, ServerWorldError> {
+    if a == b {
+        return Ok(true);
+    }
+    if world.get_party(a).await.is_some_and(|party| party.is_member(b)) {
+        return Ok(true);
+    }
+    match (
+        world.get_optional_component::<GuildMembership>(a).await?,
+        world.get_optional_component::<GuildMembership>(b).await?,
+    ) {
+        (Some(a), Some(b)) => Ok(a.guild == b.guild),
+        _ => Ok(false),
+    }
+}
+
+/**
+ * Whether the world rules let one entity harm another. Only fights between players are restricted
+ */
+pub async fn can_harm(
+    world: &ServerWorldRef,
+    attacker: EntityId,
+    target: EntityId,
+) -> Result<bool, ServerWorldError> {
+    if !world.has_component::<Player>(attacker).await?
+        || !world.has_component::<Player>(target).await?
+    {
+        return Ok(true);
+    }
+    let rules = world.get_rules().await;
+    if !rules.pvp {
+        return Ok(false);
+    }
+    if in_safe_zone(world, attacker).await? || in_safe_zone(world, target).await? {
+        return Ok(false);
+    }
+    if !rules.friendly_fire && are_allies(world, attacker, target).await? {
+        return Ok(false);
+    }
+    if let Some(max_gap) = rules.max_level_gap {
+        let gap = level_of(world, attacker)
+            .await?
+            .abs_diff(level_of(world, target).await?);
+        if gap > max_gap {
+            return Ok(false);
+        }
+    }
+    Ok(true)
+}
@@ -0,0 +1,432 @@
+use mmolib::{
+    ai::Npc,
+    chunk,
+    combat::Dead,
+    entity_id::EntityId,
+    event::GameEvent,
+    player::Player,
+    position::Position,
+    quest::{QuestLog, QuestProgress},
+    server_response_type::ServerResponseType,
+};
+
+use crate::{
+    inventory, progression,
+    server_world::{ServerWorldError, ServerWorldRef},
+};
+
+//how far away an npc can be talked to or handed quests, 1 is adjacent
+pub const TALK_REACH: u32 = 1;
+//a player can't have more quests going than this
+pub const MAX_ACTIVE_QUESTS: usize = 20;
+//how often locations and collected items are checked against quest objectives
+const QUEST_POLL_INTERVAL_TICKS: u64 = 20;
+
+async fn block_position_of(
+    world: &ServerWorldRef,
+    entity_id: EntityId,
+) -> Result<Option<chunk::Position>, ServerWorldError> {
+    Ok(world
+        .get_optional_component::<Position>(entity_id)
+        .await?
+        .and_then(|p| p.block_position()))
+}
+
+/**
+ * Whether an npc of the given kind stands within talking distance
+ */
+async fn is_near_npc(
+    world: &ServerWorldRef,
+    actor: EntityId,
+    kind: &str,
+) -> Result<bool, ServerWorldError> {
+    let from = match block_position_of(world, actor).await? {
+        Some(from) => from,
+        None => return Ok(false),
+    };
+    let npcs = world
+        .get_entities_with_component_type_ids([
+            mmolib::component::get_type_id::<Npc>(),
+            mmolib::component::get_type_id::<Position>(),
+        ])
+        .await?;
+    for npc_id in npcs {
+        let is_kind = world
+            .get_optional_component::<Npc>(npc_id)
+            .await?
+            .is_some_and(|npc| npc.kind == kind);
+        if !is_kind {
+            continue;
+        }
+        if let Some(p) = block_position_of(world, npc_id).await? {
+            if chunk::chebyshev_distance(from, p) <= TALK_REACH {
+                return Ok(true);
+            }
+        }
+    }
+    Ok(false)
+}
+
+pub async fn talk(
+    world: &ServerWorldRef,
+    actor: EntityId,
+    npc_id: EntityId,
+) -> Result<ServerResponseType, ServerWorldError> {
+    if world.has_component::<Dead>(actor).await? {
+        return Ok(ServerResponseType::PermissionDenied {});
+    }
+    let npc = match world.get_optional_component::<Npc>(npc_id).await? {
+        Some(npc) => npc,
+        None => {
+            return Ok(ServerResponseType::Error {
+                message: "nobody to talk to",
+            })
+        }
+    };
+    let to = match (
+        block_position_of(world, actor).await?,
+        block_position_of(world, npc_id).await?,
+    ) {
+        (Some(from), Some(to)) if chunk::chebyshev_distance(from, to) <= TALK_REACH => to,
+        _ => {
+            return Ok(ServerResponseType::Error {
+                message: "too far away to talk",
+            })
+        }
+    };
+    world
+        .emit_event(
+            Some(to),
+            GameEvent::Talked {
+                entity: actor,
+                npc: npc_id,
+                kind: npc.kind,
+            },
+        )
+        .await;
+    Ok(ServerResponseType::Ok {})
+}
+
+pub async fn accept(
+    world: &ServerWorldRef,
+    actor: EntityId,
+    quest_name: &str,
+) -> Result<ServerResponseType, ServerWorldError> {
+    if world.has_component::<Dead>(actor).await? {
+        return Ok(ServerResponseType::PermissionDenied {});
+    }
+    let quest = match world.get_quest(quest_name) {
+        Some(quest) => quest,
+        None => {
+            return Ok(ServerResponseType::Error {
+                message: "unknown quest",
+            })
+        }
+    };
+    let mut log = world
+        .get_optional_component::<QuestLog>(actor)
+        .await?
+        .unwrap_or_default();
+    if log.get_active(quest_name).is_some() {
+        return Ok(ServerResponseType::Error {
+            message: "quest already accepted",
+        });
+    }
+    if log.has_completed(quest_name) && !quest.is_repeatable() {
+        return Ok(ServerResponseType::Error {
+            message: "quest already completed",
+        });
+    }
+    if log.active.len() >= MAX_ACTIVE_QUESTS {
+        return Ok(ServerResponseType::Error {
+            message: "too many quests",
+        });
+    }
+    if let Some(giver) = quest.get_giver() {
+        if !is_near_npc(world, actor, giver).await? {
+            return Ok(ServerResponseType::Error {
+                message: "quest giver not nearby",
+            });
+        }
+    }
+    log.active.push(QuestProgress::new(quest));
+    world.set_component(actor, log).await?;
+    world
+        .emit_event(
+            block_position_of(world, actor).await?,
+            GameEvent::QuestAccepted {
+                entity: actor,
+                quest: quest_name.to_owned(),
+            },
+        )
+        .await;
+    Ok(ServerResponseType::Ok {})
+}
+
+pub async fn turn_in(
+    world: &ServerWorldRef,
+    actor: EntityId,
+    quest_name: &str,
+) -> Result<ServerResponseType, ServerWorldError> {
+    if world.has_component::<Dead>(actor).await? {
+        return Ok(ServerResponseType::PermissionDenied {});
+    }
+    let quest = match world.get_quest(quest_name) {
+        Some(quest) => quest,
+        None => {
+            return Ok(ServerResponseType::Error {
+                message: "unknown quest",
+            })
+        }
+    };
+    let mut log = match world.get_optional_component::<QuestLog>(actor).await? {
+        Some(log) if log.get_active(quest_name).is_some() => log,
+        _ => {
+            return Ok(ServerResponseType::Error {
+                message: "quest not accepted",
+            })
+        }
+    };
+    if let Some(giver) = quest.get_giver() {
+        if !is_near_npc(world, actor, giver).await? {
+            return Ok(ServerResponseType::Error {
+                message: "quest giver not nearby",
+            });
+        }
+    }
+    //collected items are checked again now since they're about to be handed over
+    let mut progress = log.get_active(quest_name).cloned().unwrap();
+    let mut held = Vec::new();
+    for objective in quest.get_objectives() {
+        if let mmolib::quest::Objective::Collect { item, .. } = objective {
+            let item_type_id = mmolib::item::item_type_id(item);
+            held.push((item_type_id, inventory::count_items(world, actor, item_type_id).await?));
+        }
+    }
+    progress.record_held(quest, |id| {
+        held.iter()
+            .find(|(held_id, _)| *held_id == id)
+            .map_or(0, |(_, count)| *count)
+    });
+    if !quest.is_complete(&progress) {
+        return Ok(ServerResponseType::Error {
+            message: "quest not finished",
+        });
+    }
+    //objectives wanting the same item are handed in together, so none of them is taken unless all can be
+    let mut wanted: Vec<(mmolib::item::ItemTypeId, u32)> = Vec::new();
+    for objective in quest.get_objectives() {
+        if let mmolib::quest::Objective::Collect { item, count } = objective {
+            let item_type_id = mmolib::item::item_type_id(item);
+            match wanted.iter_mut().find(|(id, _)| *id == item_type_id) {
+                Some((_, total)) => *total += count,
+                None => wanted.push((item_type_id, *count)),
+            }
+        }
+    }
+    for (item_type_id, count) in &wanted {
+        if inventory::count_items(world, actor, *item_type_id).await? < *count {
+            return Ok(ServerResponseType::Error {
+                message: "missing quest items",
+            });
+        }
+    }
+    for (item_type_id, count) in wanted {
+        if !inventory::take_items(world, actor, item_type_id, count).await? {
+            return Ok(ServerResponseType::Error {
+                message: "missing quest items",
+            });
+        }
+    }
+    log.active.retain(|p| p.quest != quest_name);
+    if !log.has_completed(quest_name) {
+        log.completed.push(quest_name.to_owned());
+    }
+    world.set_component(actor, log).await?;
+    let location = block_position_of(world, actor).await?;
+    for (item_type_id, count) in quest.get_rewards().get_items() {
+        let leftover = inventory::give_item(world, actor, item_type_id, count).await?;
+        if let (true, Some(location)) = (leftover > 0, location) {
+            inventory::spawn_item_at(world, location, item_type_id, leftover).await?;
+        }
+    }
+    progression::grant_xp(world, actor, quest.get_rewards().get_xp()).await?;
+    world
+        .emit_event(
+            location,
+            GameEvent::QuestCompleted {
+                entity: actor,
+                quest: quest_name.to_owned(),
+            },
+        )
+        .await;
+    Ok(ServerResponseType::Ok {})
+}
+
+/**
+ * Apply a change to every active quest of a player, telling them about the ones that moved on
+ */
+async fn update_log(
+    world: &ServerWorldRef,
+    entity_id: EntityId,
+    f: impl Fn(&mut QuestProgress, &mmolib::quest::Quest) -> bool,
+) -> Result<(), ServerWorldError> {
+    let mut log = match world.get_optional_component::<QuestLog>(entity_id).await? {
+        Some(log) if !log.active.is_empty() => log,
+        _ => return Ok(()),
+    };
+    let mut updated = Vec::new();
+    for progress in log.active.iter_mut() {
+        if let Some(quest) = world.get_quest(&progress.quest) {
+            if f(progress, quest) {
+                updated.push(progress.quest.clone());
+            }
+        }
+    }
+    if updated.is_empty() {
+        return Ok(());
+    }
+    world.set_component(entity_id, log).await?;
+    let location = block_position_of(world, entity_id).await?;
+    for quest in updated {
+        world
+            .emit_event(
+                location,
+                GameEvent::QuestUpdated {
+                    entity: entity_id,
+                    quest,
+                },
+            )
+            .await;
+    }
+    Ok(())
+}
+
+/**
+ * Check locations and held items against the objectives that depend on them
+ */
+async fn poll(world: &ServerWorldRef, entity_id: EntityId) -> Result<(), ServerWorldError> {
+    let log = match world.get_optional_component::<QuestLog>(entity_id).await? {
+        Some(log) if !log.active.is_empty() => log,
+        _ => return Ok(()),
+    };
+    let mut held = Vec::new();
+    for progress in &log.active {
+        if let Some(quest) = world.get_quest(&progress.quest) {
+            for objective in quest.get_objectives() {
+                if let mmolib::quest::Objective::Collect { item, .. } = objective {
+                    let item_type_id = mmolib::item::item_type_id(item);
+                    held.push((item_type_id, inventory::count_items(world, entity_id, item_type_id).await?));
+                }
+            }
+        }
+    }
+    let position = block_position_of(world, entity_id).await?;
+    update_log(world, entity_id, |progress, quest| {
+        let moved = position.is_some_and(|p| progress.record_position(quest, p));
+        let collected = progress.record_held(quest, |id| {
+            held.iter()
+                .find(|(held_id, _)| *held_id == id)
+                .map_or(0, |(_, count)| *count)
+        });
+        moved || collected
+    })
+    .await
+}
+
+/**
+ * Move quests along from this tick's events, and every so often from where players are and what they carry
+ */
+pub async fn run(world: &ServerWorldRef) -> Result<(), ServerWorldError> {
+    for (_, event) in world.get_pending_events().await {
+        match event {
+            GameEvent::Death {
+                killer: Some(killer),
+                kind: Some(kind),
+                ..
+            } => {
+                update_log(world, killer, |progress, quest| progress.record_kill(quest, &kind))
+                    .await?
+            }
+            GameEvent::Talked { entity, kind, .. } => {
+                update_log(world, entity, |progress, quest| progress.record_talk(quest, &kind))
+                    .await?
+            }
+            _ => {}
+        }
+    }
+    if !world.get_tick().is_multiple_of(QUEST_POLL_INTERVAL_TICKS) {
+        return Ok(());
+    }
+    let players = world
+        .get_entities_with_component_type_ids([
+            mmolib::component::get_type_id::<Player>(),
+            mmolib::component::get_type_id::<QuestLog>(),
+        ])
+        .await?;
+    for player in players {
+        poll(world, player).await?;
+    }
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_quest_progress_and_turn_in() -> Result<(), ServerWorldError> {
+    use crate::{combat, spawning};
+    use mmolib::{
+        item::{item_type_id, Inventory},
+        stats::Experience,
+    };
+    let world = crate::server_world::test_world("quests").await;
+    let actor = EntityId::new();
+    world.set_component(actor, Position::new(3, 3)).await?;
+    world.set_component(actor, Inventory::new(10)).await?;
+    world.set_component(actor, Experience::new()).await?;
+    assert!(matches!(
+        accept(&world, actor, "goblin_culling").await?,
+        ServerResponseType::Ok {}
+    ));
+    assert!(matches!(
+        accept(&world, actor, "goblin_culling").await?,
+        ServerResponseType::Error {
+            message: "quest already accepted"
+        }
+    ));
+    //spawned goblins count towards the kill objective
+    let goblin = world.get_creature("goblin").unwrap();
+    for _ in 0..5 {
+        let goblin_id = spawning::spawn_creature(&world, goblin, (4, 4)).await?;
+        combat::apply_damage(&world, Some(actor), goblin_id, 1000).await?;
+    }
+    run(&world).await?;
+    world.clear_events().await;
+    let log = world.get_optional_component::<QuestLog>(actor).await?.unwrap();
+    assert_eq!(log.get_active("goblin_culling").unwrap().progress[0], 5);
+    assert!(matches!(
+        turn_in(&world, actor, "goblin_culling").await?,
+        ServerResponseType::Error {
+            message: "quest not finished"
+        }
+    ));
+    inventory::give_item(&world, actor, item_type_id("rock"), 4).await?;
+    assert!(matches!(
+        turn_in(&world, actor, "goblin_culling").await?,
+        ServerResponseType::Ok {}
+    ));
+    assert_eq!(inventory::count_items(&world, actor, item_type_id("rock")).await?, 1);
+    assert_eq!(
+        inventory::count_items(&world, actor, item_type_id("healthpotion")).await?,
+        2
+    );
+    let log = world.get_optional_component::<QuestLog>(actor).await?.unwrap();
+    assert!(log.get_active("goblin_culling").is_none());
+    assert!(log.has_completed("goblin_culling"));
+    assert!(world.get_optional_component::<Experience>(actor).await?.unwrap().xp >= 50);
+    assert!(matches!(
+        turn_in(&world, actor, "goblin_culling").await?,
+        ServerResponseType::Error {
+            message: "quest not accepted"
+        }
+    ));
+    Ok(())
+}
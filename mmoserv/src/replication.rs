@@ -0,0 +1,190 @@
+use hashbrown::HashSet;
+use mmolib::{
+    chunk::Position,
+    component::{get_type_id, ComponentType},
+    entity_id::EntityId,
+    event::GameEvent,
+    server_response_type::{ComponentUpdate, ComponentUpdateType, ServerResponseType},
+    world_time::{world_entity, Weather, WorldTime},
+};
+
+use crate::{
+    server_world::{ServerWorldError, ServerWorldRef},
+    visibility::{FieldOfView, VIEW_RADIUS},
+};
+
+async fn snapshot<T: ComponentType>(
+    world: &ServerWorldRef,
+    entity_id: EntityId,
+) -> Result<Option<ComponentUpdate>, ServerWorldError> {
+    Ok(match world.get_optional_component::<T>(entity_id).await? {
+        Some(component) => Some(ComponentUpdate::new(
+            entity_id,
+            get_type_id::<T>(),
+            ComponentUpdateType::Added {
+                packet: serde_json::to_value(&component).map_err(ServerWorldError::SerdeError)?,
+            },
+        )),
+        None => None,
+    })
+}
+
+/**
+ * The world-wide state a player needs when they first join, as component updates
+ */
+pub async fn world_state(world: &ServerWorldRef) -> Result<Vec<ComponentUpdate>, ServerWorldError> {
+    Ok([
+        snapshot::<WorldTime>(world, world_entity()).await?,
+        snapshot::<Weather>(world, world_entity()).await?,
+    ]
+    .into_iter()
+    .flatten()
+    .collect())
+}
+
+/**
+ * Build the Ticked response for one player, leaving out what they cannot see.
+ * Entities are sent whole as they come into view and removed as they leave it
+ */
+pub async fn build_ticked(
+    world: &ServerWorldRef,
+    viewer: EntityId,
+    updates: &[ComponentUpdate],
+    events: &[(Option<Position>, GameEvent)],
+) -> Result<ServerResponseType, ServerWorldError> {
+    let origin = match world
+        .get_component_ref::<mmolib::position::Position>(viewer)
+        .await
+    {
+        Ok(position) => position.block_position(),
+        Err(ServerWorldError::ComponentNotFound) => None,
+        Err(e) => return Err(e),
+    };
+    let fov = match origin {
+        Some(origin) => Some(FieldOfView::compute(world, origin, VIEW_RADIUS).await?),
+        None => None,
+    };
+    let inventory = world
+        .get_optional_component::<mmolib::item::Inventory>(viewer)
+        .await?;
+    let in_inventory = |entity_id| inventory.as_ref().is_some_and(|i| i.contains(entity_id));
+    let mut seen = HashSet::new();
+    if let Some(fov) = &fov {
+        //entities that appeared this tick aren't found by component until the next write
+        let mut candidates = world
+            .get_entities_with_component_type_ids([get_type_id::<mmolib::position::Position>()])
+            .await?;
+        candidates.extend(updates.iter().map(|update| update.get_entity_id()));
+        for entity_id in candidates {
+            if entity_id != viewer && fov.can_see_entity(world, entity_id).await? {
+                seen.insert(entity_id);
+            }
+        }
+    }
+    let previous = world.swap_seen_entities(viewer, seen.clone()).await;
+    let mut component_updates = Vec::new();
+    //entities coming into view are sent whole, they may not have changed in a long time
+    for entity_id in seen.difference(&previous) {
+        component_updates.extend(world.snapshot_entity(*entity_id).await?);
+    }
+    //and those going out of view are taken off the client, unless it was picked up
+    for entity_id in previous.difference(&seen) {
+        if in_inventory(*entity_id) {
+            continue;
+        }
+        for update in world.snapshot_entity(*entity_id).await? {
+            component_updates.push(ComponentUpdate::new(
+                *entity_id,
+                update.get_component_type_id(),
+                ComponentUpdateType::Removed,
+            ));
+        }
+    }
+    for update in updates {
+        let entity_id = update.get_entity_id();
+        let visible = entity_id == viewer
+            || entity_id == world_entity()
+            || in_inventory(entity_id)
+            //entities just come into view were sent whole above
+            || (seen.contains(&entity_id) && previous.contains(&entity_id))
+            //removals carry no data and the entity may already be gone, e.g. despawned in view
+            || (matches!(update.get_component_update_info(), ComponentUpdateType::Removed)
+                && previous.contains(&entity_id));
+        if visible {
+            component_updates.push(update.clone());
+        }
+    }
+    let events = events
+        .iter()
+        .filter(|(location, event)| {
+            event.get_entities().contains(&viewer)
+                || match (&fov, location) {
+                    (Some(fov), Some(location)) => fov.can_see(*location),
+                    _ => false,
+                }
+        })
+        .map(|(_, event)| event.clone())
+        .collect();
+    let block_updates = match origin {
+        Some(origin) => world.get_block_updates_in_range(origin, VIEW_RADIUS).await,
+        None => Vec::new(),
+    };
+    Ok(ServerResponseType::Ticked {
+        world_name: world.get_world_name().to_owned(),
+        component_updates,
+        block_updates,
+        events,
+    })
+}
+
+#[tokio::test]
+async fn test_entities_enter_and_leave_view() -> Result<(), ServerWorldError> {
+    use mmolib::{block_type::block_type_id, chunk, combat::Health, position};
+    let world = crate::server_world::test_world("replication").await;
+    world
+        .insert_chunk(
+            chunk::chunk_id_from_position((40, 40)),
+            chunk::Chunk::new_from_array(
+                [[block_type_id("dirt"); chunk::CHUNK_SIZE]; chunk::CHUNK_SIZE],
+            ),
+        )
+        .await?;
+    let (viewer, other) = (EntityId::new(), EntityId::new());
+    world
+        .set_component(viewer, position::Position::new(40, 40))
+        .await?;
+    world
+        .set_component(other, position::Position::new(44, 44))
+        .await?;
+    world.set_component(other, Health::new(10)).await?;
+    world.write_all_changes().await?;
+    world.clear_changes().await;
+    let updates_of = |response: ServerResponseType| match response {
+        ServerResponseType::Ticked {
+            component_updates, ..
+        } => component_updates
+            .into_iter()
+            .filter(|update| update.get_entity_id() == other)
+            .map(|update| update.get_component_update_info().clone())
+            .collect::<Vec<_>>(),
+        _ => Vec::new(),
+    };
+    //standing still, but new to the viewer, so sent whole
+    let sent = updates_of(build_ticked(&world, viewer, &[], &[]).await?);
+    assert_eq!(sent.len(), 2);
+    assert!(sent
+        .iter()
+        .all(|update| matches!(update, ComponentUpdateType::Added { .. })));
+    assert!(updates_of(build_ticked(&world, viewer, &[], &[]).await?).is_empty());
+    //walking out of view takes it off the client
+    world
+        .set_component(other, position::Position::new(40, 60))
+        .await?;
+    let updates = world.get_pending_changes().await;
+    let sent = updates_of(build_ticked(&world, viewer, &updates, &[]).await?);
+    assert_eq!(sent.len(), 2);
+    assert!(sent
+        .iter()
+        .all(|update| matches!(update, ComponentUpdateType::Removed)));
+    Ok(())
+}
@@ -0,0 +1,281 @@
+use std::collections::HashMap;
+
+use mmolib::{
+    entity_id::EntityId, server_request_type::ServerRequestType,
+    server_response_type::ServerResponseType,
+};
+use tokio::sync::RwLock;
+
+use crate::{
+    character, chat, guild, moderation, party, player_action, trading,
+    server_world::{ServerWorld, ServerWorldError, ServerWorldRef},
+};
+
+/**
+ * Every world the server has running, by name
+ */
+pub struct Worlds {
+    connection_url: String,
+    raw_path: String,
+    loaded: RwLock<HashMap<String, ServerWorldRef>>,
+}
+
+impl Worlds {
+    pub fn new(connection_url: &str, raw_path: &str) -> Self {
+        Worlds {
+            connection_url: connection_url.to_owned(),
+            raw_path: raw_path.to_owned(),
+            loaded: RwLock::new(HashMap::new()),
+        }
+    }
+    pub async fn get(&self, world_name: &str) -> Option<ServerWorldRef> {
+        self.loaded.read().await.get(world_name).cloned()
+    }
+}
+
+/**
+ * Handle a request from a logged in user, creating or loading worlds or passing it on to one
+ */
+pub async fn handle_request(
+    worlds: &Worlds,
+    username: &str,
+    request: &ServerRequestType,
+) -> Result<ServerResponseType, ServerWorldError> {
+    match request {
+        ServerRequestType::CreateGame { world_name, rules } => {
+            if !rules.is_valid() {
+                return Ok(ServerResponseType::Error {
+                    message: "invalid world rules",
+                });
+            }
+            //held so the world can't be loaded while it is being set up
+            let mut loaded = worlds.loaded.write().await;
+            match ServerWorld::create(
+                &worlds.connection_url,
+                world_name,
+                &worlds.raw_path,
+                rules.clone(),
+            )
+            .await?
+            {
+                Some(world) => {
+                    loaded.insert(world_name.clone(), world);
+                    Ok(ServerResponseType::Ok {})
+                }
+                None => Ok(ServerResponseType::Error {
+                    message: "world already exists",
+                }),
+            }
+        }
+        ServerRequestType::LoadGame { world_name } => {
+            let mut loaded = worlds.loaded.write().await;
+            if !loaded.contains_key(world_name) {
+                match ServerWorld::load(&worlds.connection_url, world_name, &worlds.raw_path)
+                    .await?
+                {
+                    Some(world) => {
+                        loaded.insert(world_name.clone(), world);
+                    }
+                    None => {
+                        return Ok(ServerResponseType::Error {
+                            message: "no such world",
+                        })
+                    }
+                }
+            }
+            Ok(ServerResponseType::Ok {})
+        }
+        _ => {
+            let world_name = match request.get_world_name() {
+                Some(world_name) => world_name,
+                None => {
+                    return Ok(ServerResponseType::Error {
+                        message: "request not handled by world",
+                    })
+                }
+            };
+            match worlds.get(world_name).await {
+                Some(world) => handle_world_request(&world, username, request).await,
+                None => Ok(ServerResponseType::Error {
+                    message: "no such world",
+                }),
+            }
+        }
+    }
+}
+
+/**
+ * Handle a request aimed at a world on behalf of a logged in user
+ */
+pub async fn handle_world_request(
+    world: &ServerWorldRef,
+    username: &str,
+    request: &ServerRequestType,
+) -> Result<ServerResponseType, ServerWorldError> {
+    match request {
+        ServerRequestType::Spawn {
+            player_parameters, ..
+        } => character::spawn(world, username, player_parameters).await,
+        ServerRequestType::PlayerAction { .. }
+        | ServerRequestType::TradeRequest { .. }
+        | ServerRequestType::TradeOffer { .. }
+        | ServerRequestType::TradeAccept { .. }
+        | ServerRequestType::TradeCancel { .. }
+        | ServerRequestType::PartyAction { .. }
+        | ServerRequestType::GuildAction { .. }
+        | ServerRequestType::SendChat { .. }
+        | ServerRequestType::ChatHistory { .. }
+        | ServerRequestType::ReportMessage { .. } => {
+            let actor = match character::restore(world, username).await? {
+                Some(actor) => actor,
+                None => {
+                    return Ok(ServerResponseType::Error {
+                        message: "no character in this world",
+                    })
+                }
+            };
+            handle_character_request(world, actor, request).await
+        }
+        ServerRequestType::Mute { .. }
+        | ServerRequestType::Unmute { .. }
+        | ServerRequestType::GetReports { .. } => {
+            moderation::handle_moderator_request(world, username, request).await
+        }
+        _ => Ok(ServerResponseType::Error {
+            message: "request not handled by world",
+        }),
+    }
+}
+
+/**
+ * Requests made by a player's character
+ */
+async fn handle_character_request(
+    world: &ServerWorldRef,
+    actor: EntityId,
+    request: &ServerRequestType,
+) -> Result<ServerResponseType, ServerWorldError> {
+    match request {
+        ServerRequestType::PlayerAction { action, .. } => {
+            player_action::handle_player_action(world, actor, action).await
+        }
+        ServerRequestType::TradeRequest { target, .. } => {
+            trading::request(world, actor, *target).await
+        }
+        ServerRequestType::TradeOffer { items, coins, .. } => {
+            trading::offer(world, actor, items, *coins).await
+        }
+        ServerRequestType::TradeAccept { .. } => trading::accept(world, actor).await,
+        ServerRequestType::TradeCancel { .. } => trading::cancel(world, actor).await,
+        ServerRequestType::PartyAction { action, .. } => {
+            party::handle_party_action(world, actor, action).await
+        }
+        ServerRequestType::GuildAction { action, .. } => {
+            guild::handle_guild_action(world, actor, action).await
+        }
+        ServerRequestType::SendChat {
+            message,
+            channel,
+            to,
+            ..
+        } => chat::send(world, actor, message, *channel, to.as_deref()).await,
+        ServerRequestType::ChatHistory { channel, .. } => {
+            chat::history(world, actor, *channel).await
+        }
+        ServerRequestType::ReportMessage {
+            username, message, ..
+        } => moderation::report(world, actor, username, message).await,
+        _ => Ok(ServerResponseType::Error {
+            message: "request not handled by world",
+        }),
+    }
+}
+
+#[tokio::test]
+async fn test_create_game() -> Result<(), ServerWorldError> {
+    use mmolib::world_rules::WorldRules;
+    //fresh name each run as the test database outlives the test
+    let world_name = format!("test_create_{}", EntityId::new().id());
+    let worlds = Worlds::new("dockercuck.prizrak.me", "../raws");
+    let create = ServerRequestType::CreateGame {
+        world_name: world_name.clone(),
+        rules: WorldRules {
+            pvp: false,
+            ..WorldRules::default()
+        },
+    };
+    assert!(matches!(
+        handle_request(&worlds, "alice", &create).await?,
+        ServerResponseType::Ok {}
+    ));
+    assert!(!worlds.get(&world_name).await.unwrap().get_rules().await.pvp);
+    assert!(matches!(
+        handle_request(&worlds, "alice", &create).await?,
+        ServerResponseType::Error {
+            message: "world already exists"
+        }
+    ));
+    //a restarted server can't create it again either
+    let restarted = Worlds::new("dockercuck.prizrak.me", "../raws");
+    assert!(matches!(
+        handle_request(&restarted, "alice", &create).await?,
+        ServerResponseType::Error {
+            message: "world already exists"
+        }
+    ));
+    //worlds that were never created can't be loaded
+    assert!(matches!(
+        handle_request(
+            &restarted,
+            "alice",
+            &ServerRequestType::LoadGame {
+                world_name: format!("{}_missing", world_name),
+            },
+        )
+        .await?,
+        ServerResponseType::Error {
+            message: "no such world"
+        }
+    ));
+    assert!(matches!(
+        handle_request(
+            &restarted,
+            "alice",
+            &ServerRequestType::CreateGame {
+                world_name: format!("{}_slow", world_name),
+                rules: WorldRules {
+                    respawn_delay_ticks: u64::MAX,
+                    ..WorldRules::default()
+                },
+            },
+        )
+        .await?,
+        ServerResponseType::Error {
+            message: "invalid world rules"
+        }
+    ));
+    //requests only reach worlds that are running
+    let reports = ServerRequestType::GetReports {
+        world_name: world_name.clone(),
+    };
+    assert!(matches!(
+        handle_request(&restarted, "alice", &reports).await?,
+        ServerResponseType::Error {
+            message: "no such world"
+        }
+    ));
+    handle_request(
+        &restarted,
+        "alice",
+        &ServerRequestType::LoadGame {
+            world_name: world_name.clone(),
+        },
+    )
+    .await?;
+    assert!(!restarted.get(&world_name).await.unwrap().get_rules().await.pvp);
+    assert!(matches!(
+        handle_request(&restarted, "alice", &reports).await?,
+        ServerResponseType::PermissionDenied {}
+    ));
+    Ok(())
+}
@@ -1,4 +1,7 @@
-use std::sync::Arc;
+use std::sync::{
+    atomic::{AtomicU64, Ordering},
+    Arc,
+};
 
 use clap::Parser;
 use futures::Future;
@@ -8,7 +11,11 @@ use redis::{aio::MultiplexedConnection, AsyncCommands, Client, RedisError};
 use serde::{Deserialize, Serialize};
 use tokio::sync::RwLock;
 
-use crate::{args, change_tracker::Change, query};
+use crate::{
+    args,
+    change_tracker::{Change, ChangeTracker, ChangeType},
+    fire, loot, pathfinding, query, shop,
+};
 
 pub fn get_redis_connection_string(host: &str, port: u16) -> String {
     format!("redis://{}:{}/", host, port)
@@ -20,7 +27,9 @@ pub enum ServerWorldError {
     RedisError(RedisError),
     SerdeError(serde_json::Error),
     ComponentChanged,
-    ComponentNotFound
+    ComponentNotFound,
+    ChunkNotFound(mmolib::chunk::ChunkId),
+    ChunkSerdeError(serde_cbor::Error),
 }
 
 #[derive(Clone)]
@@ -41,21 +50,108 @@ impl ServerWorldRef {
     }
 }
 
+/**
+ * A read only copy of the chunks covering an area. Blocks outside the area or in missing chunks read as None
+ */
+pub struct AreaSnapshot<'a> {
+    world: &'a ServerWorld,
+    area: (mmolib::chunk::Position, mmolib::chunk::Position),
+    chunks: HashMap<mmolib::chunk::ChunkId, mmolib::chunk::Chunk>,
+}
+
+impl<'a> AreaSnapshot<'a> {
+    pub fn get_block(
+        &self,
+        position: mmolib::chunk::Position,
+    ) -> Option<mmolib::block_type::BlockTypeId> {
+        if !mmolib::chunk::area_contains(self.area, position) {
+            return None;
+        }
+        Some(
+            self.chunks
+                .get(&mmolib::chunk::chunk_id_from_position(position))?
+                .get_block(mmolib::chunk::convert_to_chunk_relative_position(position)),
+        )
+    }
+    pub fn get_block_type(
+        &self,
+        position: mmolib::chunk::Position,
+    ) -> Option<&'a mmolib::block_type::BlockType> {
+        self.world.get_block_type(self.get_block(position)?)
+    }
+}
+
 pub struct ServerWorld {
     world_name: String,
     conn: MultiplexedConnection,
     cached_queries: Arc<RwLock<HashMap<query::Query, query::QueryResult>>>,
     cached_components: Arc<RwLock<HashMap<mmolib::component::ComponentInstanceId, mmolib::component::Component>>>,
+    //components removed since the last write, still in redis until the pipeline runs
+    removed_components: Arc<RwLock<HashSet<mmolib::component::ComponentInstanceId>>>,
     changes: Arc<
         RwLock<
             HashMap<
                 mmolib::component::ComponentInstanceId,
-                Change,
+                ChangeTracker,
             >,
         >,
     >,
+    cached_chunks: Arc<RwLock<HashMap<mmolib::chunk::ChunkId, mmolib::chunk::Chunk>>>,
+    block_updates: Arc<RwLock<Vec<mmolib::server_response_type::BlockUpdate>>>,
+    fluid_queue: Arc<RwLock<Vec<mmolib::chunk::Position>>>,
+    burning: Arc<RwLock<HashMap<mmolib::chunk::Position, fire::BurningBlock>>>,
+    trades: Arc<RwLock<Vec<mmolib::trade::Trade>>>,
+    //held while items or coins change hands, taken before the trades when both are needed
+    economy_lock: Arc<tokio::sync::Mutex<()>>,
+    parties: Arc<RwLock<Vec<mmolib::group::Party>>>,
+    //held while a party is read, changed and stored back
+    party_lock: Arc<tokio::sync::Mutex<()>>,
+    next_party_id: AtomicU64,
+    //(inviter, invitee)
+    party_invites: Arc<RwLock<Vec<(mmolib::entity_id::EntityId, mmolib::entity_id::EntityId)>>>,
+    //guilds read from storage, None for names known not to exist
+    cached_guilds: Arc<RwLock<HashMap<String, Option<mmolib::group::Guild>>>>,
+    //(guild, invitee)
+    guild_invites: Arc<RwLock<Vec<(String, mmolib::entity_id::EntityId)>>>,
+    //held while a guild is read, changed and written back
+    guild_lock: Arc<tokio::sync::Mutex<()>>,
+    //player entities being ticked for a connected client
+    online: Arc<RwLock<HashSet<mmolib::entity_id::EntityId>>>,
+    //entities each online player's client has been sent, to tell when they come into or go out of view
+    seen_entities: Arc<RwLock<HashMap<mmolib::entity_id::EntityId, HashSet<mmolib::entity_id::EntityId>>>>,
+    //chat allowance of each online player, reset when they go offline
+    chat_limits: Arc<RwLock<HashMap<mmolib::entity_id::EntityId, mmolib::moderation::RateLimit>>>,
+    //tick each player may next report at, kept across sessions so logging out doesn't reset it
+    report_limits: Arc<RwLock<HashMap<mmolib::entity_id::EntityId, u64>>>,
+    //last lines each online player was sent, the context for their reports
+    received_chat: Arc<RwLock<HashMap<mmolib::entity_id::EntityId, std::collections::VecDeque<mmolib::chat::ChatLine>>>>,
+    chat_outbox: Arc<RwLock<Vec<(mmolib::entity_id::EntityId, mmolib::server_response_type::ServerResponseType)>>>,
+    events: Arc<RwLock<Vec<(Option<mmolib::chunk::Position>, mmolib::event::GameEvent)>>>,
+    block_types: HashMap<mmolib::block_type::BlockTypeId, mmolib::block_type::BlockType>,
+    item_types: HashMap<mmolib::item::ItemTypeId, mmolib::item::ItemType>,
+    effect_rules: HashMap<mmolib::effect::Effect, mmolib::effect::EffectRule>,
+    classes: HashMap<String, mmolib::character::CharacterClass>,
+    races: HashMap<String, mmolib::character::Race>,
+    spawn_point: mmolib::position::Position,
+    level_curve: mmolib::stats::LevelCurve,
+    behavior_trees: HashMap<String, mmolib::ai::BehaviorTree>,
+    loot_tables: std::collections::HashMap<String, mmolib::loot::LootTable>,
+    creatures: HashMap<String, mmolib::ai::Creature>,
+    spawn_tables: HashMap<String, mmolib::ai::SpawnTable>,
+    recipes: HashMap<String, mmolib::recipe::Recipe>,
+    quests: HashMap<String, mmolib::quest::Quest>,
+    shops: HashMap<String, mmolib::shop::Shop>,
+    word_filters: Vec<mmolib::moderation::WordFilter>,
+    vendor_placements: Vec<mmolib::shop::VendorPlacement>,
+    chest_placements: Vec<mmolib::loot::ChestPlacement>,
+    safe_zones: Vec<mmolib::world_rules::SafeZone>,
+    build_zones: Vec<mmolib::world_rules::BuildZone>,
+    regions: Vec<mmolib::world_time::Region>,
+    weather_rules: HashMap<mmolib::world_time::WeatherState, mmolib::world_time::WeatherRule>,
+    rules: Arc<RwLock<mmolib::world_rules::WorldRules>>,
+    pathfinder: pathfinding::Pathfinder,
     raws: mmolib::raws::RawTree,
-    tick: u64,
+    tick: AtomicU64,
     write_pipeline : Arc<RwLock<redis::Pipeline>>
 }
 
@@ -67,23 +163,224 @@ impl ServerWorld {
     ) -> Result<ServerWorldRef, ServerWorldError> {
         let x = redis::Client::open(get_redis_connection_string(connection_url, 6379))
             .map_err(|e| ServerWorldError::RedisError(e))?;
+        let raws = mmolib::raws::RawTree::new(raw_path);
+        let block_types = raws
+            .search_for_all(&["block"])
+            .into_iter()
+            .filter_map(|raw| mmolib::block_type::BlockType::new(raw).ok())
+            .map(|block_type| (block_type.get_id(), block_type))
+            .collect();
+        let item_types = raws
+            .search_for_all(&["item"])
+            .into_iter()
+            .filter_map(|raw| mmolib::item::ItemType::new(raw).ok())
+            .map(|item_type| (item_type.get_id(), item_type))
+            .collect();
+        let effect_rules = raws
+            .search_for_all(&["effect"])
+            .into_iter()
+            .filter_map(|raw| mmolib::effect::EffectRule::new(raw).ok())
+            .map(|rule| (rule.get_effect(), rule))
+            .collect();
+        let classes = raws
+            .search_for_all(&["class"])
+            .into_iter()
+            .filter_map(|raw| mmolib::character::CharacterClass::new(raw).ok())
+            .map(|class| (class.get_canonical_name().to_owned(), class))
+            .collect();
+        let races = raws
+            .search_for_all(&["race"])
+            .into_iter()
+            .filter_map(|raw| mmolib::character::Race::new(raw).ok())
+            .map(|race| (race.get_canonical_name().to_owned(), race))
+            .collect();
+        let behavior_trees = raws
+            .search_for_all(&["ai"])
+            .into_iter()
+            .filter_map(|raw| mmolib::ai::BehaviorTree::new(raw).ok())
+            .map(|tree| (tree.get_canonical_name().to_owned(), tree))
+            .collect();
+        let loot_tables = raws
+            .search_for_all(&["loot"])
+            .into_iter()
+            .filter_map(|raw| mmolib::loot::LootTable::new(raw).ok())
+            .map(|table| (table.get_canonical_name().to_owned(), table))
+            .collect();
+        let creatures = raws
+            .search_for_all(&["creature"])
+            .into_iter()
+            .filter_map(|raw| mmolib::ai::Creature::new(raw).ok())
+            .map(|creature| (creature.get_canonical_name().to_owned(), creature))
+            .collect();
+        let spawn_tables = raws
+            .search_for_all(&["spawn_table"])
+            .into_iter()
+            .filter_map(|raw| mmolib::ai::SpawnTable::new(raw).ok())
+            .map(|table| (table.get_canonical_name().to_owned(), table))
+            .collect();
+        let recipes = raws
+            .search_for_all(&["recipe"])
+            .into_iter()
+            .filter_map(|raw| mmolib::recipe::Recipe::new(raw).ok())
+            .map(|recipe| (recipe.get_canonical_name().to_owned(), recipe))
+            .collect();
+        let quests = raws
+            .search_for_all(&["quest"])
+            .into_iter()
+            .filter_map(|raw| mmolib::quest::Quest::new(raw).ok())
+            .map(|quest| (quest.get_canonical_name().to_owned(), quest))
+            .collect();
+        let shops = raws
+            .search_for_all(&["shop"])
+            .into_iter()
+            .filter_map(|raw| mmolib::shop::Shop::new(raw).ok())
+            .map(|shop| (shop.get_canonical_name().to_owned(), shop))
+            .collect();
+        let word_filters = raws
+            .search_for_all(&["filter"])
+            .into_iter()
+            .filter_map(|raw| mmolib::moderation::WordFilter::new(raw).ok())
+            .collect();
+        let vendor_placements = raws
+            .search_for_all(&["vendor"])
+            .into_iter()
+            .filter_map(|raw| mmolib::shop::VendorPlacement::new(raw).ok())
+            .collect();
+        let chest_placements = raws
+            .search_for_all(&["chest"])
+            .into_iter()
+            .filter_map(|raw| mmolib::loot::ChestPlacement::new(raw).ok())
+            .collect();
+        let spawn_point = raws
+            .search(&["world".to_owned(), "spawn".to_owned()])
+            .and_then(|raw| raw.get::<mmolib::position::Position>())
+            .unwrap_or(mmolib::position::Position::new(0, 0));
+        let level_curve = raws
+            .search(&["world".to_owned(), "levels".to_owned()])
+            .and_then(|raw| mmolib::stats::LevelCurve::new(raw).ok())
+            .unwrap_or_default();
+        let safe_zones = raws
+            .search_for_all(&["zone"])
+            .into_iter()
+            .filter_map(|raw| mmolib::world_rules::SafeZone::new(raw).ok())
+            .collect();
+        let build_zones = raws
+            .search_for_all(&["build_zone"])
+            .into_iter()
+            .filter_map(|raw| mmolib::world_rules::BuildZone::new(raw).ok())
+            .collect();
+        let regions = raws
+            .search_for_all(&["region"])
+            .into_iter()
+            .filter_map(|raw| mmolib::world_time::Region::new(raw).ok())
+            .collect();
+        let weather_rules = raws
+            .search_for_all(&["weather"])
+            .into_iter()
+            .filter_map(|raw| mmolib::world_time::WeatherRule::new(raw).ok())
+            .map(|rule| (rule.get_weather(), rule))
+            .collect();
+        let conn = x
+            .get_multiplexed_tokio_connection()
+            .await
+            .map_err(|e| ServerWorldError::RedisError(e))?;
+        //ticks are stored in components, so the count carries on where the last run stopped
+        let tick = conn
+            .clone()
+            .get::<String, Option<u64>>(format!("{}:tick", world_name))
+            .await
+            .map_err(|e| ServerWorldError::RedisError(e))?
+            .unwrap_or(0);
+        let mut burning = HashMap::new();
+        for (position, block) in conn
+            .clone()
+            .hgetall::<String, std::collections::HashMap<String, String>>(format!("{}:burning", world_name))
+            .await
+            .map_err(|e| ServerWorldError::RedisError(e))?
+        {
+            burning.insert(
+                serde_json::from_str(&position).map_err(|e| ServerWorldError::SerdeError(e))?,
+                serde_json::from_str(&block).map_err(|e| ServerWorldError::SerdeError(e))?,
+            );
+        }
+        //worlds opened without being created, like the test worlds, get the defaults
+        let rules = match conn
+            .clone()
+            .get::<String, Option<String>>(format!("{}:rules", world_name))
+            .await
+            .map_err(|e| ServerWorldError::RedisError(e))?
+        {
+            Some(rules) => serde_json::from_str(&rules).map_err(|e| ServerWorldError::SerdeError(e))?,
+            None => mmolib::world_rules::WorldRules::default(),
+        };
         Ok(ServerWorldRef { world : Arc::new(ServerWorld {
-            conn: x
-                .get_multiplexed_tokio_connection()
-                .await
-                .map_err(|e| ServerWorldError::RedisError(e))?,
+            conn,
             world_name: world_name.to_owned(),
             changes: Arc::new(RwLock::new(HashMap::new())),
-            raws: mmolib::raws::RawTree::new(raw_path),
-            tick: 0,
+            cached_chunks: Arc::new(RwLock::new(HashMap::new())),
+            block_updates: Arc::new(RwLock::new(Vec::new())),
+            fluid_queue: Arc::new(RwLock::new(Vec::new())),
+            burning: Arc::new(RwLock::new(burning)),
+            trades: Arc::new(RwLock::new(Vec::new())),
+            economy_lock: Arc::new(tokio::sync::Mutex::new(())),
+            parties: Arc::new(RwLock::new(Vec::new())),
+            party_lock: Arc::new(tokio::sync::Mutex::new(())),
+            //party chat history is kept by id, so ids must not repeat after a restart
+            next_party_id: AtomicU64::new(
+                std::time::SystemTime::now()
+                    .duration_since(std::time::UNIX_EPOCH)
+                    .map_or(1, |d| d.as_millis() as u64),
+            ),
+            party_invites: Arc::new(RwLock::new(Vec::new())),
+            cached_guilds: Arc::new(RwLock::new(HashMap::new())),
+            guild_invites: Arc::new(RwLock::new(Vec::new())),
+            guild_lock: Arc::new(tokio::sync::Mutex::new(())),
+            online: Arc::new(RwLock::new(HashSet::new())),
+            seen_entities: Arc::new(RwLock::new(HashMap::new())),
+            chat_limits: Arc::new(RwLock::new(HashMap::new())),
+            report_limits: Arc::new(RwLock::new(HashMap::new())),
+            received_chat: Arc::new(RwLock::new(HashMap::new())),
+            chat_outbox: Arc::new(RwLock::new(Vec::new())),
+            events: Arc::new(RwLock::new(Vec::new())),
+            block_types,
+            item_types,
+            effect_rules,
+            classes,
+            races,
+            spawn_point,
+            level_curve,
+            behavior_trees,
+            loot_tables,
+            creatures,
+            spawn_tables,
+            recipes,
+            quests,
+            shops,
+            word_filters,
+            vendor_placements,
+            chest_placements,
+            safe_zones,
+            build_zones,
+            regions,
+            weather_rules,
+            rules: Arc::new(RwLock::new(rules)),
+            pathfinder: pathfinding::Pathfinder::new(),
+            raws,
+            tick: AtomicU64::new(tick),
             cached_queries: Arc::new(RwLock::new(HashMap::new())),
             cached_components: Arc::new(RwLock::new(HashMap::new())),
-            write_pipeline: Arc::new(RwLock::new(redis::Pipeline::new()))
+            removed_components: Arc::new(RwLock::new(HashSet::new())),
+            //a tick's writes land all at once or not at all, so nothing is half moved between entities
+            write_pipeline: Arc::new(RwLock::new(redis::Pipeline::new().atomic().to_owned()))
         }) } )
     }
     pub async fn write_all_changes(&self) -> Result<(), ServerWorldError> {
         let mut pipeline = self.write_pipeline.write().await;
+        pipeline.set(format!("{}:tick", self.world_name), self.get_tick());
         pipeline.ignore().query_async(&mut self.conn.clone()).await.map_err(|e| ServerWorldError::RedisError(e))?;
+        pipeline.clear();
+        //redis has caught up with the removals, still holding the pipeline so none queued since are forgotten
+        self.removed_components.write().await.clear();
         Ok(())
     }
     pub async fn write_component<T: mmolib::component::ComponentType + 'static>(
@@ -112,55 +409,179 @@ impl ServerWorld {
             mmolib::component::get_type_id::<T>().get_number(),
         );
         conn.sadd(component_entity_key, entity_id.id());
+        self.removed_components
+            .write()
+            .await
+            .remove(&mmolib::component::ComponentInstanceId::new::<T>(entity_id));
         Ok(())
     }
     async fn delete_component<T: mmolib::component::ComponentType + 'static>(
         &self,
         entity_id: mmolib::entity_id::EntityId,
     ) -> Result<(), ServerWorldError> {
-        let mut conn = self.conn.clone();
+        self.delete_component_by_type_id(entity_id, mmolib::component::get_type_id::<T>())
+            .await
+    }
+    //deletes go through the write pipeline so they can't be overtaken by queued writes
+    async fn delete_component_by_type_id(
+        &self,
+        entity_id: mmolib::entity_id::EntityId,
+        component_type_id: mmolib::component::ComponentTypeId,
+    ) -> Result<(), ServerWorldError> {
+        let mut conn = self.write_pipeline.write().await;
         let component_data_key = format!(
             "{}:{}:{}",
             self.world_name,
             entity_id.id(),
-            mmolib::component::get_type_id::<T>().get_number()
+            component_type_id.get_number()
         );
         let entity_key = format!("{}:{}", self.world_name, entity_id.id());
         let component_entity_key = format!(
             "{}:{}",
             self.world_name,
-            mmolib::component::get_type_id::<T>().get_number()
+            component_type_id.get_number()
         );
-        conn.del(component_data_key)
+        conn.del(component_data_key);
+        conn.srem(entity_key, component_type_id.get_number());
+        conn.srem(component_entity_key, entity_id.id());
+        //until the pipeline runs redis still has it, so reads must not fall through to it
+        self.removed_components
+            .write()
             .await
-            .map_err(|e| ServerWorldError::RedisError(e))?;
-        conn.srem(
-            entity_key,
-            mmolib::component::get_type_id::<T>().get_number(),
-        )
-        .await
-        .map_err(|e| ServerWorldError::RedisError(e))?;
-        conn.srem(component_entity_key, entity_id.id())
+            .insert(mmolib::component::ComponentInstanceId::new_explicit(
+                entity_id,
+                component_type_id,
+            ));
+        Ok(())
+    }
+    /**
+     * Remove every component of an entity
+     */
+    pub async fn despawn_entity(
+        &self,
+        entity_id: mmolib::entity_id::EntityId,
+    ) -> Result<(), ServerWorldError> {
+        let entity_key = format!("{}:{}", self.world_name, entity_id.id());
+        //components written this tick may not have reached redis yet, so also look in the cache
+        let mut component_type_ids: HashSet<mmolib::component::ComponentTypeId> = self
+            .conn
+            .clone()
+            .smembers::<&str, Vec<u64>>(&entity_key)
             .await
-            .map_err(|e| ServerWorldError::RedisError(e))?;
+            .map_err(|e| ServerWorldError::RedisError(e))?
+            .into_iter()
+            .map(mmolib::component::ComponentTypeId::new_with_number)
+            .collect();
+        component_type_ids.extend(
+            self.cached_components
+                .read()
+                .await
+                .keys()
+                .filter(|id| id.get_entity_id() == entity_id)
+                .map(|id| id.get_component_type_id()),
+        );
+        for component_type_id in component_type_ids {
+            self.delete_component_by_type_id(entity_id, component_type_id)
+                .await?;
+            self.record_change(ChangeTracker::new_remove(entity_id, component_type_id))
+                .await;
+        }
+        //evicted only once the removals are marked, so a concurrent read can't cache them again
+        self.cached_components
+            .write()
+            .await
+            .retain(|id, _| id.get_entity_id() != entity_id);
         Ok(())
     }
+    /**
+     * Every component an entity has, as updates adding it, for clients that haven't been sent it before
+     */
+    pub async fn snapshot_entity(
+        &self,
+        entity_id: mmolib::entity_id::EntityId,
+    ) -> Result<Vec<mmolib::server_response_type::ComponentUpdate>, ServerWorldError> {
+        let entity_key = format!("{}:{}", self.world_name, entity_id.id());
+        //like despawning, components written this tick are only in the cache so far
+        let mut component_type_ids: HashSet<mmolib::component::ComponentTypeId> = self
+            .conn
+            .clone()
+            .smembers::<&str, Vec<u64>>(&entity_key)
+            .await
+            .map_err(|e| ServerWorldError::RedisError(e))?
+            .into_iter()
+            .map(mmolib::component::ComponentTypeId::new_with_number)
+            .collect();
+        component_type_ids.extend(
+            self.cached_components
+                .read()
+                .await
+                .keys()
+                .filter(|id| id.get_entity_id() == entity_id)
+                .map(|id| id.get_component_type_id()),
+        );
+        let mut res = Vec::new();
+        for component_type_id in component_type_ids {
+            let id = mmolib::component::ComponentInstanceId::new_explicit(entity_id, component_type_id);
+            if self.removed_components.read().await.contains(&id) {
+                continue;
+            }
+            let cached = self.cached_components.read().await.get(&id).map(|c| c.to_value());
+            let packet = match cached {
+                Some(packet) => packet,
+                None => {
+                    let dat = self
+                        .conn
+                        .clone()
+                        .get::<String, Option<String>>(format!(
+                            "{}:{}:{}",
+                            self.world_name,
+                            entity_id.id(),
+                            component_type_id.get_number()
+                        ))
+                        .await
+                        .map_err(|e| ServerWorldError::RedisError(e))?;
+                    match dat {
+                        Some(dat) => serde_json::from_str(&dat).map_err(|e| ServerWorldError::SerdeError(e))?,
+                        None => continue,
+                    }
+                }
+            };
+            res.push(mmolib::server_response_type::ComponentUpdate::new(
+                entity_id,
+                component_type_id,
+                mmolib::server_response_type::ComponentUpdateType::Added { packet },
+            ));
+        }
+        Ok(res)
+    }
     pub async fn get_entities_with_component_type_ids(
         &self,
         component_type_ids: impl IntoIterator<Item = mmolib::component::ComponentTypeId>,
     ) -> Result<HashSet<mmolib::entity_id::EntityId>, ServerWorldError> {
         let mut conn = self.conn.clone();
-        Ok(conn
+        let component_type_ids: Vec<_> = component_type_ids.into_iter().collect();
+        let entities = conn
             .sinter::<Vec<String>, Vec<String>>(
                 component_type_ids
-                    .into_iter()
+                    .iter()
                     .map(|x| format!("{}:{}", self.world_name, x.get_number()))
                     .collect::<Vec<String>>(),
             )
             .await
-            .map_err(|e| ServerWorldError::RedisError(e))?
+            .map_err(|e| ServerWorldError::RedisError(e))?;
+        //leave out entities that lost one of the components since the last write
+        let removed = self.removed_components.read().await;
+        Ok(entities
             .iter()
             .map(|x| mmolib::entity_id::EntityId::new_with_number(x.parse::<u64>().unwrap()))
+            .filter(|entity_id| {
+                !component_type_ids.iter().any(|component_type_id| {
+                    removed.contains(&mmolib::component::ComponentInstanceId::new_explicit(
+                        *entity_id,
+                        *component_type_id,
+                    ))
+                })
+            })
             .collect())
     }
     async fn get_component<T: mmolib::component::ComponentType + 'static>(
@@ -203,6 +624,10 @@ impl ServerWorld {
             let id = mmolib::component::ComponentInstanceId::new::<T>(entity_id);
             //insert it into the cache
             let mut cache = self.cached_components.write().await;
+            //removed but not yet written, redis is behind
+            if self.removed_components.read().await.contains(&id) {
+                return Err(ServerWorldError::ComponentNotFound);
+            }
             cache.insert(id, mmolib::component::Component::new(component));
             //return it
             Ok(cache.get(&id).unwrap().get_ref::<T>().unwrap())
@@ -213,6 +638,1004 @@ impl ServerWorld {
         }
         
     }
+    pub fn get_world_name(&self) -> &str {
+        &self.world_name
+    }
+    pub fn get_tick(&self) -> u64 {
+        self.tick.load(Ordering::SeqCst)
+    }
+    pub fn advance_tick(&self) -> u64 {
+        self.tick.fetch_add(1, Ordering::SeqCst) + 1
+    }
+    async fn record_change(&self, change: ChangeTracker) {
+        let id = mmolib::component::ComponentInstanceId::new_explicit(
+            change.get_entity_id(),
+            change.get_component_type(),
+        );
+        let mut changes = self.changes.write().await;
+        //a component added and changed within the same tick is still new to clients
+        let change = match (changes.get(&id).map(|c| c.get_change_type()), change.get_change_type()) {
+            (Some(ChangeType::Add(_)), ChangeType::Change(c)) => {
+                ChangeTracker::new_add_component(change.get_entity_id(), c.clone())
+            }
+            _ => change,
+        };
+        changes.insert(id, change);
+    }
+    /**
+     * Add or replace a component, persist it and replicate it to clients on the next tick
+     */
+    pub async fn set_component<T: mmolib::component::ComponentType + 'static>(
+        &self,
+        entity_id: mmolib::entity_id::EntityId,
+        component: T,
+    ) -> Result<(), ServerWorldError> {
+        let exists = self.has_component::<T>(entity_id).await?;
+        self.write_component(entity_id, &component).await?;
+        let id = mmolib::component::ComponentInstanceId::new::<T>(entity_id);
+        self.cached_components
+            .write()
+            .await
+            .insert(id, mmolib::component::Component::new(component.clone()));
+        self.record_change(if exists {
+            ChangeTracker::new_change(entity_id, component)
+        } else {
+            ChangeTracker::new_add(entity_id, component)
+        })
+        .await;
+        Ok(())
+    }
+    /**
+     * Remove a component if the entity has it
+     */
+    pub async fn remove_component<T: mmolib::component::ComponentType + 'static>(
+        &self,
+        entity_id: mmolib::entity_id::EntityId,
+    ) -> Result<(), ServerWorldError> {
+        if !self.has_component::<T>(entity_id).await? {
+            return Ok(());
+        }
+        self.delete_component::<T>(entity_id).await?;
+        self.cached_components
+            .write()
+            .await
+            .remove(&mmolib::component::ComponentInstanceId::new::<T>(entity_id));
+        self.record_change(ChangeTracker::new_remove(
+            entity_id,
+            mmolib::component::get_type_id::<T>(),
+        ))
+        .await;
+        Ok(())
+    }
+    /**
+     * A copy of a component, or None if the entity doesn't have it
+     */
+    pub async fn get_optional_component<T: mmolib::component::ComponentType + 'static>(
+        &self,
+        entity_id: mmolib::entity_id::EntityId,
+    ) -> Result<Option<T>, ServerWorldError> {
+        match self.get_component_ref::<T>(entity_id).await {
+            Ok(component) => Ok(Some((*component).clone())),
+            Err(ServerWorldError::ComponentNotFound) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+    pub async fn has_component<T: mmolib::component::ComponentType + 'static>(
+        &self,
+        entity_id: mmolib::entity_id::EntityId,
+    ) -> Result<bool, ServerWorldError> {
+        match self.get_component_ref::<T>(entity_id).await {
+            Ok(_) => Ok(true),
+            Err(ServerWorldError::ComponentNotFound) => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
+    /**
+     * Resend a component to clients even though it did not change, e.g. to correct a client prediction
+     */
+    pub async fn mark_changed<T: mmolib::component::ComponentType + 'static>(
+        &self,
+        entity_id: mmolib::entity_id::EntityId,
+    ) -> Result<(), ServerWorldError> {
+        let component = (*self.get_component_ref::<T>(entity_id).await?).clone();
+        self.record_change(ChangeTracker::new_change(entity_id, component))
+            .await;
+        Ok(())
+    }
+    pub async fn get_pending_changes(&self) -> Vec<mmolib::server_response_type::ComponentUpdate> {
+        self.changes
+            .read()
+            .await
+            .values()
+            .map(|c| c.to_component_update())
+            .collect()
+    }
+    pub async fn clear_changes(&self) {
+        self.changes.write().await.clear();
+    }
+    pub fn get_block_type(
+        &self,
+        block_type_id: mmolib::block_type::BlockTypeId,
+    ) -> Option<&mmolib::block_type::BlockType> {
+        self.block_types.get(&block_type_id)
+    }
+    pub fn get_item_type(
+        &self,
+        item_type_id: mmolib::item::ItemTypeId,
+    ) -> Option<&mmolib::item::ItemType> {
+        self.item_types.get(&item_type_id)
+    }
+    /**
+     * How an effect behaves, falling back to refreshing every tick when the raws don't say
+     */
+    pub fn get_effect_rule(&self, effect: mmolib::effect::Effect) -> mmolib::effect::EffectRule {
+        self.effect_rules
+            .get(&effect)
+            .cloned()
+            .unwrap_or_else(|| mmolib::effect::EffectRule::new_default(effect))
+    }
+    pub fn get_class(&self, canonical_name: &str) -> Option<&mmolib::character::CharacterClass> {
+        self.classes.get(canonical_name)
+    }
+    pub fn get_race(&self, canonical_name: &str) -> Option<&mmolib::character::Race> {
+        self.races.get(canonical_name)
+    }
+    pub fn get_behavior_tree(&self, canonical_name: &str) -> Option<&mmolib::ai::BehaviorTree> {
+        self.behavior_trees.get(canonical_name)
+    }
+    pub fn get_loot_tables(&self) -> &std::collections::HashMap<String, mmolib::loot::LootTable> {
+        &self.loot_tables
+    }
+    pub fn get_creature(&self, canonical_name: &str) -> Option<&mmolib::ai::Creature> {
+        self.creatures.get(canonical_name)
+    }
+    pub fn get_spawn_table(&self, canonical_name: &str) -> Option<&mmolib::ai::SpawnTable> {
+        self.spawn_tables.get(canonical_name)
+    }
+    pub fn get_recipe(&self, canonical_name: &str) -> Option<&mmolib::recipe::Recipe> {
+        self.recipes.get(canonical_name)
+    }
+    pub fn get_shop(&self, canonical_name: &str) -> Option<&mmolib::shop::Shop> {
+        self.shops.get(canonical_name)
+    }
+    pub fn get_vendor_placements(&self) -> &[mmolib::shop::VendorPlacement] {
+        &self.vendor_placements
+    }
+    pub fn get_chest_placements(&self) -> &[mmolib::loot::ChestPlacement] {
+        &self.chest_placements
+    }
+    /**
+     * Set up a fresh world with the rules it was created with, None if the world already exists.
+     * The rules are claimed straight away so two creates can't both set the world up
+     */
+    pub async fn create(
+        connection_url: &str,
+        world_name: &str,
+        raw_path: &str,
+        rules: mmolib::world_rules::WorldRules,
+    ) -> Result<Option<ServerWorldRef>, ServerWorldError> {
+        let world = ServerWorld::new(connection_url, world_name, raw_path).await?;
+        let claimed: bool = world
+            .conn
+            .clone()
+            .set_nx(
+                format!("{}:rules", world_name),
+                serde_json::to_string(&rules).map_err(|e| ServerWorldError::SerdeError(e))?,
+            )
+            .await
+            .map_err(|e| ServerWorldError::RedisError(e))?;
+        if !claimed {
+            return Ok(None);
+        }
+        *world.rules.write().await = rules;
+        shop::place_vendors(&world).await?;
+        loot::place_chests(&world).await?;
+        world.write_all_changes().await?;
+        Ok(Some(world))
+    }
+    /**
+     * Open a world that was created before, None if it never was
+     */
+    pub async fn load(
+        connection_url: &str,
+        world_name: &str,
+        raw_path: &str,
+    ) -> Result<Option<ServerWorldRef>, ServerWorldError> {
+        let world = ServerWorld::new(connection_url, world_name, raw_path).await?;
+        let exists: bool = world
+            .conn
+            .clone()
+            .exists(format!("{}:rules", world_name))
+            .await
+            .map_err(|e| ServerWorldError::RedisError(e))?;
+        Ok(if exists { Some(world) } else { None })
+    }
+    pub async fn get_rules(&self) -> mmolib::world_rules::WorldRules {
+        self.rules.read().await.clone()
+    }
+    pub async fn set_rules(
+        &self,
+        rules: mmolib::world_rules::WorldRules,
+    ) -> Result<(), ServerWorldError> {
+        self.write_pipeline.write().await.set(
+            format!("{}:rules", self.world_name),
+            serde_json::to_string(&rules).map_err(|e| ServerWorldError::SerdeError(e))?,
+        );
+        *self.rules.write().await = rules;
+        Ok(())
+    }
+    pub fn is_safe_zone(&self, position: mmolib::chunk::Position) -> bool {
+        self.safe_zones.iter().any(|zone| zone.contains(position))
+    }
+    /**
+     * Whether a player may change a block, only listed builders can inside a build zone
+     */
+    pub fn can_build(&self, username: &str, position: mmolib::chunk::Position) -> bool {
+        self.build_zones
+            .iter()
+            .filter(|zone| zone.contains(position))
+            .all(|zone| zone.allows(username))
+    }
+    pub fn get_regions(&self) -> &[mmolib::world_time::Region] {
+        &self.regions
+    }
+    pub fn get_region_at(
+        &self,
+        position: mmolib::chunk::Position,
+    ) -> Option<&mmolib::world_time::Region> {
+        self.regions.iter().find(|region| region.contains(position))
+    }
+    pub fn get_weather_rule(
+        &self,
+        weather: mmolib::world_time::WeatherState,
+    ) -> Option<&mmolib::world_time::WeatherRule> {
+        self.weather_rules.get(&weather)
+    }
+    pub fn get_level_curve(&self) -> &mmolib::stats::LevelCurve {
+        &self.level_curve
+    }
+    pub fn get_quest(&self, canonical_name: &str) -> Option<&mmolib::quest::Quest> {
+        self.quests.get(canonical_name)
+    }
+    pub fn get_spawn_point(&self) -> mmolib::position::Position {
+        self.spawn_point.clone()
+    }
+    /**
+     * Claim a character name for this world, false if someone already has it
+     */
+    pub async fn reserve_character_name(&self, name: &str) -> Result<bool, ServerWorldError> {
+        let added: u32 = self
+            .conn
+            .clone()
+            .sadd(
+                format!("{}:character_names", self.world_name),
+                name.to_lowercase(),
+            )
+            .await
+            .map_err(|e| ServerWorldError::RedisError(e))?;
+        Ok(added == 1)
+    }
+    /**
+     * Give up a reserved character name, e.g. when creating the character failed
+     */
+    pub async fn release_character_name(&self, name: &str) -> Result<(), ServerWorldError> {
+        self.conn
+            .clone()
+            .srem(
+                format!("{}:character_names", self.world_name),
+                name.to_lowercase(),
+            )
+            .await
+            .map_err(|e| ServerWorldError::RedisError(e))
+    }
+    /**
+     * Tie an account to its character, false if it already has one.
+     * Written straight away rather than with the tick so two spawns can't both claim the account
+     */
+    pub async fn link_account(
+        &self,
+        username: &str,
+        entity_id: mmolib::entity_id::EntityId,
+    ) -> Result<bool, ServerWorldError> {
+        self.conn
+            .clone()
+            .set_nx(format!("{}:account:{}", self.world_name, username), entity_id.id())
+            .await
+            .map_err(|e| ServerWorldError::RedisError(e))
+    }
+    pub async fn unlink_account(&self, username: &str) -> Result<(), ServerWorldError> {
+        self.conn
+            .clone()
+            .del(format!("{}:account:{}", self.world_name, username))
+            .await
+            .map_err(|e| ServerWorldError::RedisError(e))
+    }
+    /**
+     * The character entity an account plays in this world
+     */
+    pub async fn get_account_entity(
+        &self,
+        username: &str,
+    ) -> Result<Option<mmolib::entity_id::EntityId>, ServerWorldError> {
+        Ok(self
+            .conn
+            .clone()
+            .get::<String, Option<u64>>(format!("{}:account:{}", self.world_name, username))
+            .await
+            .map_err(|e| ServerWorldError::RedisError(e))?
+            .map(mmolib::entity_id::EntityId::new_with_number))
+    }
+    pub fn get_pathfinder(&self) -> &pathfinding::Pathfinder {
+        &self.pathfinder
+    }
+    fn chunk_key(&self, chunk_id: mmolib::chunk::ChunkId) -> String {
+        format!("{}:chunk:{}", self.world_name, chunk_id.id())
+    }
+    async fn load_chunk(
+        &self,
+        chunk_id: mmolib::chunk::ChunkId,
+    ) -> Result<mmolib::chunk::Chunk, ServerWorldError> {
+        let dat = self
+            .conn
+            .clone()
+            .get::<String, Option<Vec<u8>>>(self.chunk_key(chunk_id))
+            .await
+            .map_err(|e| ServerWorldError::RedisError(e))?
+            .ok_or(ServerWorldError::ChunkNotFound(chunk_id))?;
+        mmolib::chunk::Chunk::new(&dat).map_err(|e| ServerWorldError::ChunkSerdeError(e))
+    }
+    pub async fn get_chunk(
+        &self,
+        chunk_id: mmolib::chunk::ChunkId,
+    ) -> Result<mmolib::chunk::Chunk, ServerWorldError> {
+        if let Some(chunk) = self.cached_chunks.read().await.get(&chunk_id) {
+            return Ok(chunk.clone());
+        }
+        let chunk = self.load_chunk(chunk_id).await?;
+        self.cached_chunks
+            .write()
+            .await
+            .insert(chunk_id, chunk.clone());
+        Ok(chunk)
+    }
+    /**
+     * Store a whole chunk, e.g. from world generation
+     */
+    pub async fn insert_chunk(
+        &self,
+        chunk_id: mmolib::chunk::ChunkId,
+        chunk: mmolib::chunk::Chunk,
+    ) -> Result<(), ServerWorldError> {
+        let dat = chunk
+            .to_bytes()
+            .map_err(|e| ServerWorldError::ChunkSerdeError(e))?;
+        self.write_pipeline
+            .write()
+            .await
+            .set(self.chunk_key(chunk_id), dat);
+        self.cached_chunks.write().await.insert(chunk_id, chunk);
+        Ok(())
+    }
+    pub async fn snapshot_area(
+        &self,
+        area: (mmolib::chunk::Position, mmolib::chunk::Position),
+    ) -> Result<AreaSnapshot<'_>, ServerWorldError> {
+        let mut chunks = HashMap::new();
+        let step = mmolib::chunk::CHUNK_SIZE as u32;
+        for x in (area.0 .0 / step)..=(area.1 .0 / step) {
+            for y in (area.0 .1 / step)..=(area.1 .1 / step) {
+                let chunk_id = mmolib::chunk::chunk_id_from_position((x * step, y * step));
+                match self.get_chunk(chunk_id).await {
+                    Ok(c) => {
+                        chunks.insert(chunk_id, c);
+                    }
+                    Err(ServerWorldError::ChunkNotFound(_)) => {}
+                    Err(e) => return Err(e),
+                }
+            }
+        }
+        Ok(AreaSnapshot {
+            world: self,
+            area,
+            chunks,
+        })
+    }
+    pub async fn get_block(
+        &self,
+        position: mmolib::chunk::Position,
+    ) -> Result<mmolib::block_type::BlockTypeId, ServerWorldError> {
+        let chunk = self
+            .get_chunk(mmolib::chunk::chunk_id_from_position(position))
+            .await?;
+        Ok(chunk.get_block(mmolib::chunk::convert_to_chunk_relative_position(position)))
+    }
+    /**
+     * Change a single block, persist its chunk and queue a block update for clients
+     */
+    pub async fn set_block(
+        &self,
+        position: mmolib::chunk::Position,
+        block_type_id: mmolib::block_type::BlockTypeId,
+    ) -> Result<(), ServerWorldError> {
+        self.replace_block(position, None, block_type_id).await?;
+        Ok(())
+    }
+    /**
+     * Change a block only if it still is the expected one, false and nothing changed otherwise.
+     * The chunk stays locked from the check until its write is queued, so concurrent edits can't overwrite each other
+     */
+    pub async fn replace_block(
+        &self,
+        position: mmolib::chunk::Position,
+        expected: Option<mmolib::block_type::BlockTypeId>,
+        block_type_id: mmolib::block_type::BlockTypeId,
+    ) -> Result<bool, ServerWorldError> {
+        let chunk_id = mmolib::chunk::chunk_id_from_position(position);
+        let relative_position = mmolib::chunk::convert_to_chunk_relative_position(position);
+        //make sure the chunk is cached before locking it
+        self.get_chunk(chunk_id).await?;
+        {
+            let mut chunks = self.cached_chunks.write().await;
+            let chunk = chunks
+                .get_mut(&chunk_id)
+                .ok_or(ServerWorldError::ChunkNotFound(chunk_id))?;
+            if expected.map_or(false, |expected| chunk.get_block(relative_position) != expected) {
+                return Ok(false);
+            }
+            chunk.set_block(relative_position, block_type_id);
+            let dat = chunk
+                .to_bytes()
+                .map_err(|e| ServerWorldError::ChunkSerdeError(e))?;
+            self.write_pipeline
+                .write()
+                .await
+                .set(self.chunk_key(chunk_id), dat);
+        }
+        self.pathfinder.invalidate(position).await;
+        //water around the block may need to flow in or drain away
+        self.queue_fluid_update(position).await;
+        self.block_updates
+            .write()
+            .await
+            .push(mmolib::server_response_type::BlockUpdate::new(position, block_type_id));
+        Ok(true)
+    }
+    /**
+     * A block, but only if its chunk is already in memory
+     */
+    pub async fn get_loaded_block(
+        &self,
+        position: mmolib::chunk::Position,
+    ) -> Option<mmolib::block_type::BlockTypeId> {
+        self.cached_chunks
+            .read()
+            .await
+            .get(&mmolib::chunk::chunk_id_from_position(position))
+            .map(|chunk| chunk.get_block(mmolib::chunk::convert_to_chunk_relative_position(position)))
+    }
+    /**
+     * Schedule a block and its neighbours for the fluid simulation
+     */
+    pub async fn queue_fluid_update(&self, position: mmolib::chunk::Position) {
+        let mut queue = self.fluid_queue.write().await;
+        queue.push(position);
+        for direction in mmolib::server_request_type::Direction::CARDINAL {
+            if let Some(neighbour) = direction.step(position) {
+                queue.push(neighbour);
+            }
+        }
+    }
+    /**
+     * Take up to limit distinct blocks waiting on the fluid simulation, leaving the rest queued
+     */
+    pub async fn take_fluid_updates(&self, limit: usize) -> Vec<mmolib::chunk::Position> {
+        let mut queue = self.fluid_queue.write().await;
+        let mut seen = HashSet::new();
+        let mut taken = Vec::new();
+        let mut rest = Vec::new();
+        for position in queue.drain(..) {
+            if !seen.insert(position) {
+                continue;
+            }
+            if taken.len() < limit {
+                taken.push(position);
+            } else {
+                rest.push(position);
+            }
+        }
+        *queue = rest;
+        taken
+    }
+    /**
+     * The trade an entity is part of, if any
+     */
+    pub async fn get_trade(
+        &self,
+        entity_id: mmolib::entity_id::EntityId,
+    ) -> Option<mmolib::trade::Trade> {
+        self.trades
+            .read()
+            .await
+            .iter()
+            .find(|t| t.involves(entity_id))
+            .cloned()
+    }
+    pub async fn get_trades(&self) -> Vec<mmolib::trade::Trade> {
+        self.trades.read().await.clone()
+    }
+    /**
+     * Store a trade, replacing the one between the same two entities
+     */
+    pub async fn put_trade(&self, trade: mmolib::trade::Trade) {
+        let mut trades = self.trades.write().await;
+        trades.retain(|t| !t.involves(trade.initiator.entity));
+        trades.push(trade);
+    }
+    /**
+     * Remove the trade an entity is part of. Only one caller can ever get a given trade back
+     */
+    pub async fn take_trade(
+        &self,
+        entity_id: mmolib::entity_id::EntityId,
+    ) -> Option<mmolib::trade::Trade> {
+        let mut trades = self.trades.write().await;
+        let index = trades.iter().position(|t| t.involves(entity_id))?;
+        Some(trades.remove(index))
+    }
+    /**
+     * Hold every trade for a change that has to see and update a trade in one go.
+     * Anything checking whether an item is in a trade waits until the guard is dropped
+     */
+    pub async fn lock_trades(
+        &self,
+    ) -> tokio::sync::RwLockWriteGuard<'_, Vec<mmolib::trade::Trade>> {
+        self.trades.write().await
+    }
+    /**
+     * Hold off every other change of who owns what, for one that reads items or coins and writes them back
+     */
+    pub async fn lock_economy(&self) -> tokio::sync::MutexGuard<'_, ()> {
+        self.economy_lock.lock().await
+    }
+    /**
+     * Whether an item is on offer in an open trade and so can't be moved
+     */
+    pub async fn is_item_in_trade(&self, item: mmolib::entity_id::EntityId) -> bool {
+        self.trades.read().await.iter().any(|t| t.includes_item(item))
+    }
+    /**
+     * The party an entity is in, if any
+     */
+    pub async fn get_party(
+        &self,
+        entity_id: mmolib::entity_id::EntityId,
+    ) -> Option<mmolib::group::Party> {
+        self.parties
+            .read()
+            .await
+            .iter()
+            .find(|p| p.is_member(entity_id))
+            .cloned()
+    }
+    /**
+     * Start a party led by an entity
+     */
+    pub async fn create_party(&self, leader: mmolib::entity_id::EntityId) -> mmolib::group::Party {
+        let party = mmolib::group::Party::new(
+            self.next_party_id.fetch_add(1, std::sync::atomic::Ordering::Relaxed),
+            leader,
+        );
+        self.parties.write().await.push(party.clone());
+        party
+    }
+    /**
+     * Store a party, dropping it once nobody is left in it
+     */
+    pub async fn put_party(&self, party: mmolib::group::Party) {
+        let mut parties = self.parties.write().await;
+        parties.retain(|p| p.id != party.id);
+        if !party.members.is_empty() {
+            parties.push(party);
+        }
+    }
+    /**
+     * Hold off every other party change, for one that reads a party and stores it back
+     */
+    pub async fn lock_parties(&self) -> tokio::sync::MutexGuard<'_, ()> {
+        self.party_lock.lock().await
+    }
+    pub async fn invite_to_party(
+        &self,
+        inviter: mmolib::entity_id::EntityId,
+        invitee: mmolib::entity_id::EntityId,
+    ) {
+        let mut invites = self.party_invites.write().await;
+        invites.retain(|(_, to)| *to != invitee);
+        invites.push((inviter, invitee));
+    }
+    /**
+     * Remove and return whoever invited an entity to a party
+     */
+    pub async fn take_party_invite(
+        &self,
+        invitee: mmolib::entity_id::EntityId,
+    ) -> Option<mmolib::entity_id::EntityId> {
+        let mut invites = self.party_invites.write().await;
+        let index = invites.iter().position(|(_, to)| *to == invitee)?;
+        Some(invites.remove(index).0)
+    }
+    fn guild_key(&self, name: &str) -> String {
+        format!("{}:guild:{}", self.world_name, name.to_lowercase())
+    }
+    /**
+     * A guild by name, names are not case sensitive
+     */
+    pub async fn get_guild(
+        &self,
+        name: &str,
+    ) -> Result<Option<mmolib::group::Guild>, ServerWorldError> {
+        let key = self.guild_key(name);
+        if let Some(guild) = self.cached_guilds.read().await.get(&key) {
+            return Ok(guild.clone());
+        }
+        let guild = match self
+            .conn
+            .clone()
+            .get::<String, Option<String>>(key.clone())
+            .await
+            .map_err(|e| ServerWorldError::RedisError(e))?
+        {
+            Some(guild) => Some(serde_json::from_str(&guild).map_err(|e| ServerWorldError::SerdeError(e))?),
+            None => None,
+        };
+        self.cached_guilds.write().await.insert(key, guild.clone());
+        Ok(guild)
+    }
+    /**
+     * Hold off every other guild change, for one that reads a guild and writes it back
+     */
+    pub async fn lock_guilds(&self) -> tokio::sync::MutexGuard<'_, ()> {
+        self.guild_lock.lock().await
+    }
+    pub async fn put_guild(&self, guild: mmolib::group::Guild) -> Result<(), ServerWorldError> {
+        let key = self.guild_key(&guild.name);
+        self.write_pipeline.write().await.set(
+            key.clone(),
+            serde_json::to_string(&guild).map_err(|e| ServerWorldError::SerdeError(e))?,
+        );
+        self.cached_guilds.write().await.insert(key, Some(guild));
+        Ok(())
+    }
+    pub async fn remove_guild(&self, name: &str) {
+        let key = self.guild_key(name);
+        self.write_pipeline.write().await.del(key.clone());
+        self.cached_guilds.write().await.insert(key, None);
+        self.guild_invites
+            .write()
+            .await
+            .retain(|(guild, _)| !guild.eq_ignore_ascii_case(name));
+    }
+    pub async fn invite_to_guild(&self, guild: &str, invitee: mmolib::entity_id::EntityId) {
+        let mut invites = self.guild_invites.write().await;
+        invites.retain(|(_, to)| *to != invitee);
+        invites.push((guild.to_owned(), invitee));
+    }
+    /**
+     * Remove and return the guild an entity was invited to
+     */
+    pub async fn take_guild_invite(&self, invitee: mmolib::entity_id::EntityId) -> Option<String> {
+        let mut invites = self.guild_invites.write().await;
+        let index = invites.iter().position(|(_, to)| *to == invitee)?;
+        Some(invites.remove(index).0)
+    }
+    /**
+     * Replace the set of online players, returning the ones that just came online
+     */
+    pub async fn set_online(
+        &self,
+        entities: &[mmolib::entity_id::EntityId],
+    ) -> Vec<mmolib::entity_id::EntityId> {
+        let online: HashSet<_> = entities.iter().copied().collect();
+        //anything kept per session goes once the player leaves
+        self.chat_limits
+            .write()
+            .await
+            .retain(|entity_id, _| online.contains(entity_id));
+        self.received_chat
+            .write()
+            .await
+            .retain(|entity_id, _| online.contains(entity_id));
+        self.seen_entities
+            .write()
+            .await
+            .retain(|entity_id, _| online.contains(entity_id));
+        let mut previous = self.online.write().await;
+        let joined = online.difference(&previous).copied().collect();
+        *previous = online;
+        joined
+    }
+    pub fn get_word_filters(&self) -> &[mmolib::moderation::WordFilter] {
+        &self.word_filters
+    }
+    /**
+     * Use up one of a player's chat messages, false if they are sending too fast
+     */
+    pub async fn allow_chat(&self, entity_id: mmolib::entity_id::EntityId) -> bool {
+        let tick = self.get_tick();
+        self.chat_limits
+            .write()
+            .await
+            .entry(entity_id)
+            .or_insert_with(|| mmolib::moderation::RateLimit::new(tick))
+            .allow(tick)
+    }
+    /**
+     * Remember a line sent to a player, keeping only the most recent
+     */
+    pub async fn record_received_chat(
+        &self,
+        entity_id: mmolib::entity_id::EntityId,
+        line: &mmolib::chat::ChatLine,
+        keep: usize,
+    ) {
+        if !self.is_online(entity_id).await {
+            return;
+        }
+        let mut received = self.received_chat.write().await;
+        let lines = received.entry(entity_id).or_default();
+        lines.push_back(line.clone());
+        while lines.len() > keep {
+            lines.pop_front();
+        }
+    }
+    pub async fn get_received_chat(
+        &self,
+        entity_id: mmolib::entity_id::EntityId,
+    ) -> Vec<mmolib::chat::ChatLine> {
+        self.received_chat
+            .read()
+            .await
+            .get(&entity_id)
+            .map_or(Vec::new(), |lines| lines.iter().cloned().collect())
+    }
+    pub async fn push_report(
+        &self,
+        report: &mmolib::moderation::Report,
+    ) -> Result<(), ServerWorldError> {
+        let key = format!("{}:reports", self.world_name);
+        self.write_pipeline
+            .write()
+            .await
+            .rpush(
+                key.clone(),
+                serde_json::to_string(report).map_err(|e| ServerWorldError::SerdeError(e))?,
+            )
+            .ltrim(key, -(mmolib::moderation::REPORT_CAP as isize), -1);
+        Ok(())
+    }
+    /**
+     * Start a player's wait until their next report, false if they are still waiting
+     */
+    pub async fn allow_report(&self, entity_id: mmolib::entity_id::EntityId) -> bool {
+        let tick = self.get_tick();
+        let mut limits = self.report_limits.write().await;
+        limits.retain(|_, next_tick| *next_tick > tick);
+        if limits.contains_key(&entity_id) {
+            return false;
+        }
+        limits.insert(entity_id, tick + mmolib::moderation::REPORT_COOLDOWN_TICKS);
+        true
+    }
+    pub async fn get_reports(&self) -> Result<Vec<mmolib::moderation::Report>, ServerWorldError> {
+        let reports = self
+            .conn
+            .clone()
+            .lrange::<String, Vec<String>>(format!("{}:reports", self.world_name), 0, -1)
+            .await
+            .map_err(|e| ServerWorldError::RedisError(e))?;
+        reports
+            .iter()
+            .map(|report| serde_json::from_str(report).map_err(|e| ServerWorldError::SerdeError(e)))
+            .collect()
+    }
+    /**
+     * Record which entities a player's client now knows about, returning the ones it knew before
+     */
+    pub async fn swap_seen_entities(
+        &self,
+        viewer: mmolib::entity_id::EntityId,
+        seen: HashSet<mmolib::entity_id::EntityId>,
+    ) -> HashSet<mmolib::entity_id::EntityId> {
+        self.seen_entities
+            .write()
+            .await
+            .insert(viewer, seen)
+            .unwrap_or_default()
+    }
+    pub async fn get_online(&self) -> Vec<mmolib::entity_id::EntityId> {
+        self.online.read().await.iter().copied().collect()
+    }
+    pub async fn is_online(&self, entity_id: mmolib::entity_id::EntityId) -> bool {
+        self.online.read().await.contains(&entity_id)
+    }
+    fn chat_history_key(&self, name: &str) -> String {
+        format!("{}:chat:{}", self.world_name, name)
+    }
+    /**
+     * Add a line to a chat history, dropping the oldest past the cap
+     */
+    pub async fn push_chat_history(
+        &self,
+        name: &str,
+        line: &mmolib::chat::ChatLine,
+    ) -> Result<(), ServerWorldError> {
+        let key = self.chat_history_key(name);
+        self.write_pipeline
+            .write()
+            .await
+            .rpush(
+                key.clone(),
+                serde_json::to_string(line).map_err(|e| ServerWorldError::SerdeError(e))?,
+            )
+            .ltrim(key, -(mmolib::chat::HISTORY_CAP as isize), -1);
+        Ok(())
+    }
+    /**
+     * The kept lines of a chat history, oldest first
+     */
+    pub async fn get_chat_history(
+        &self,
+        name: &str,
+    ) -> Result<Vec<mmolib::chat::ChatLine>, ServerWorldError> {
+        let lines = self
+            .conn
+            .clone()
+            .lrange::<String, Vec<String>>(self.chat_history_key(name), 0, -1)
+            .await
+            .map_err(|e| ServerWorldError::RedisError(e))?;
+        lines
+            .iter()
+            .map(|line| serde_json::from_str(line).map_err(|e| ServerWorldError::SerdeError(e)))
+            .collect()
+    }
+    /**
+     * Queue a message to go out to one player with the next tick
+     */
+    pub async fn send_to(
+        &self,
+        recipient: mmolib::entity_id::EntityId,
+        response: mmolib::server_response_type::ServerResponseType,
+    ) {
+        self.chat_outbox.write().await.push((recipient, response));
+    }
+    pub async fn take_outbox(
+        &self,
+    ) -> Vec<(mmolib::entity_id::EntityId, mmolib::server_response_type::ServerResponseType)> {
+        std::mem::take(&mut *self.chat_outbox.write().await)
+    }
+    /**
+     * Every block currently on fire and what it was before it caught
+     */
+    pub async fn get_burning_blocks(&self) -> Vec<(mmolib::chunk::Position, fire::BurningBlock)> {
+        self.burning
+            .read()
+            .await
+            .iter()
+            .map(|(position, burning)| (*position, burning.clone()))
+            .collect()
+    }
+    pub async fn is_burning(&self, position: mmolib::chunk::Position) -> bool {
+        self.burning.read().await.contains_key(&position)
+    }
+    fn burning_key(&self) -> String {
+        format!("{}:burning", self.world_name)
+    }
+    /**
+     * Mark a block as on fire. Saved with the tick so fires carry on after a restart
+     */
+    pub async fn set_burning(
+        &self,
+        position: mmolib::chunk::Position,
+        burning: fire::BurningBlock,
+    ) -> Result<(), ServerWorldError> {
+        let mut blocks = self.burning.write().await;
+        self.write_pipeline.write().await.hset(
+            self.burning_key(),
+            serde_json::to_string(&position).map_err(|e| ServerWorldError::SerdeError(e))?,
+            serde_json::to_string(&burning).map_err(|e| ServerWorldError::SerdeError(e))?,
+        );
+        blocks.insert(position, burning);
+        Ok(())
+    }
+    pub async fn clear_burning(
+        &self,
+        position: mmolib::chunk::Position,
+    ) -> Result<Option<fire::BurningBlock>, ServerWorldError> {
+        let mut blocks = self.burning.write().await;
+        let burning = blocks.remove(&position);
+        if burning.is_some() {
+            self.write_pipeline.write().await.hdel(
+                self.burning_key(),
+                serde_json::to_string(&position).map_err(|e| ServerWorldError::SerdeError(e))?,
+            );
+        }
+        Ok(burning)
+    }
+    /**
+     * The flowing water block with a given depth. If the raws have several the lowest id wins,
+     * so every run and every server picks the same one
+     */
+    pub fn get_fluid_block(&self, level: u8) -> Option<mmolib::block_type::BlockTypeId> {
+        self.block_types
+            .values()
+            .filter(|b| {
+                matches!(b.get_layer(), mmolib::block_type::BlockLayer::Water)
+                    && !b.is_fluid_source()
+                    && b.get_fluid_level() == level
+            })
+            .map(|b| b.get_id())
+            .min()
+    }
+    /**
+     * Block updates since the last tick within range of a position
+     */
+    pub async fn get_block_updates_in_range(
+        &self,
+        center: mmolib::chunk::Position,
+        range: u32,
+    ) -> Vec<mmolib::server_response_type::BlockUpdate> {
+        self.block_updates
+            .read()
+            .await
+            .iter()
+            .filter(|update| mmolib::chunk::chebyshev_distance(center, update.block_pos) <= range)
+            .cloned()
+            .collect()
+    }
+    pub async fn clear_block_updates(&self) {
+        self.block_updates.write().await.clear();
+    }
+    /**
+     * Queue an event for this tick, located at the block it happened on if it happened somewhere
+     */
+    pub async fn emit_event(
+        &self,
+        location: Option<mmolib::chunk::Position>,
+        event: mmolib::event::GameEvent,
+    ) {
+        self.events.write().await.push((location, event));
+    }
+    pub async fn get_pending_events(
+        &self,
+    ) -> Vec<(Option<mmolib::chunk::Position>, mmolib::event::GameEvent)> {
+        self.events.read().await.clone()
+    }
+    pub async fn clear_events(&self) {
+        self.events.write().await.clear();
+    }
+    /**
+     * All entities whose position lies on the given block
+     */
+    pub async fn get_entities_at(
+        &self,
+        position: mmolib::chunk::Position,
+    ) -> Result<Vec<mmolib::entity_id::EntityId>, ServerWorldError> {
+        let mut res = Vec::new();
+        for entity_id in self
+            .get_entities_with_component_type_ids([
+                mmolib::component::get_type_id::<mmolib::position::Position>(),
+            ])
+            .await?
+        {
+            let entity_position = self
+                .get_component_ref::<mmolib::position::Position>(entity_id)
+                .await?;
+            if entity_position.block_position() == Some(position) {
+                res.push(entity_id);
+            }
+        }
+        Ok(res)
+    }
     pub async fn destroy_world(self) -> Result<(), ServerWorldError> {
         let mut conn = self.conn.clone();
         let keys = conn
@@ -253,4 +1676,51 @@ async fn test_connection() -> () {
 pub async fn create_server() -> Result<(), ServerWorldError> {
     let w = ServerWorld::new("dockercuck.prizrak.me","test","C:\\Users\\justin.suess\\Code\\mmonew\\raws").await?;
     Ok(())
-}
\ No newline at end of file
+}
+
+/**
+ * A world on the test database with the repo's raws, named so tests don't share entities
+ */
+#[cfg(test)]
+pub async fn test_world(name: &str) -> ServerWorldRef {
+    ServerWorld::new("dockercuck.prizrak.me", &format!("test_{}", name), "../raws")
+        .await
+        .expect("could not open test world")
+}
+
+#[tokio::test]
+async fn test_removed_component_stays_removed_until_written() -> Result<(), ServerWorldError> {
+    use mmolib::{component::get_type_id, entity_id::EntityId, position::Position};
+    let world = test_world("removal").await;
+    let removed = EntityId::new();
+    let despawned = EntityId::new();
+    world.set_component(removed, Position::new(1, 2)).await?;
+    world.set_component(despawned, Position::new(3, 4)).await?;
+    world.write_all_changes().await?;
+    world.remove_component::<Position>(removed).await?;
+    world.despawn_entity(despawned).await?;
+    for entity_id in [removed, despawned] {
+        assert!(world.get_optional_component::<Position>(entity_id).await?.is_none());
+        assert!(!world
+            .get_entities_with_component_type_ids([get_type_id::<Position>()])
+            .await?
+            .contains(&entity_id));
+    }
+    world.write_all_changes().await?;
+    assert!(world.get_optional_component::<Position>(removed).await?.is_none());
+    //putting it back within the same tick wins over the removal
+    world.remove_component::<Position>(despawned).await?;
+    world.set_component(despawned, Position::new(5, 6)).await?;
+    world.write_all_changes().await?;
+    assert!(world.get_optional_component::<Position>(despawned).await?.is_some());
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_tick_survives_restart() -> Result<(), ServerWorldError> {
+    let world = test_world("tick").await;
+    let tick = world.advance_tick();
+    world.write_all_changes().await?;
+    assert_eq!(test_world("tick").await.get_tick(), tick);
+    Ok(())
+}
@@ -0,0 +1,292 @@
+use mmolib::{
+    ai::Npc,
+    chunk,
+    combat::Dead,
+    currency::Wallet,
+    entity_id::EntityId,
+    item::{item_type_id, Durability, Inventory, Item},
+    position::Position,
+    server_response_type::ServerResponseType,
+    shop::{Shop, Vendor},
+};
+
+use crate::{
+    inventory, progression,
+    server_world::{ServerWorldError, ServerWorldRef},
+};
+
+//how close a player has to stand to a vendor to deal with it
+pub const VENDOR_REACH: u32 = 3;
+
+/**
+ * Put every vendor from the raws into a freshly created world
+ */
+pub async fn place_vendors(world: &ServerWorldRef) -> Result<(), ServerWorldError> {
+    for placement in world.get_vendor_placements() {
+        let shop = match world.get_shop(placement.get_shop()) {
+            Some(shop) => shop,
+            None => continue,
+        };
+        let vendor_id = EntityId::new();
+        world
+            .set_component(vendor_id, placement.get_position().clone())
+            .await?;
+        world
+            .set_component(
+                vendor_id,
+                Npc {
+                    kind: placement.get_kind().to_owned(),
+                },
+            )
+            .await?;
+        world
+            .set_component(vendor_id, Vendor::new(shop, world.get_tick()))
+            .await?;
+    }
+    Ok(())
+}
+
+async fn block_position_of(
+    world: &ServerWorldRef,
+    entity_id: EntityId,
+) -> Result<Option<chunk::Position>, ServerWorldError> {
+    Ok(world
+        .get_optional_component::<Position>(entity_id)
+        .await?
+        .and_then(|p| p.block_position()))
+}
+
+/**
+ * The vendor and its shop if the actor is alive and standing close enough to it
+ */
+async fn reachable_vendor(
+    world: &ServerWorldRef,
+    actor: EntityId,
+    vendor_id: EntityId,
+) -> Result<Result<(Vendor, &Shop), ServerResponseType>, ServerWorldError> {
+    if world.has_component::<Dead>(actor).await? {
+        return Ok(Err(ServerResponseType::PermissionDenied {}));
+    }
+    let vendor = match world.get_optional_component::<Vendor>(vendor_id).await? {
+        Some(vendor) => vendor,
+        None => {
+            return Ok(Err(ServerResponseType::Error {
+                message: "not a vendor",
+            }))
+        }
+    };
+    let shop = match world.get_shop(&vendor.shop) {
+        Some(shop) => shop,
+        None => {
+            return Ok(Err(ServerResponseType::Error {
+                message: "not a vendor",
+            }))
+        }
+    };
+    let near = match (
+        block_position_of(world, actor).await?,
+        block_position_of(world, vendor_id).await?,
+    ) {
+        (Some(from), Some(to)) => chunk::chebyshev_distance(from, to) <= VENDOR_REACH,
+        _ => false,
+    };
+    if !near {
+        return Ok(Err(ServerResponseType::Error {
+            message: "vendor out of reach",
+        }));
+    }
+    Ok(Ok((vendor, shop)))
+}
+
+async fn wallet_of(
+    world: &ServerWorldRef,
+    entity_id: EntityId,
+) -> Result<Wallet, ServerWorldError> {
+    Ok(world
+        .get_optional_component::<Wallet>(entity_id)
+        .await?
+        .unwrap_or_default())
+}
+
+pub async fn buy(
+    world: &ServerWorldRef,
+    actor: EntityId,
+    vendor_id: EntityId,
+    item: &str,
+    count: u32,
+) -> Result<ServerResponseType, ServerWorldError> {
+    //the stock, wallet and inventory are read and written back, so nothing else may move them
+    //in between
+    let _economy = world.lock_economy().await;
+    let (mut vendor, shop) = match reachable_vendor(world, actor, vendor_id).await? {
+        Ok(found) => found,
+        Err(response) => return Ok(response),
+    };
+    let entry = match shop.get_entry(item) {
+        Some(entry) if count > 0 => entry,
+        _ => {
+            return Ok(ServerResponseType::Error {
+                message: "vendor doesn't sell that",
+            })
+        }
+    };
+    let cost = match entry.get_price().checked_mul(u64::from(count)) {
+        Some(cost) => cost,
+        None => {
+            return Ok(ServerResponseType::Error {
+                message: "too many to buy",
+            })
+        }
+    };
+    if !vendor.take(item, count) {
+        return Ok(ServerResponseType::Error {
+            message: "vendor is out of stock",
+        });
+    }
+    let mut wallet = wallet_of(world, actor).await?;
+    if !wallet.spend(cost) {
+        return Ok(ServerResponseType::Error {
+            message: "not enough coins",
+        });
+    }
+    let leftover = inventory::give_item(world, actor, item_type_id(item), count).await?;
+    if leftover == count {
+        return Ok(ServerResponseType::Error {
+            message: "inventory full",
+        });
+    }
+    //only pay for and take stock of what fit
+    wallet.coins += entry.get_price() * u64::from(leftover);
+    for (name, left) in vendor.stock.iter_mut() {
+        if name == item {
+            *left += leftover;
+        }
+    }
+    world.set_component(actor, wallet).await?;
+    world.set_component(vendor_id, vendor).await?;
+    Ok(ServerResponseType::Ok {})
+}
+
+pub async fn sell(
+    world: &ServerWorldRef,
+    actor: EntityId,
+    vendor_id: EntityId,
+    item_id: EntityId,
+) -> Result<ServerResponseType, ServerWorldError> {
+    let _economy = world.lock_economy().await;
+    let (_, shop) = match reachable_vendor(world, actor, vendor_id).await? {
+        Ok(found) => found,
+        Err(response) => return Ok(response),
+    };
+    let mut inventory = match world.get_optional_component::<Inventory>(actor).await? {
+        Some(inventory) if inventory.contains(item_id) => inventory,
+        _ => {
+            return Ok(ServerResponseType::Error {
+                message: "item not in inventory",
+            })
+        }
+    };
+    if world.is_item_in_trade(item_id).await {
+        return Ok(ServerResponseType::Error {
+            message: "item is being traded",
+        });
+    }
+    let item = match world.get_optional_component::<Item>(item_id).await? {
+        Some(item) => item,
+        None => {
+            return Ok(ServerResponseType::Error {
+                message: "not an item",
+            })
+        }
+    };
+    let price = match shop.get_buy_price(item.item_type_id) {
+        Some(price) => price,
+        None => {
+            return Ok(ServerResponseType::Error {
+                message: "vendor doesn't buy that",
+            })
+        }
+    };
+    let mut paid = price.saturating_mul(u64::from(item.count));
+    //worn items fetch less
+    if let Some(durability) = world.get_optional_component::<Durability>(item_id).await? {
+        paid = paid * u64::from(durability.current) / u64::from(durability.max.max(1));
+    }
+    inventory.remove(item_id);
+    world.set_component(actor, inventory).await?;
+    progression::unequip_if_equipped(world, actor, item_id).await?;
+    world.despawn_entity(item_id).await?;
+    let mut wallet = wallet_of(world, actor).await?;
+    wallet.coins = wallet.coins.saturating_add(paid);
+    world.set_component(actor, wallet).await?;
+    Ok(ServerResponseType::Ok {})
+}
+
+pub async fn repair(
+    world: &ServerWorldRef,
+    actor: EntityId,
+    vendor_id: EntityId,
+    item_id: EntityId,
+) -> Result<ServerResponseType, ServerWorldError> {
+    let _economy = world.lock_economy().await;
+    let (_, shop) = match reachable_vendor(world, actor, vendor_id).await? {
+        Ok(found) => found,
+        Err(response) => return Ok(response),
+    };
+    let cost_per_point = match shop.get_repair_cost() {
+        Some(cost) => cost,
+        None => {
+            return Ok(ServerResponseType::Error {
+                message: "vendor doesn't repair",
+            })
+        }
+    };
+    let in_inventory = world
+        .get_optional_component::<Inventory>(actor)
+        .await?
+        .is_some_and(|inventory| inventory.contains(item_id));
+    let mut durability = match world.get_optional_component::<Durability>(item_id).await? {
+        Some(durability) if in_inventory && durability.missing() > 0 => durability,
+        _ => {
+            return Ok(ServerResponseType::Error {
+                message: "nothing to repair",
+            })
+        }
+    };
+    let mut wallet = wallet_of(world, actor).await?;
+    if !wallet.spend(cost_per_point.saturating_mul(u64::from(durability.missing()))) {
+        return Ok(ServerResponseType::Error {
+            message: "not enough coins",
+        });
+    }
+    let was_broken = durability.is_broken();
+    durability.current = durability.max;
+    world.set_component(item_id, durability).await?;
+    world.set_component(actor, wallet).await?;
+    if was_broken {
+        progression::refresh_stats(world, actor).await?;
+    }
+    Ok(ServerResponseType::Ok {})
+}
+
+/**
+ * Bring vendors back to full stock once their restock time comes around
+ */
+pub async fn run(world: &ServerWorldRef) -> Result<(), ServerWorldError> {
+    let vendors = world
+        .get_entities_with_component_type_ids([mmolib::component::get_type_id::<Vendor>()])
+        .await?;
+    let tick = world.get_tick();
+    for vendor_id in vendors {
+        let vendor = match world.get_optional_component::<Vendor>(vendor_id).await? {
+            Some(vendor) if vendor.restock_tick <= tick => vendor,
+            _ => continue,
+        };
+        if let Some(shop) = world.get_shop(&vendor.shop) {
+            world
+                .set_component(vendor_id, Vendor::new(shop, tick))
+                .await?;
+        }
+    }
+    Ok(())
+}
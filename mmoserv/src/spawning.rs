@@ -0,0 +1,219 @@
+use mmolib::{
+    ai::{Ai, Creature, Npc},
+    chunk,
+    combat::{Dead, Health},
+    entity_id::EntityId,
+    loot::LootDrop,
+    position::Position,
+    stats::XpReward,
+};
+use rand::Rng;
+
+use crate::{
+    movement,
+    server_world::{ServerWorldError, ServerWorldRef},
+    weather,
+};
+
+//how often each online player gets a chance of something spawning near them
+pub const SPAWN_INTERVAL_TICKS: u64 = 100;
+//npcs turn up out of sight of the player but close enough to find
+pub const SPAWN_MIN_DISTANCE: u32 = 8;
+pub const SPAWN_MAX_DISTANCE: u32 = 16;
+
+/**
+ * Put a creature from the raws into the world
+ */
+pub async fn spawn_creature(
+    world: &ServerWorldRef,
+    creature: &Creature,
+    position: chunk::Position,
+) -> Result<EntityId, ServerWorldError> {
+    let entity_id = EntityId::new();
+    world
+        .set_component(entity_id, Position::from_block_position(position))
+        .await?;
+    world
+        .set_component(
+            entity_id,
+            Npc {
+                kind: creature.get_canonical_name().to_owned(),
+            },
+        )
+        .await?;
+    world
+        .set_component(entity_id, Ai::new(creature.get_ai()))
+        .await?;
+    world
+        .set_component(entity_id, Health::new(creature.get_health()))
+        .await?;
+    if let Some(table) = creature.get_loot() {
+        world
+            .set_component(
+                entity_id,
+                LootDrop {
+                    table: table.to_owned(),
+                },
+            )
+            .await?;
+    }
+    if creature.get_xp() > 0 {
+        world
+            .set_component(
+                entity_id,
+                XpReward {
+                    xp: creature.get_xp(),
+                },
+            )
+            .await?;
+    }
+    Ok(entity_id)
+}
+
+/**
+ * Maybe spawn something from the local spawn table at a random spot around a player
+ */
+async fn spawn_near(
+    world: &ServerWorldRef,
+    player: EntityId,
+    npcs: &mut Vec<chunk::Position>,
+) -> Result<(), ServerWorldError> {
+    if world.has_component::<Dead>(player).await? {
+        return Ok(());
+    }
+    let origin = match world
+        .get_optional_component::<Position>(player)
+        .await?
+        .and_then(|p| p.block_position())
+    {
+        Some(origin) => origin,
+        None => return Ok(()),
+    };
+    //the rng can't be held across an await
+    let (offset, roll) = {
+        let mut rng = rand::thread_rng();
+        let max = SPAWN_MAX_DISTANCE as i64;
+        let mut offset: (i64, i64) = (0, 0);
+        while offset.0.abs().max(offset.1.abs()) < SPAWN_MIN_DISTANCE as i64 {
+            offset = (rng.gen_range(-max..=max), rng.gen_range(-max..=max));
+        }
+        (offset, rng.gen::<f32>())
+    };
+    let position = match (
+        u32::try_from(i64::from(origin.0) + offset.0),
+        u32::try_from(i64::from(origin.1) + offset.1),
+    ) {
+        (Ok(x), Ok(y)) => (x, y),
+        _ => return Ok(()),
+    };
+    let table =
+        match weather::spawn_table_at(world, position).and_then(|t| world.get_spawn_table(t)) {
+            Some(table) => table,
+            None => return Ok(()),
+        };
+    let nearby = npcs
+        .iter()
+        .filter(|npc| chunk::chebyshev_distance(**npc, position) <= SPAWN_MAX_DISTANCE)
+        .count();
+    if nearby >= table.get_max_nearby()
+        || world.is_safe_zone(position)
+        || !movement::is_walkable(world, position).await?
+    {
+        return Ok(());
+    }
+    if let Some(creature) = table.choose(roll).and_then(|c| world.get_creature(c)) {
+        spawn_creature(world, creature, position).await?;
+        npcs.push(position);
+    }
+    Ok(())
+}
+
+/**
+ * Spawn npcs around online players from the spawn table of wherever they are, which changes at night
+ */
+pub async fn run(world: &ServerWorldRef) -> Result<(), ServerWorldError> {
+    if !world.get_tick().is_multiple_of(SPAWN_INTERVAL_TICKS) {
+        return Ok(());
+    }
+    let mut npcs = Vec::new();
+    for npc in world
+        .get_entities_with_component_type_ids([
+            mmolib::component::get_type_id::<Ai>(),
+            mmolib::component::get_type_id::<Position>(),
+        ])
+        .await?
+    {
+        if let Some(p) = world
+            .get_optional_component::<Position>(npc)
+            .await?
+            .and_then(|p| p.block_position())
+        {
+            npcs.push(p);
+        }
+    }
+    for player in world.get_online().await {
+        spawn_near(world, player, &mut npcs).await?;
+    }
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_spawn_near_players() -> Result<(), ServerWorldError> {
+    use mmolib::block_type::block_type_id;
+    let world = crate::server_world::test_world("spawning").await;
+    //clear out npcs from earlier runs so they don't count against the tables
+    for npc in world
+        .get_entities_with_component_type_ids([mmolib::component::get_type_id::<Ai>()])
+        .await?
+    {
+        world.despawn_entity(npc).await?;
+    }
+    world
+        .insert_chunk(
+            chunk::chunk_id_from_position((48, 48)),
+            chunk::Chunk::new_from_array(
+                [[block_type_id("dirt"); chunk::CHUNK_SIZE]; chunk::CHUNK_SIZE],
+            ),
+        )
+        .await?;
+    let player = EntityId::new();
+    world.set_component(player, Position::new(48, 48)).await?;
+    world.set_online(&[player]).await;
+    world.write_all_changes().await?;
+    //spawn spots are random, so give it plenty of chances to land on the chunk
+    let mut spawned = Vec::new();
+    for _ in 0..100 {
+        while !world.advance_tick().is_multiple_of(SPAWN_INTERVAL_TICKS) {}
+        run(&world).await?;
+        world.write_all_changes().await?;
+        spawned = world
+            .get_entities_with_component_type_ids([mmolib::component::get_type_id::<Ai>()])
+            .await?
+            .into_iter()
+            .collect();
+        if !spawned.is_empty() {
+            break;
+        }
+    }
+    assert!(!spawned.is_empty(), "nothing spawned near the player");
+    let npc = spawned[0];
+    //kills of it count towards quests
+    assert_eq!(
+        world
+            .get_optional_component::<Npc>(npc)
+            .await?
+            .unwrap()
+            .kind,
+        "goblin"
+    );
+    assert!(world.has_component::<Health>(npc).await?);
+    assert!(world.has_component::<LootDrop>(npc).await?);
+    let position = world
+        .get_optional_component::<Position>(npc)
+        .await?
+        .and_then(|p| p.block_position())
+        .unwrap();
+    let distance = chunk::chebyshev_distance(position, (48, 48));
+    assert!((SPAWN_MIN_DISTANCE..=SPAWN_MAX_DISTANCE).contains(&distance));
+    Ok(())
+}
@@ -0,0 +1,170 @@
+use mmolib::{
+    effect::{ActiveEffect, ActiveEffects, Effect, EffectImmunity},
+    entity_id::EntityId,
+    event::GameEvent,
+    position::Position,
+};
+
+use crate::{
+    combat, progression,
+    server_world::{ServerWorldError, ServerWorldRef},
+};
+
+async fn location_of(
+    world: &ServerWorldRef,
+    entity_id: EntityId,
+) -> Result<Option<mmolib::chunk::Position>, ServerWorldError> {
+    Ok(world
+        .get_optional_component::<Position>(entity_id)
+        .await?
+        .and_then(|p| p.block_position()))
+}
+
+/**
+ * Start an effect on an entity, following the stacking rule from the raws
+ */
+pub async fn apply_effect(
+    world: &ServerWorldRef,
+    target: EntityId,
+    effect: Effect,
+    magnitude: i32,
+    duration_ticks: u64,
+    source: Option<EntityId>,
+) -> Result<(), ServerWorldError> {
+    if let Some(immunity) = world.get_optional_component::<EffectImmunity>(target).await? {
+        if immunity.is_immune(effect) {
+            return Ok(());
+        }
+    }
+    let mut effects = world
+        .get_optional_component::<ActiveEffects>(target)
+        .await?
+        .unwrap_or_default();
+    let is_new = !effects.has(effect);
+    effects.apply(
+        ActiveEffect {
+            effect,
+            magnitude,
+            remaining_ticks: duration_ticks,
+            source,
+        },
+        world.get_effect_rule(effect).get_stacking(),
+    );
+    world.set_component(target, effects).await?;
+    progression::refresh_stats(world, target).await?;
+    if is_new {
+        world
+            .emit_event(
+                location_of(world, target).await?,
+                GameEvent::EffectApplied {
+                    entity: target,
+                    effect,
+                },
+            )
+            .await;
+    }
+    Ok(())
+}
+
+/**
+ * End an effect early, like rain putting out a fire
+ */
+pub async fn remove_effect(
+    world: &ServerWorldRef,
+    target: EntityId,
+    effect: Effect,
+) -> Result<(), ServerWorldError> {
+    let mut effects = match world.get_optional_component::<ActiveEffects>(target).await? {
+        Some(effects) if effects.has(effect) => effects,
+        _ => return Ok(()),
+    };
+    effects.effects.retain(|e| e.effect != effect);
+    if effects.effects.is_empty() {
+        world.remove_component::<ActiveEffects>(target).await?;
+    } else {
+        world.set_component(target, effects).await?;
+    }
+    world
+        .emit_event(
+            location_of(world, target).await?,
+            GameEvent::EffectExpired {
+                entity: target,
+                effect,
+            },
+        )
+        .await;
+    progression::refresh_stats(world, target).await
+}
+
+/**
+ * Per tick behaviour of a single effect instance
+ */
+async fn tick_effect(
+    world: &ServerWorldRef,
+    entity_id: EntityId,
+    active: &ActiveEffect,
+) -> Result<(), ServerWorldError> {
+    let rule = world.get_effect_rule(active.effect);
+    if !world.get_tick().is_multiple_of(rule.get_interval_ticks()) {
+        return Ok(());
+    }
+    match active.effect {
+        Effect::Poison | Effect::Fire => {
+            combat::apply_damage(world, active.source, entity_id, active.magnitude).await
+        }
+        //these modify stats while they last rather than doing something each tick
+        Effect::Haste | Effect::Strength | Effect::Stink => Ok(()),
+    }
+}
+
+/**
+ * Advance every active effect by one tick, expiring the ones that ran out
+ */
+pub async fn run(world: &ServerWorldRef) -> Result<(), ServerWorldError> {
+    let entities = world
+        .get_entities_with_component_type_ids([mmolib::component::get_type_id::<ActiveEffects>()])
+        .await?;
+    for entity_id in entities {
+        let effects = match world.get_optional_component::<ActiveEffects>(entity_id).await? {
+            Some(effects) => effects,
+            None => continue,
+        };
+        for active in &effects.effects {
+            tick_effect(world, entity_id, active).await?;
+        }
+        //ticking may have killed and despawned the entity
+        let mut effects = match world.get_optional_component::<ActiveEffects>(entity_id).await? {
+            Some(effects) => effects,
+            None => continue,
+        };
+        let before: Vec<Effect> = effects.effects.iter().map(|e| e.effect).collect();
+        for active in effects.effects.iter_mut() {
+            active.remaining_ticks = active.remaining_ticks.saturating_sub(1);
+        }
+        effects.effects.retain(|e| e.remaining_ticks > 0);
+        let expired = effects.effects.len() != before.len();
+        let location = location_of(world, entity_id).await?;
+        for effect in before {
+            if !effects.has(effect) {
+                world
+                    .emit_event(
+                        location,
+                        GameEvent::EffectExpired {
+                            entity: entity_id,
+                            effect,
+                        },
+                    )
+                    .await;
+            }
+        }
+        if effects.effects.is_empty() {
+            world.remove_component::<ActiveEffects>(entity_id).await?;
+        } else {
+            world.set_component(entity_id, effects).await?;
+        }
+        if expired {
+            progression::refresh_stats(world, entity_id).await?;
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,392 @@
+use mmolib::{
+    chunk,
+    combat::Dead,
+    currency::Wallet,
+    entity_id::EntityId,
+    event::GameEvent,
+    item::{Equipment, Inventory, Item},
+    player::Player,
+    position::Position,
+    server_response_type::ServerResponseType,
+    trade::{Trade, TradePhase, TradeSide},
+};
+
+use crate::server_world::{ServerWorldError, ServerWorldRef};
+
+//how far apart two players can be and still trade
+pub const TRADE_REACH: u32 = 4;
+//trades nobody touched for this long are called off
+pub const TRADE_TIMEOUT_TICKS: u64 = 2400;
+
+async fn block_position_of(
+    world: &ServerWorldRef,
+    entity_id: EntityId,
+) -> Result<Option<chunk::Position>, ServerWorldError> {
+    Ok(world
+        .get_optional_component::<Position>(entity_id)
+        .await?
+        .and_then(|p| p.block_position()))
+}
+
+/**
+ * Whether two players are alive and close enough to trade
+ */
+async fn can_trade(
+    world: &ServerWorldRef,
+    a: EntityId,
+    b: EntityId,
+) -> Result<bool, ServerWorldError> {
+    if world.has_component::<Dead>(a).await? || world.has_component::<Dead>(b).await? {
+        return Ok(false);
+    }
+    match (
+        block_position_of(world, a).await?,
+        block_position_of(world, b).await?,
+    ) {
+        (Some(a), Some(b)) => Ok(chunk::chebyshev_distance(a, b) <= TRADE_REACH),
+        _ => Ok(false),
+    }
+}
+
+async fn announce(world: &ServerWorldRef, trade: &Trade) -> Result<(), ServerWorldError> {
+    world
+        .emit_event(
+            block_position_of(world, trade.initiator.entity).await?,
+            GameEvent::TradeUpdated {
+                trade: trade.clone(),
+            },
+        )
+        .await;
+    Ok(())
+}
+
+async fn call_off(world: &ServerWorldRef, trade: &Trade) -> Result<(), ServerWorldError> {
+    world
+        .emit_event(
+            block_position_of(world, trade.initiator.entity).await?,
+            GameEvent::TradeCancelled {
+                initiator: trade.initiator.entity,
+                partner: trade.partner.entity,
+            },
+        )
+        .await;
+    Ok(())
+}
+
+pub async fn request(
+    world: &ServerWorldRef,
+    actor: EntityId,
+    target: EntityId,
+) -> Result<ServerResponseType, ServerWorldError> {
+    if actor == target || !world.has_component::<Player>(target).await? {
+        return Ok(ServerResponseType::Error {
+            message: "can only trade with other players",
+        });
+    }
+    if !can_trade(world, actor, target).await? {
+        return Ok(ServerResponseType::Error {
+            message: "too far away to trade",
+        });
+    }
+    if world.get_trade(actor).await.is_some() || world.get_trade(target).await.is_some() {
+        return Ok(ServerResponseType::Error {
+            message: "already trading",
+        });
+    }
+    let trade = Trade::new(actor, target, world.get_tick());
+    world.put_trade(trade.clone()).await;
+    announce(world, &trade).await?;
+    Ok(ServerResponseType::TradeStatus { trade })
+}
+
+pub async fn offer(
+    world: &ServerWorldRef,
+    actor: EntityId,
+    items: &[EntityId],
+    coins: u64,
+) -> Result<ServerResponseType, ServerWorldError> {
+    match world.get_trade(actor).await {
+        Some(trade) if trade.phase != TradePhase::Requested => {}
+        _ => {
+            return Ok(ServerResponseType::Error {
+                message: "not trading",
+            })
+        }
+    }
+    let inventory = world
+        .get_optional_component::<Inventory>(actor)
+        .await?
+        .unwrap_or_else(|| Inventory::new(0));
+    let equipment = world
+        .get_optional_component::<Equipment>(actor)
+        .await?
+        .unwrap_or_default();
+    let mut offered = Vec::new();
+    for item_id in items {
+        if !inventory.contains(*item_id)
+            || equipment.is_equipped(*item_id)
+            || offered.iter().any(|(i, _)| i == item_id)
+        {
+            return Ok(ServerResponseType::Error {
+                message: "item cannot be offered",
+            });
+        }
+        match world.get_optional_component::<Item>(*item_id).await? {
+            Some(item) => offered.push((*item_id, item.count)),
+            None => {
+                return Ok(ServerResponseType::Error {
+                    message: "not an item",
+                })
+            }
+        }
+    }
+    let wallet = world
+        .get_optional_component::<Wallet>(actor)
+        .await?
+        .unwrap_or_default();
+    if wallet.coins < coins {
+        return Ok(ServerResponseType::Error {
+            message: "not enough coins",
+        });
+    }
+    //changed under the lock so an accept can't agree to the offer as it was before
+    let trade = {
+        let mut trades = world.lock_trades().await;
+        let trade = match trades.iter_mut().find(|t| t.involves(actor)) {
+            Some(trade) if trade.phase != TradePhase::Requested => trade,
+            _ => {
+                return Ok(ServerResponseType::Error {
+                    message: "not trading",
+                })
+            }
+        };
+        if let Some(side) = trade.side_mut(actor) {
+            side.items = offered;
+            side.coins = coins;
+        }
+        trade.reset_agreement();
+        trade.last_change_tick = world.get_tick();
+        trade.clone()
+    };
+    announce(world, &trade).await?;
+    Ok(ServerResponseType::TradeStatus { trade })
+}
+
+/**
+ * Check one side can still hand over everything it offered, exactly as it was offered
+ */
+async fn still_has_offer(
+    world: &ServerWorldRef,
+    side: &TradeSide,
+) -> Result<bool, ServerWorldError> {
+    let inventory = match world.get_optional_component::<Inventory>(side.entity).await? {
+        Some(inventory) => inventory,
+        None => return Ok(side.items.is_empty() && side.coins == 0),
+    };
+    for (item_id, count) in &side.items {
+        let unchanged = inventory.contains(*item_id)
+            && world
+                .get_optional_component::<Item>(*item_id)
+                .await?
+                .is_some_and(|item| item.count == *count);
+        if !unchanged {
+            return Ok(false);
+        }
+    }
+    Ok(world
+        .get_optional_component::<Wallet>(side.entity)
+        .await?
+        .unwrap_or_default()
+        .coins
+        >= side.coins)
+}
+
+async fn has_room_for(
+    world: &ServerWorldRef,
+    receiver: &TradeSide,
+    giver: &TradeSide,
+) -> Result<bool, ServerWorldError> {
+    let inventory = world
+        .get_optional_component::<Inventory>(receiver.entity)
+        .await?
+        .unwrap_or_else(|| Inventory::new(0));
+    Ok(inventory.items.len() - receiver.items.len() + giver.items.len() <= inventory.capacity)
+}
+
+/**
+ * Swap the offers. Everything is checked before anything moves, and all the writes go out
+ * together at the end of the tick
+ */
+async fn execute(world: &ServerWorldRef, trade: &Trade) -> Result<bool, ServerWorldError> {
+    let (a, b) = (&trade.initiator, &trade.partner);
+    if !can_trade(world, a.entity, b.entity).await?
+        || !still_has_offer(world, a).await?
+        || !still_has_offer(world, b).await?
+        || !has_room_for(world, a, b).await?
+        || !has_room_for(world, b, a).await?
+    {
+        return Ok(false);
+    }
+    for (from, to) in [(a, b), (b, a)] {
+        let mut inventory = world
+            .get_optional_component::<Inventory>(to.entity)
+            .await?
+            .unwrap_or_else(|| Inventory::new(0));
+        inventory.items.retain(|i| !to.items.iter().any(|(o, _)| o == i));
+        inventory
+            .items
+            .extend(from.items.iter().map(|(item_id, _)| *item_id));
+        world.set_component(to.entity, inventory).await?;
+        let mut wallet = world
+            .get_optional_component::<Wallet>(to.entity)
+            .await?
+            .unwrap_or_default();
+        wallet.coins = wallet.coins - to.coins + from.coins;
+        world.set_component(to.entity, wallet).await?;
+    }
+    world
+        .emit_event(
+            block_position_of(world, a.entity).await?,
+            GameEvent::TradeCompleted {
+                initiator: a.entity,
+                partner: b.entity,
+            },
+        )
+        .await;
+    Ok(true)
+}
+
+pub async fn accept(
+    world: &ServerWorldRef,
+    actor: EntityId,
+) -> Result<ServerResponseType, ServerWorldError> {
+    //the trades stay locked from agreeing until the swap is queued, so neither the offers
+    //nor the items in them can change in between, and the economy lock keeps shops and
+    //pickups off the inventories and wallets being swapped
+    let _economy = world.lock_economy().await;
+    let mut trades = world.lock_trades().await;
+    let index = match trades.iter().position(|t| t.involves(actor)) {
+        Some(index) => index,
+        None => {
+            return Ok(ServerResponseType::Error {
+                message: "not trading",
+            })
+        }
+    };
+    let trade = &mut trades[index];
+    if trade.phase == TradePhase::Requested {
+        if trade.partner.entity != actor {
+            return Ok(ServerResponseType::Error {
+                message: "waiting for the other player",
+            });
+        }
+        trade.phase = TradePhase::Negotiating;
+    } else if trade.agree(actor) {
+        //taking the trade out means a second accept can't run it twice
+        let trade = trades.remove(index);
+        if execute(world, &trade).await? {
+            return Ok(ServerResponseType::Ok {});
+        }
+        drop(trades);
+        call_off(world, &trade).await?;
+        return Ok(ServerResponseType::Error {
+            message: "trade could not be completed",
+        });
+    }
+    trade.last_change_tick = world.get_tick();
+    let trade = trade.clone();
+    drop(trades);
+    announce(world, &trade).await?;
+    Ok(ServerResponseType::TradeStatus { trade })
+}
+
+pub async fn cancel(
+    world: &ServerWorldRef,
+    actor: EntityId,
+) -> Result<ServerResponseType, ServerWorldError> {
+    match world.take_trade(actor).await {
+        Some(trade) => {
+            call_off(world, &trade).await?;
+            Ok(ServerResponseType::Ok {})
+        }
+        None => Ok(ServerResponseType::Error {
+            message: "not trading",
+        }),
+    }
+}
+
+/**
+ * Call off trades that went stale or whose players died or walked away
+ */
+pub async fn run(world: &ServerWorldRef) -> Result<(), ServerWorldError> {
+    for trade in world.get_trades().await {
+        let stale = trade.last_change_tick + TRADE_TIMEOUT_TICKS <= world.get_tick();
+        if stale || !can_trade(world, trade.initiator.entity, trade.partner.entity).await? {
+            if let Some(trade) = world.take_trade(trade.initiator.entity).await {
+                call_off(world, &trade).await?;
+            }
+        }
+    }
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_trade() -> Result<(), ServerWorldError> {
+    use crate::inventory;
+    use mmolib::item::item_type_id;
+    let world = crate::server_world::test_world("trading").await;
+    let a = EntityId::new();
+    let b = EntityId::new();
+    for (entity_id, username) in [(a, "alice"), (b, "bob")] {
+        world.set_component(entity_id, Position::new(3, 3)).await?;
+        world.set_component(entity_id, Inventory::new(10)).await?;
+        world
+            .set_component(
+                entity_id,
+                Player {
+                    username: username.to_owned(),
+                },
+            )
+            .await?;
+    }
+    world.set_component(b, Wallet { coins: 10 }).await?;
+    inventory::give_item(&world, a, item_type_id("rock"), 2).await?;
+    let rock = world
+        .get_optional_component::<Inventory>(a)
+        .await?
+        .unwrap()
+        .items[0];
+    assert!(matches!(
+        request(&world, a, b).await?,
+        ServerResponseType::TradeStatus { .. }
+    ));
+    assert!(matches!(
+        accept(&world, a).await?,
+        ServerResponseType::Error {
+            message: "waiting for the other player"
+        }
+    ));
+    accept(&world, b).await?;
+    offer(&world, a, &[rock], 0).await?;
+    offer(&world, b, &[], 10).await?;
+    accept(&world, a).await?;
+    //changing an offer means agreeing again
+    offer(&world, b, &[], 5).await?;
+    let trade = world.get_trade(a).await.unwrap();
+    assert!(!trade.initiator.ready && !trade.partner.ready);
+    assert!(world.is_item_in_trade(rock).await);
+    for actor in [a, b, a] {
+        assert!(matches!(
+            accept(&world, actor).await?,
+            ServerResponseType::TradeStatus { .. }
+        ));
+    }
+    assert_eq!(world.get_trade(a).await.unwrap().phase, TradePhase::Locked);
+    assert!(matches!(accept(&world, b).await?, ServerResponseType::Ok {}));
+    assert!(world.get_trade(a).await.is_none());
+    assert_eq!(inventory::count_items(&world, a, item_type_id("rock")).await?, 0);
+    assert_eq!(inventory::count_items(&world, b, item_type_id("rock")).await?, 2);
+    assert_eq!(world.get_optional_component::<Wallet>(a).await?.unwrap().coins, 5);
+    assert_eq!(world.get_optional_component::<Wallet>(b).await?.unwrap().coins, 5);
+    Ok(())
+}
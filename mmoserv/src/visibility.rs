@@ -0,0 +1,234 @@
+use hashbrown::HashSet;
+use mmolib::{
+    block_type::BlockLayer,
+    chunk::{self, Position},
+    entity_id::EntityId,
+};
+
+use crate::server_world::{AreaSnapshot, ServerWorld, ServerWorldError};
+
+//how far an entity can see in blocks
+pub const VIEW_RADIUS: u32 = 16;
+
+//transforms from octant 0 to each of the eight octants
+const OCTANTS: [(i64, i64, i64, i64); 8] = [
+    (1, 0, 0, 1),
+    (0, 1, 1, 0),
+    (0, -1, 1, 0),
+    (-1, 0, 0, 1),
+    (-1, 0, 0, -1),
+    (0, -1, -1, 0),
+    (0, 1, -1, 0),
+    (1, 0, 0, -1),
+];
+
+fn offset_position(origin: Position, dx: i64, dy: i64) -> Option<Position> {
+    Some((
+        u32::try_from(i64::from(origin.0) + dx).ok()?,
+        u32::try_from(i64::from(origin.1) + dy).ok()?,
+    ))
+}
+
+/**
+ * Blocks occlude sight when they are solid or we know nothing about them
+ */
+pub fn is_opaque(snapshot: &AreaSnapshot, position: Position) -> bool {
+    match snapshot.get_block_type(position) {
+        Some(block_type) => matches!(block_type.get_layer(), BlockLayer::Solid),
+        None => true,
+    }
+}
+
+/**
+ * What stays fixed while shadowcasting from one origin
+ */
+struct Shadowcast<'a, F: Fn(Position) -> bool> {
+    origin: Position,
+    radius: u32,
+    is_opaque: &'a F,
+    visible: &'a mut HashSet<Position>,
+}
+
+impl<F: Fn(Position) -> bool> Shadowcast<'_, F> {
+    /**
+     * Recursive shadowcasting of a single octant
+     */
+    fn cast_light(
+        &mut self,
+        row: u32,
+        mut start_slope: f64,
+        end_slope: f64,
+        transform: (i64, i64, i64, i64),
+    ) {
+        if start_slope < end_slope {
+            return;
+        }
+        let (xx, xy, yx, yy) = transform;
+        let radius_squared = i64::from(self.radius) * i64::from(self.radius);
+        let mut next_start_slope = start_slope;
+        for distance in row..=self.radius {
+            let dy = -i64::from(distance);
+            let mut blocked = false;
+            for dx in -i64::from(distance)..=0 {
+                let left_slope = (dx as f64 - 0.5) / (dy as f64 + 0.5);
+                let right_slope = (dx as f64 + 0.5) / (dy as f64 - 0.5);
+                if start_slope < right_slope {
+                    continue;
+                } else if end_slope > left_slope {
+                    break;
+                }
+                let position = offset_position(self.origin, dx * xx + dy * xy, dx * yx + dy * yy);
+                if dx * dx + dy * dy <= radius_squared {
+                    if let Some(p) = position {
+                        self.visible.insert(p);
+                    }
+                }
+                let opaque = position.is_none_or(self.is_opaque);
+                if blocked {
+                    if opaque {
+                        next_start_slope = right_slope;
+                    } else {
+                        blocked = false;
+                        start_slope = next_start_slope;
+                    }
+                } else if opaque && distance < self.radius {
+                    blocked = true;
+                    self.cast_light(distance + 1, start_slope, left_slope, transform);
+                    next_start_slope = right_slope;
+                }
+            }
+            if blocked {
+                break;
+            }
+        }
+    }
+}
+
+/**
+ * Every block visible from origin within radius
+ */
+pub fn compute_fov_with(
+    origin: Position,
+    radius: u32,
+    is_opaque: impl Fn(Position) -> bool,
+) -> HashSet<Position> {
+    let mut visible = HashSet::new();
+    visible.insert(origin);
+    let mut shadowcast = Shadowcast {
+        origin,
+        radius,
+        is_opaque: &is_opaque,
+        visible: &mut visible,
+    };
+    for transform in OCTANTS {
+        shadowcast.cast_light(1, 1.0, 0.0, transform);
+    }
+    visible
+}
+
+/**
+ * Whether nothing opaque lies strictly between two blocks
+ */
+pub fn line_of_sight_with(a: Position, b: Position, is_opaque: impl Fn(Position) -> bool) -> bool {
+    //nothing lies between a block and itself
+    if a == b {
+        return true;
+    }
+    //bresenham walk from a to b
+    let (mut x, mut y) = (i64::from(a.0), i64::from(a.1));
+    let (x1, y1) = (i64::from(b.0), i64::from(b.1));
+    let dx = (x1 - x).abs();
+    let dy = -(y1 - y).abs();
+    let sx = if x < x1 { 1 } else { -1 };
+    let sy = if y < y1 { 1 } else { -1 };
+    let mut err = dx + dy;
+    loop {
+        let e2 = 2 * err;
+        if e2 >= dy {
+            err += dy;
+            x += sx;
+        }
+        if e2 <= dx {
+            err += dx;
+            y += sy;
+        }
+        if (x, y) == (x1, y1) {
+            return true;
+        }
+        if is_opaque((x as u32, y as u32)) {
+            return false;
+        }
+    }
+}
+
+pub struct FieldOfView {
+    origin: Position,
+    visible: HashSet<Position>,
+}
+
+impl FieldOfView {
+    pub async fn compute(
+        world: &ServerWorld,
+        origin: Position,
+        radius: u32,
+    ) -> Result<Self, ServerWorldError> {
+        let snapshot = world
+            .snapshot_area(chunk::area_around(origin, origin, radius))
+            .await?;
+        Ok(FieldOfView {
+            origin,
+            visible: compute_fov_with(origin, radius, |p| is_opaque(&snapshot, p)),
+        })
+    }
+    pub fn get_origin(&self) -> Position {
+        self.origin
+    }
+    pub fn can_see(&self, position: Position) -> bool {
+        self.visible.contains(&position)
+    }
+    /**
+     * Whether the entity stands on a visible block. Entities without a position are never visible
+     */
+    pub async fn can_see_entity(
+        &self,
+        world: &ServerWorld,
+        entity_id: EntityId,
+    ) -> Result<bool, ServerWorldError> {
+        match world
+            .get_component_ref::<mmolib::position::Position>(entity_id)
+            .await
+        {
+            Ok(position) => Ok(position.block_position().is_some_and(|p| self.can_see(p))),
+            Err(ServerWorldError::ComponentNotFound) => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+pub async fn has_line_of_sight(
+    world: &ServerWorld,
+    a: Position,
+    b: Position,
+) -> Result<bool, ServerWorldError> {
+    let snapshot = world.snapshot_area(chunk::area_around(a, b, 0)).await?;
+    Ok(line_of_sight_with(a, b, |p| is_opaque(&snapshot, p)))
+}
+
+#[test]
+fn test_fov_and_line_of_sight() {
+    //a wall along x = 5
+    let wall = |p: Position| p.0 == 5;
+    let visible = compute_fov_with((2, 10), 8, wall);
+    assert!(visible.contains(&(4, 10)));
+    assert!(visible.contains(&(5, 10)));
+    assert!(!visible.contains(&(7, 10)));
+    assert!(line_of_sight_with((2, 10), (4, 3), wall));
+    assert!(!line_of_sight_with((2, 10), (7, 10), wall));
+    assert!(line_of_sight_with((2, 10), (5, 10), wall));
+}
+
+#[test]
+fn test_line_of_sight_to_own_block() {
+    assert!(line_of_sight_with((3, 3), (3, 3), |_| true));
+    assert!(line_of_sight_with((0, 0), (0, 0), |_| false));
+}
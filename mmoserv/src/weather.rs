@@ -0,0 +1,141 @@
+use mmolib::{
+    chunk,
+    combat::{Dead, Health},
+    entity_id::EntityId,
+    position::Position,
+    world_time::{
+        choose_weather, world_entity, DayPhase, RegionWeather, Weather, WeatherState, WorldTime,
+        WEATHER_CHANGE_TICKS,
+    },
+};
+use rand::Rng;
+
+use crate::{
+    server_world::{ServerWorldError, ServerWorldRef},
+    status_effects,
+};
+
+//how often the weather acts on entities out in it
+const EXPOSURE_INTERVAL_TICKS: u64 = 20;
+
+async fn update_clock(world: &ServerWorldRef) -> Result<(), ServerWorldError> {
+    let time = WorldTime::at(world.get_tick());
+    if world
+        .get_optional_component::<WorldTime>(world_entity())
+        .await?
+        .as_ref()
+        != Some(&time)
+    {
+        world.set_component(world_entity(), time).await?;
+    }
+    Ok(())
+}
+
+async fn update_weather(world: &ServerWorldRef) -> Result<(), ServerWorldError> {
+    let due = world.get_tick().is_multiple_of(WEATHER_CHANGE_TICKS)
+        || !world.has_component::<Weather>(world_entity()).await?;
+    if !due {
+        return Ok(());
+    }
+    let regions = {
+        let mut rng = rand::thread_rng();
+        world
+            .get_regions()
+            .iter()
+            .map(|region| RegionWeather {
+                region: region.get_canonical_name().to_owned(),
+                state: choose_weather(region.get_attributes(), rng.gen(), rng.gen()),
+            })
+            .collect()
+    };
+    world
+        .set_component(world_entity(), Weather { regions })
+        .await
+}
+
+/**
+ * The weather over a block, clear outside of any region
+ */
+pub async fn weather_at(
+    world: &ServerWorldRef,
+    position: chunk::Position,
+) -> Result<WeatherState, ServerWorldError> {
+    let region = match world.get_region_at(position) {
+        Some(region) => region,
+        None => return Ok(WeatherState::Clear),
+    };
+    Ok(world
+        .get_optional_component::<Weather>(world_entity())
+        .await?
+        .map_or(WeatherState::Clear, |w| w.get(region.get_canonical_name())))
+}
+
+/**
+ * Step time at a block as a percentage of normal
+ */
+pub async fn movement_percent_at(
+    world: &ServerWorldRef,
+    position: chunk::Position,
+) -> Result<u64, ServerWorldError> {
+    let weather = weather_at(world, position).await?;
+    Ok(world
+        .get_weather_rule(weather)
+        .map_or(100, |rule| rule.get_movement_percent()))
+}
+
+/**
+ * The spawn table that should be used at a block right now, for anything that spawns npcs
+ */
+pub fn spawn_table_at(world: &ServerWorldRef, position: chunk::Position) -> Option<&str> {
+    world
+        .get_region_at(position)?
+        .get_spawn_table(DayPhase::at(world.get_tick()))
+}
+
+async fn expose(world: &ServerWorldRef, entity_id: EntityId) -> Result<(), ServerWorldError> {
+    //the dead are out of the weather until they respawn
+    if world.has_component::<Dead>(entity_id).await? {
+        return Ok(());
+    }
+    let position = match world
+        .get_optional_component::<Position>(entity_id)
+        .await?
+        .and_then(|p| p.block_position())
+    {
+        Some(position) => position,
+        None => return Ok(()),
+    };
+    let rule = match world.get_weather_rule(weather_at(world, position).await?) {
+        Some(rule) => rule,
+        None => return Ok(()),
+    };
+    for effect in rule.get_removes() {
+        status_effects::remove_effect(world, entity_id, *effect).await?;
+    }
+    if let Some((effect, magnitude, duration_ticks)) = rule.get_applies() {
+        status_effects::apply_effect(world, entity_id, effect, magnitude, duration_ticks, None)
+            .await?;
+    }
+    Ok(())
+}
+
+/**
+ * Advance the clock, roll new weather when it's due and let the weather act on everything out in it
+ */
+pub async fn run(world: &ServerWorldRef) -> Result<(), ServerWorldError> {
+    update_clock(world).await?;
+    update_weather(world).await?;
+    if !world.get_tick().is_multiple_of(EXPOSURE_INTERVAL_TICKS) {
+        return Ok(());
+    }
+    let entities = world
+        .get_entities_with_component_type_ids([
+            mmolib::component::get_type_id::<Health>(),
+            mmolib::component::get_type_id::<Position>(),
+        ])
+        .await?;
+    for entity_id in entities {
+        expose(world, entity_id).await?;
+    }
+    Ok(())
+}